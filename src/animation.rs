@@ -0,0 +1,194 @@
+//! This module defines keyframed animation of Objects.
+use crate::image::*;
+use crate::object::*;
+use crate::parse::Error;
+use crate::util::*;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// Easing curve of the transition from a keyframe to the next.
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Holds the value until the next keyframe.
+    Step,
+    /// A CSS style cubic Bézier curve through (0, 0), (x1, y1), (x2, y2) and (1, 1).
+    CubicBezier(f64, f64, f64, f64),
+}
+impl Easing {
+    /// Returns the Easing named `linear`, `step`, `ease`, `ease-in`, `ease-out` or `ease-in-out`.
+    pub fn from_name(name: &str) -> Option<Easing> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "step" => Some(Easing::Step),
+            "ease" => Some(Easing::CubicBezier(0.25, 0.1, 0.25, 1.0)),
+            "ease-in" => Some(Easing::CubicBezier(0.42, 0.0, 1.0, 1.0)),
+            "ease-out" => Some(Easing::CubicBezier(0.0, 0.0, 0.58, 1.0)),
+            "ease-in-out" => Some(Easing::CubicBezier(0.42, 0.0, 0.58, 1.0)),
+            _ => None,
+        }
+    }
+    /// Maps the progress t, between 0 and 1, through the curve.
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let bezier = |a: f64, b: f64, s: f64| {
+                    3.0 * a * s * (1.0 - s).powi(2) + 3.0 * b * s.powi(2) * (1.0 - s) + s.powi(3)
+                };
+                // x is monotonic in s for x1, x2 in [0, 1], so bisect for x(s) = t.
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..32 {
+                    let mid = (low + high) / 2.0;
+                    if bezier(x1, x2, mid) < t {
+                        low = mid
+                    } else {
+                        high = mid
+                    }
+                }
+                bezier(y1, y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+/// Linear interpolation between two values.
+pub trait Lerp {
+    /// Interpolates from self to other by t, between 0 and 1.
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+impl Lerp for (f64, f64) {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+}
+impl Lerp for [u8; 4] {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        let mut color = [0; 4];
+        for i in 0..4 {
+            color[i] = (self[i] as f64).lerp(&(other[i] as f64), t).round() as u8;
+        }
+        color
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Keyframed values of a single property.
+pub struct Track<T> {
+    keyframes: Vec<(usize, T, Easing)>,
+}
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> Track<T> {
+    /// Creates an empty Track.
+    pub fn new() -> Track<T> {
+        Track {
+            keyframes: Vec::new(),
+        }
+    }
+    /// Returns true if the Track has no keyframes.
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+    /// Inserts a keyframe, replacing any at the same frame.
+    ///
+    /// The easing applies to the transition to the next keyframe.
+    pub fn insert(&mut self, frame: usize, value: T, easing: Easing) {
+        match self.keyframes.binary_search_by_key(&frame, |(f, _, _)| *f) {
+            Ok(idx) => self.keyframes[idx] = (frame, value, easing),
+            Err(idx) => self.keyframes.insert(idx, (frame, value, easing)),
+        }
+    }
+}
+impl<T: Lerp + Clone> Track<T> {
+    /// Returns the value at frame, holding the first and last keyframes outside their range.
+    pub fn at(&self, frame: usize) -> Option<T> {
+        let next = self.keyframes.iter().position(|(f, _, _)| *f > frame);
+        match next {
+            None => self.keyframes.last().map(|(_, value, _)| value.clone()),
+            Some(0) => Some(self.keyframes[0].1.clone()),
+            Some(idx) => {
+                let (start, from, easing) = &self.keyframes[idx - 1];
+                let (end, to, _) = &self.keyframes[idx];
+                let t = (frame - start) as f64 / (end - start) as f64;
+                Some(from.lerp(to, easing.apply(t)))
+            }
+        }
+    }
+}
+
+/// An [Object] with animated [Properties].
+pub struct Animated {
+    object: Box<dyn Object>,
+    /// Properties when no keyframe applies.
+    pub properties: Properties,
+    /// Origin keyframes, as percentages of width and height of Image.
+    pub origin: Track<(f64, f64)>,
+    /// Opacity keyframes.
+    pub opacity: Track<f64>,
+    /// Scale keyframes.
+    pub scale: Track<f64>,
+    /// Rotation keyframes.
+    pub rotation: Track<f64>,
+    /// Color keyframes.
+    pub color: Track<[u8; 4]>,
+}
+impl Animated {
+    /// Wraps an [Object] for animation.
+    pub fn new(object: Box<dyn Object>) -> Animated {
+        Animated {
+            object,
+            properties: Properties::new(),
+            origin: Track::new(),
+            opacity: Track::new(),
+            scale: Track::new(),
+            rotation: Track::new(),
+            color: Track::new(),
+        }
+    }
+    /// Returns the [Properties] at frame of a [Canvas].
+    pub fn properties_at(&self, canvas: &Canvas, frame: usize) -> Properties {
+        let mut properties = self.properties.clone();
+        if let Some(origin) = self.origin.at(frame) {
+            properties.origin = Some(canvas.point(origin));
+        }
+        if let Some(opacity) = self.opacity.at(frame) {
+            properties.opacity = opacity;
+        }
+        if let Some(scale) = self.scale.at(frame) {
+            properties.scale = scale;
+        }
+        if let Some(rotation) = self.rotation.at(frame) {
+            properties.rotation = rotation;
+        }
+        if let Some(color) = self.color.at(frame) {
+            properties.color = Some(Pixel::from(&color));
+        }
+        properties
+    }
+}
+impl Object for Animated {
+    fn draw_with(&mut self, canvas: &mut Canvas, _properties: &Properties) {
+        let properties = self.properties_at(canvas, canvas.frame);
+        self.object.draw_with(canvas, &properties)
+    }
+    fn resize(&mut self, scale: [usize; 2]) -> Result<(), Error> {
+        self.object.resize(scale)
+    }
+}
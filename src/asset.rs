@@ -1,15 +1,21 @@
 //! This module defines assets loadable by the generator.
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs::{metadata, read};
+use std::path::{Path as FilePath, PathBuf};
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use svg::node::element::tag::Path;
 use svg::parser::Event;
 #[allow(unused_imports)]
 use swash::zeno::{Mask, PathData};
+use swash::FontRef;
 
 //use crate::object::*;
 use crate::image::*;
+use crate::parse::Error;
 
 /// An Asset.
 pub trait Asset {
@@ -46,12 +52,9 @@ impl Svg {
         let mut content = String::new();
         let mut data = String::new();
         for event in svg::open(&src, &mut content).unwrap() {
-            match event {
-                Event::Tag(Path, _, attributes) => {
-                    data.push_str(attributes.get("d").unwrap());
-                    data.push(' ');
-                },
-                _ => {}
+            if let Event::Tag(Path, _, attributes) = event {
+                data.push_str(attributes.get("d").unwrap());
+                data.push(' ');
             }
         }
         
@@ -67,11 +70,127 @@ impl Svg {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 /// Font Asset.
 pub struct Font {
     id: String,
     src: PathBuf,
+    data: Arc<Vec<u8>>,
+}
+impl Font {
+    /// Creates a Font from the contents of its file at src, failing if they are not a font.
+    pub fn new(src: PathBuf, data: Vec<u8>) -> Result<Font, Error> {
+        if !Font::is_font(&data) {
+            return Err(Error::Invalid(format!("{} is not a font", src.display())));
+        }
+        let id = match src.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => "font".to_string(),
+        };
+        Ok(Font {
+            id,
+            src,
+            data: Arc::new(data),
+        })
+    }
+    /// Returns true if data is the contents of a font file.
+    pub fn is_font(data: &[u8]) -> bool {
+        FontRef::from_index(data, 0).is_some()
+    }
+    /// Loads Font from file.
+    pub fn load(src: PathBuf) -> Result<Font, Error> {
+        let data = read(&src).map_err(|err| Error::Io(src.clone(), err))?;
+        Font::new(src, data)
+    }
+    /// Returns the name of the Font, the stem of its file name.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// Returns the path of the Font file.
+    pub fn src(&self) -> &FilePath {
+        &self.src
+    }
+    /// Returns the contents of the Font file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// Returns a reference to the Font for shaping and scaling.
+    pub fn font_ref(&self) -> FontRef<'_> {
+        FontRef::from_index(&self.data, 0).unwrap()
+    }
+}
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("id", &self.id)
+            .field("src", &self.src)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Key of a cached Image: its source file, frame and size it was resized to.
+type ImageKey = (PathBuf, Option<usize>, Option<[usize; 2]>);
+
+#[derive(Default)]
+/// Cache of decoded and resized Images and of loaded Fonts.
+///
+/// Entries are reused while the modification time of their source file is unchanged,
+/// so re-rendering a Manifest only reprocesses the files which changed.
+pub struct AssetCache {
+    images: HashMap<ImageKey, (Option<SystemTime>, Image)>,
+    fonts: HashMap<PathBuf, (Option<SystemTime>, Font)>,
+    loads: usize,
+}
+impl AssetCache {
+    /// Creates an empty cache.
+    pub fn new() -> AssetCache {
+        AssetCache::default()
+    }
+    /// Returns the number of Images and Fonts loaded because they were not cached.
+    pub fn loads(&self) -> usize {
+        self.loads
+    }
+    /// Returns the Image decoded from file, loading it if file changed since it was cached.
+    /// Errors of loading are returned and not cached.
+    ///
+    /// The frame and size the Image is resized to are part of the key.
+    pub fn image(
+        &mut self,
+        file: &FilePath,
+        frame: Option<usize>,
+        size: Option<[usize; 2]>,
+        load: impl FnOnce() -> Result<Image, Error>,
+    ) -> Result<Image, Error> {
+        let modified = modified(file);
+        let key = (file.to_path_buf(), frame, size);
+        match self.images.get(&key) {
+            Some((cached, image)) if *cached == modified && modified.is_some() => Ok(image.clone()),
+            _ => {
+                self.loads += 1;
+                let image = load()?;
+                self.images.insert(key, (modified, image.clone()));
+                Ok(image)
+            }
+        }
+    }
+    /// Returns the Font of file, loading it if file changed since it was cached.
+    pub fn font(&mut self, file: &FilePath, load: impl FnOnce() -> Result<Font, Error>) -> Result<Font, Error> {
+        let modified = modified(file);
+        match self.fonts.get(file) {
+            Some((cached, font)) if *cached == modified && modified.is_some() => Ok(font.clone()),
+            _ => {
+                self.loads += 1;
+                let font = load()?;
+                self.fonts.insert(file.to_path_buf(), (modified, font.clone()));
+                Ok(font)
+            }
+        }
+    }
+}
+
+/// Returns the modification time of file, if it can be read.
+pub fn modified(file: &FilePath) -> Option<SystemTime> {
+    metadata(file).and_then(|metadata| metadata.modified()).ok()
 }
 
 #[allow(dead_code)]
@@ -82,6 +201,11 @@ pub struct Assets {
     images: HashMap<String, Image>,
     svgs: HashMap<String, Svg>,
 }
+impl Default for Assets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl Assets {
     /// Returns true if the map contains no elements.
     pub fn is_empty(&self) -> bool {
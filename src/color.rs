@@ -0,0 +1,344 @@
+//! This module defines the parsing of Colors written in a Manifest.
+//!
+//! A Color is an `[r, g, b, a]` array, or a string in one of the CSS notations
+//! `#rrggbb[aa]`, `#rgb[a]`, a CSS color name, `rgb()`, `hsl()` or `oklch()`.
+use std::fmt;
+
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+
+/// Parses a Color written as a string.
+///
+/// Returns the message of the error if the string is not a Color.
+pub fn parse_color(text: &str) -> Result<[u8; 4], String> {
+    let text = text.trim();
+    let lower = text.to_ascii_lowercase();
+    let invalid = || format!("invalid color {text}");
+    if let Some(hex) = lower.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(invalid);
+    }
+    if let Some((name, args)) = lower.strip_suffix(')').and_then(|text| text.split_once('(')) {
+        let args: Vec<&str> = args.split([',', '/', ' ']).filter(|arg| !arg.is_empty()).collect();
+        let (channels, alpha) = match args.len() {
+            3 => (&args[..], None),
+            4 => (&args[..3], Some(args[3])),
+            _ => return Err(invalid()),
+        };
+        let alpha = match alpha {
+            Some(alpha) => fraction(alpha, 1.0).ok_or_else(invalid)?,
+            None => 1.0,
+        };
+        let rgb = match name.trim() {
+            "rgb" | "rgba" => {
+                let mut rgb = [0.0; 3];
+                for (value, channel) in rgb.iter_mut().zip(channels) {
+                    *value = fraction(channel, 255.0).ok_or_else(invalid)?;
+                }
+                rgb
+            }
+            "hsl" | "hsla" => {
+                let hue = hue(channels[0]).ok_or_else(invalid)?;
+                let saturation = fraction(channels[1], 100.0).ok_or_else(invalid)?;
+                let lightness = fraction(channels[2], 100.0).ok_or_else(invalid)?;
+                hsl_to_rgb(hue, saturation, lightness)
+            }
+            "oklch" => {
+                let lightness = fraction(channels[0], 1.0).ok_or_else(invalid)?;
+                let chroma = match channels[1].strip_suffix('%') {
+                    Some(percent) => percent.parse::<f64>().ok().map(|percent| percent / 100.0 * 0.4),
+                    None => channels[1].parse::<f64>().ok(),
+                }
+                .ok_or_else(invalid)?;
+                let hue = hue(channels[2]).ok_or_else(invalid)?;
+                oklch_to_rgb(lightness, chroma, hue)
+            }
+            _ => return Err(invalid()),
+        };
+        let byte = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        return Ok([byte(rgb[0]), byte(rgb[1]), byte(rgb[2]), byte(alpha)]);
+    }
+    match NAMES.binary_search_by_key(&lower.as_str(), |(name, _)| name) {
+        Ok(idx) => {
+            let [r, g, b] = NAMES[idx].1;
+            Ok([r, g, b, 255])
+        }
+        Err(_) if lower == "transparent" => Ok([0, 0, 0, 0]),
+        Err(_) => Err(invalid()),
+    }
+}
+
+/// Parses the digits of a hex Color, with or without alpha, in long or short form.
+fn parse_hex(hex: &str) -> Option<[u8; 4]> {
+    let digits = hex
+        .chars()
+        .map(|digit| digit.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let mut color = [255; 4];
+    match digits.len() {
+        3 | 4 => {
+            for (value, digit) in color.iter_mut().zip(&digits) {
+                *value = digit * 17;
+            }
+        }
+        6 | 8 => {
+            for (value, digits) in color.iter_mut().zip(digits.chunks(2)) {
+                *value = digits[0] * 16 + digits[1];
+            }
+        }
+        _ => return None,
+    }
+    Some(color)
+}
+
+/// Parses a number or a percentage as a fraction, numbers being fractions of max.
+fn fraction(text: &str, max: f64) -> Option<f64> {
+    match text.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().ok().map(|percent| percent / 100.0),
+        None => text.parse::<f64>().ok().map(|value| value / max),
+    }
+}
+
+/// Parses a hue in degrees, or in `deg`, `rad` or `turn` units.
+fn hue(text: &str) -> Option<f64> {
+    let (value, degrees) = if let Some(value) = text.strip_suffix("deg") {
+        (value, 1.0)
+    } else if let Some(value) = text.strip_suffix("rad") {
+        (value, 180.0 / std::f64::consts::PI)
+    } else if let Some(value) = text.strip_suffix("turn") {
+        (value, 360.0)
+    } else {
+        (text, 1.0)
+    };
+    value.parse::<f64>().ok().map(|value| (value * degrees).rem_euclid(360.0))
+}
+
+/// Converts a hue in degrees, saturation and lightness to red, green and blue fractions.
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+    let channel = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [channel(0.0), channel(8.0), channel(4.0)]
+}
+
+/// Converts an Oklch lightness, chroma and hue in degrees to sRGB red, green and blue fractions.
+fn oklch_to_rgb(lightness: f64, chroma: f64, hue: f64) -> [f64; 3] {
+    let (a, b) = (chroma * hue.to_radians().cos(), chroma * hue.to_radians().sin());
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    let linear = [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ];
+    linear.map(|value| match value <= 0.0031308 {
+        true => 12.92 * value,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    })
+}
+
+/// Deserializes a Color from an `[r, g, b, a]` array or a string.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 4], D::Error> {
+    struct ColorVisitor;
+    impl<'de> Visitor<'de> for ColorVisitor {
+        type Value = [u8; 4];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an [r, g, b, a] array or a color string")
+        }
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+            parse_color(text).map_err(E::custom)
+        }
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut color = [0; 4];
+            for (idx, value) in color.iter_mut().enumerate() {
+                *value = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(idx, &self))?;
+            }
+            if seq.next_element::<u8>()?.is_some() {
+                return Err(de::Error::invalid_length(5, &self));
+            }
+            Ok(color)
+        }
+    }
+    deserializer.deserialize_any(ColorVisitor)
+}
+
+/// Deserializes an optional Color from an `[r, g, b, a]` array or a string.
+pub fn deserialize_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[u8; 4]>, D::Error> {
+    #[derive(Deserialize)]
+    struct Color(#[serde(deserialize_with = "deserialize")] [u8; 4]);
+    Ok(Option::<Color>::deserialize(deserializer)?.map(|color| color.0))
+}
+
+/// Returns the Json Schema of a Color, an `[r, g, b, a]` array or a string.
+pub fn schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "description": "An [r, g, b, a] array, or a CSS color as in \"#00964b\", \"rgb(0 150 75)\" or \"white\".",
+        "anyOf": [
+            {
+                "type": "array",
+                "items": { "type": "integer", "minimum": 0, "maximum": 255 },
+                "minItems": 4,
+                "maxItems": 4
+            },
+            { "type": "string" }
+        ]
+    })
+}
+
+/// CSS color names, sorted by name.
+const NAMES: &[(&str, [u8; 3])] = &[
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
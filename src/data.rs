@@ -0,0 +1,100 @@
+//! This module defines the data rows of batch rendering and the filling of
+//! `{{column}}` placeholders in a Manifest.
+use std::fs::read_to_string;
+use std::path::Path;
+
+use crate::parse::Error;
+
+/// A row of data, mapping column names to values.
+pub type Row = toml::Table;
+
+/// Reads the rows of a Csv file with a header, or of a Json array of objects.
+///
+/// Csv values are strings, and Json values keep their types.
+pub fn read_rows(file: &Path) -> Result<Vec<Row>, Error> {
+    let text = read_to_string(file).map_err(|err| Error::Io(file.to_path_buf(), err))?;
+    let invalid = |err: String| Error::Invalid(format!("{}: {err}", file.display()));
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => {
+            let mut reader = csv::Reader::from_reader(text.as_bytes());
+            let headers = reader.headers().map_err(|err| invalid(err.to_string()))?.clone();
+            reader
+                .records()
+                .map(|record| {
+                    let record = record.map_err(|err| invalid(err.to_string()))?;
+                    Ok(headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(column, value)| (column.to_string(), toml::Value::String(value.to_string())))
+                        .collect())
+                })
+                .collect()
+        }
+        Some("json") => serde_json::from_str::<Vec<Row>>(&text).map_err(|err| invalid(err.to_string())),
+        _ => Err(invalid("data is neither csv nor json".to_string())),
+    }
+}
+
+/// Replaces the `{{column}}` placeholders in the strings below value with the values of row.
+///
+/// A string which is a single placeholder takes the value of the column, so
+/// `color = "{{color}}"` can be filled by a Csv value `[0, 150, 75, 255]`.
+/// Other placeholders are replaced by the value as text.
+pub fn fill(value: &mut toml::Value, row: &Row) -> Result<(), Error> {
+    match value {
+        toml::Value::String(text) => {
+            if let Some(column) = text.strip_prefix("{{").and_then(|text| text.strip_suffix("}}")) {
+                if !column.contains("{{") {
+                    *value = cell(row, column.trim())?;
+                    return Ok(());
+                }
+            }
+            let mut filled = String::new();
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(end) = rest[start..].find("}}") else {
+                    break;
+                };
+                filled.push_str(&rest[..start]);
+                match cell(row, rest[start + 2..start + end].trim())? {
+                    toml::Value::String(text) => filled.push_str(&text),
+                    value => filled.push_str(&value.to_string()),
+                }
+                rest = &rest[start + end + 2..];
+            }
+            filled.push_str(rest);
+            *text = filled;
+        }
+        toml::Value::Array(values) => {
+            for value in values {
+                fill(value, row)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                fill(value, row)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Returns the value of a column of row, parsing Csv arrays and tables as Toml.
+fn cell(row: &Row, column: &str) -> Result<toml::Value, Error> {
+    match row.get(column) {
+        Some(toml::Value::String(text)) if text.starts_with('[') || text.starts_with('{') => {
+            Ok(parse_value(text))
+        }
+        Some(value) => Ok(value.clone()),
+        None => Err(Error::Invalid(format!("no column {column} in data"))),
+    }
+}
+
+/// Parses a Toml value, returning it as a string if it is not valid Toml.
+pub fn parse_value(text: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {text}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(text.to_string()))
+}
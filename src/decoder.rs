@@ -0,0 +1,265 @@
+//! This module defines source Formats decodable by the generator.
+use std::fs::read;
+use std::io::Cursor;
+use std::path::Path;
+
+use file_format::FileFormat;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_bmp::zune_core::bytestream::ZCursor;
+use zune_bmp::BmpDecoder;
+use zune_jpeg::JpegDecoder;
+use zune_png::PngDecoder;
+
+use crate::image::*;
+use crate::parse::Error;
+
+/// Decodes the file contents to an [Image], returning the message of the error if
+/// they are corrupt or lack the frame.
+///
+/// The frame selects a frame of an animation or an entry of an icon.
+pub type DecodeFn = fn(&[u8], Option<usize>) -> Result<Image, String>;
+
+/// Registry of decoders by [FileFormat].
+pub struct Decoders {
+    decoders: Vec<(FileFormat, DecodeFn)>,
+}
+impl Decoders {
+    /// Creates an empty registry.
+    pub fn new() -> Decoders {
+        Decoders {
+            decoders: Vec::new(),
+        }
+    }
+    /// Registers a decoder for a [FileFormat], replacing any existing one.
+    pub fn register(&mut self, fmt: FileFormat, decode_fn: DecodeFn) {
+        match self.decoders.iter_mut().find(|(f, _)| *f == fmt) {
+            Some(decoder) => decoder.1 = decode_fn,
+            None => self.decoders.push((fmt, decode_fn)),
+        }
+    }
+    /// Returns the decoder registered for a [FileFormat].
+    pub fn get(&self, fmt: FileFormat) -> Option<DecodeFn> {
+        self.decoders
+            .iter()
+            .find(|(f, _)| *f == fmt)
+            .map(|(_, decode_fn)| *decode_fn)
+    }
+    /// Decodes an [Image] from file on disk.
+    ///
+    /// Errors name the file, and the frame if one was asked for.
+    pub fn decode<R: AsRef<Path>>(&self, filename: R, frame: Option<usize>) -> Result<Image, Error> {
+        let filename = filename.as_ref();
+        let file_contents = read(filename).map_err(|err| Error::Io(filename.to_path_buf(), err))?;
+        let fmt = FileFormat::from_bytes(&file_contents);
+        let Some(decode_fn) = self.get(fmt) else {
+            return Err(Error::Invalid(format!("{}: no decoder for {}", filename.display(), fmt.name())));
+        };
+        decode_fn(&file_contents, frame).map_err(|message| match frame {
+            Some(frame) => Error::Invalid(format!("{} frame {frame}: {message}", filename.display())),
+            None => Error::Invalid(format!("{}: {message}", filename.display())),
+        })
+    }
+}
+impl Default for Decoders {
+    /// Creates a registry of the builtin decoders.
+    fn default() -> Self {
+        let mut decoders = Decoders::new();
+        decoders.register(FileFormat::PortableNetworkGraphics, from_png);
+        decoders.register(FileFormat::AnimatedPortableNetworkGraphics, from_png);
+        decoders.register(FileFormat::JointPhotographicExpertsGroup, from_jpg);
+        decoders.register(FileFormat::Webp, from_webp);
+        decoders.register(FileFormat::GraphicsInterchangeFormat, from_gif);
+        decoders.register(FileFormat::WindowsBitmap, from_bmp);
+        decoders.register(FileFormat::QuiteOkImage, from_qoi);
+        decoders.register(FileFormat::TagImageFileFormat, from_tiff);
+        decoders.register(FileFormat::WindowsIcon, from_ico);
+        decoders
+    }
+}
+
+/// Expands 8-bit Gray, GrayAlpha, Rgb or Rgba image data to Rgba.
+pub fn to_rgba(data: &[u8], channels: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() / channels * 4);
+    for pixel in data.chunks_exact(channels) {
+        match *pixel {
+            [l] => bytes.extend_from_slice(&[l, l, l, 255]),
+            [l, a] => bytes.extend_from_slice(&[l, l, l, a]),
+            [r, g, b] => bytes.extend_from_slice(&[r, g, b, 255]),
+            _ => bytes.extend_from_slice(pixel),
+        }
+    }
+    bytes
+}
+
+/// Returns the message of a decoding error.
+fn message<E: std::fmt::Display>(err: E) -> String {
+    err.to_string()
+}
+
+/// Fails with message if a frame other than the first is asked of a single-frame image.
+fn first_frame(frame: Option<usize>, message: &str) -> Result<(), String> {
+    match frame {
+        Some(frame) if frame > 0 => Err(message.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes a Png. Only the default image of an animated Png is decoded.
+pub fn from_png(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    first_frame(frame, "only the first frame of a png can be decoded")?;
+    let options = DecoderOptions::default()
+        .png_set_add_alpha_channel(true)
+        .png_set_strip_to_8bit(true);
+    let mut decoder = PngDecoder::new_with_options(file_contents, options);
+    let buf = decoder.decode_raw().map_err(message)?;
+    let info = decoder.get_info().ok_or("png has no header")?;
+    Ok(Image::from(((info.width, info.height), (0, 0), buf)))
+}
+
+/// Decodes a Jpg.
+pub fn from_jpg(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    first_frame(frame, "jpg has 1 frame")?;
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGBA);
+    let mut decoder = JpegDecoder::new_with_options(file_contents, options);
+    let buf = decoder.decode().map_err(message)?;
+    let info = decoder.info().ok_or("jpg has no header")?;
+    Ok(Image::from(((info.width as usize, info.height as usize), (0, 0), buf)))
+}
+
+/// Decodes a WebP, or a frame of an animated WebP. Defaults to the first frame.
+pub fn from_webp(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    let mut decoder = image_webp::WebPDecoder::new(Cursor::new(file_contents)).map_err(message)?;
+    let (width, height) = decoder.dimensions();
+    let mut buf = vec![0; decoder.output_buffer_size().ok_or("webp is too large")?];
+    if decoder.is_animated() {
+        let frames = decoder.num_frames() as usize;
+        let last = frame.unwrap_or(0);
+        if last >= frames {
+            return Err(format!("webp has {frames} frames"));
+        }
+        for _ in 0..=last {
+            decoder.read_frame(&mut buf).map_err(message)?;
+        }
+    } else {
+        first_frame(frame, "webp has 1 frame")?;
+        decoder.read_image(&mut buf).map_err(message)?;
+    }
+    let channels = if decoder.has_alpha() { 4 } else { 3 };
+    Ok(Image::from(((width as usize, height as usize), (0, 0), to_rgba(&buf, channels))))
+}
+
+/// Decodes a frame of a Gif. Defaults to the first frame.
+///
+/// Frames before the chosen one are composited and disposed as a viewer would.
+pub fn from_gif(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(file_contents).map_err(message)?;
+    let width = decoder.width() as usize;
+    let height = decoder.height() as usize;
+    let mut screen = vec![0; width * height * 4];
+    let last = frame.unwrap_or(0);
+    for i in 0..=last {
+        let Some(frame) = decoder.read_next_frame().map_err(message)? else {
+            return Err(format!("gif has {i} frames"));
+        };
+        let previous = screen.clone();
+        let left = frame.left as usize;
+        let top = frame.top as usize;
+        let frame_width = frame.width as usize;
+        for (y, row) in frame.buffer.chunks_exact(frame_width.max(1) * 4).enumerate() {
+            for (x, pixel) in row.chunks_exact(4).enumerate() {
+                if pixel[3] == 0 || top + y >= height || left + x >= width {
+                    continue;
+                }
+                let idx = ((top + y) * width + left + x) * 4;
+                screen[idx..idx + 4].copy_from_slice(pixel);
+            }
+        }
+        if i == last {
+            break;
+        }
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in top..(top + frame.height as usize).min(height) {
+                    let start = (y * width + left.min(width)) * 4;
+                    let end = (y * width + (left + frame_width).min(width)) * 4;
+                    screen[start..end].fill(0);
+                }
+            }
+            gif::DisposalMethod::Previous => screen = previous,
+            _ => {}
+        }
+    }
+    Ok(Image::from(((width, height), (0, 0), screen)))
+}
+
+/// Decodes a Bmp.
+pub fn from_bmp(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    first_frame(frame, "bmp has 1 frame")?;
+    let mut decoder = BmpDecoder::new(ZCursor::new(file_contents));
+    decoder.decode_headers().map_err(|err| format!("{err:?}"))?;
+    let (width, height) = decoder.dimensions().ok_or("bmp has no header")?;
+    let channels = decoder.colorspace().ok_or("bmp has no header")?.num_components();
+    let buf = decoder.decode().map_err(|err| format!("{err:?}"))?;
+    Ok(Image::from(((width, height), (0, 0), to_rgba(&buf, channels))))
+}
+
+/// Decodes a Qoi.
+pub fn from_qoi(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    first_frame(frame, "qoi has 1 frame")?;
+    let (header, buf) = qoi::decode_to_vec(file_contents).map_err(message)?;
+    let channels = header.channels.as_u8() as usize;
+    Ok(Image::from((
+        (header.width as usize, header.height as usize),
+        (0, 0),
+        to_rgba(&buf, channels),
+    )))
+}
+
+/// Decodes an 8 or 16-bit Tiff, or a page of a multi-page Tiff. Defaults to the first page.
+pub fn from_tiff(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    use tiff::decoder::{Decoder, DecodingResult};
+    use tiff::ColorType;
+
+    let mut decoder = Decoder::new(Cursor::new(file_contents)).map_err(message)?;
+    if let Some(frame) = frame {
+        decoder.seek_to_image(frame).map_err(|_| "tiff has no such page".to_string())?;
+    }
+    let (width, height) = decoder.dimensions().map_err(message)?;
+    let channels = match decoder.colortype().map_err(message)? {
+        ColorType::Gray(8 | 16) => 1,
+        ColorType::GrayA(8 | 16) => 2,
+        ColorType::RGB(8 | 16) => 3,
+        ColorType::RGBA(8 | 16) => 4,
+        color_type => return Err(format!("unsupported tiff color type {:?}", color_type)),
+    };
+    let buf = match decoder.read_image().map_err(message)? {
+        DecodingResult::U8(buf) => buf,
+        DecodingResult::U16(buf) => buf.into_iter().map(|value| (value >> 8) as u8).collect(),
+        _ => return Err("unsupported tiff sample format".to_string()),
+    };
+    Ok(Image::from(((width as usize, height as usize), (0, 0), to_rgba(&buf, channels))))
+}
+
+/// Decodes an entry of an Ico. Defaults to the largest entry.
+pub fn from_ico(file_contents: &[u8], frame: Option<usize>) -> Result<Image, String> {
+    let icon_dir = ico::IconDir::read(Cursor::new(file_contents)).map_err(message)?;
+    let entries = icon_dir.entries();
+    let entry = match frame {
+        Some(frame) => entries
+            .get(frame)
+            .ok_or_else(|| format!("ico has {} entries", entries.len()))?,
+        None => entries
+            .iter()
+            .max_by_key(|entry| (entry.width() * entry.height(), entry.bits_per_pixel()))
+            .ok_or("ico has no entries")?,
+    };
+    let icon = entry.decode().map_err(message)?;
+    Ok(Image::from((
+        (icon.width() as usize, icon.height() as usize),
+        (0, 0),
+        icon.rgba_data().to_vec(),
+    )))
+}
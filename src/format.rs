@@ -1,40 +1,279 @@
 //! This module defines target Formats for the generator.
-use std::fs::File;
-use std::io::BufWriter;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image_webp as webp;
+use svg::Node;
 use jpeg_encoder as jpg;
+use tiff::encoder as tif;
 
+use crate::asset::Font;
 use crate::image::*;
+use crate::object::*;
+use crate::parse::*;
+use crate::util::*;
 
 /// Image Format.
 pub trait Format {
-    /// Writes the Image to disk.
-    fn write(&mut self, filename: &str);
+    /// Writes the encoded Image to w.
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error>;
     /// Retrieves the [Image] data of the format.
     fn image(&mut self) -> &mut Image;
+    /// Returns the file extension of the format.
+    fn extension(&self) -> &'static str;
+    /// Retrieves the [Animation] of the format, if it can be animated.
+    fn animation(&mut self) -> Option<&mut dyn Animation> {
+        None
+    }
+    /// Retrieves the [Vector] of the format, if Objects are drawn as vector graphics.
+    fn vector(&mut self) -> Option<&mut dyn Vector> {
+        None
+    }
+}
+
+/// Animated Image Format.
+pub trait Animation {
+    /// Sets the frames per second and the number of plays, zero playing forever.
+    fn set_timing(&mut self, fps: f64, plays: u32);
+    /// Adds the [Image] of the format as the next frame.
+    fn push_frame(&mut self);
+}
+
+/// Vector Image Format.
+///
+/// Objects drawn on a vector format add elements to it instead of pixels to its [Image].
+pub trait Vector {
+    /// Adds a rectangle filled with color, with its top left corner at origin.
+    fn rect(&mut self, origin: Point, width: f64, height: f64, color: Pixel, properties: &Properties);
+    /// Adds a raster [Image] with its top left corner at origin.
+    fn image(&mut self, origin: Point, image: &mut Image, properties: &Properties);
+    /// Adds the text of a [TextBox] in color, with the top left corner of the box at origin.
+    fn text(&mut self, origin: Point, text: &TextBox, color: Pixel, properties: &Properties);
+}
+
+/// Returns the delay of a frame as a fraction of a second.
+pub fn frame_delay(fps: f64) -> (u16, u16) {
+    if fps.fract() == 0.0 && (1.0..=u16::MAX as f64).contains(&fps) {
+        (1, fps as u16)
+    } else {
+        ((1000.0 / fps).round() as u16, 1000)
+    }
+}
+
+/// Creates a [Format] of the given width and height using the encoding options,
+/// failing if the options are invalid.
+pub type FormatFn = fn(usize, usize, &FormatInfo) -> Result<Box<dyn Format>, Error>;
+
+/// Registry of [Format]s by name.
+pub struct Formats {
+    formats: Vec<(String, Vec<String>, FormatFn)>,
+}
+impl Formats {
+    /// Creates an empty registry.
+    pub fn new() -> Formats {
+        Formats { formats: Vec::new() }
+    }
+    /// Registers a [Format] under a name and the file extensions it is inferred from.
+    ///
+    /// A format registered under an existing name replaces it.
+    pub fn register(&mut self, name: &str, extensions: &[&str], format_fn: FormatFn) {
+        let extensions = extensions.iter().map(|ext| ext.to_string()).collect();
+        match self.formats.iter_mut().find(|(n, _, _)| n == name) {
+            Some(format) => *format = (name.to_string(), extensions, format_fn),
+            None => self.formats.push((name.to_string(), extensions, format_fn)),
+        }
+    }
+    /// Creates the [Format] registered under name.
+    pub fn get(&self, name: &str, width: usize, height: usize, info: &FormatInfo) -> Result<Box<dyn Format>, Error> {
+        match self.formats.iter().find(|(n, _, _)| n == name) {
+            Some((_, _, format_fn)) => format_fn(width, height, info),
+            None => Err(Error::Invalid(format!("unknown image format {name}"))),
+        }
+    }
+    /// Returns the name of the [Format] inferred from the extension of filename.
+    pub fn from_extension<R: AsRef<Path>>(&self, filename: R) -> Option<&str> {
+        let ext = filename.as_ref().extension()?.to_str()?.to_lowercase();
+        self.formats
+            .iter()
+            .find(|(_, extensions, _)| extensions.contains(&ext))
+            .map(|(name, _, _)| name.as_str())
+    }
+    /// Returns true if filename has one of the extensions of the [Format] registered
+    /// under name, or an extension no Format is inferred from.
+    pub fn fits_extension<R: AsRef<Path>>(&self, name: &str, filename: R) -> bool {
+        let Some(ext) = filename.as_ref().extension().and_then(|ext| ext.to_str()) else {
+            return true;
+        };
+        let ext = ext.to_lowercase();
+        let names: Vec<&str> = self
+            .formats
+            .iter()
+            .filter(|(_, extensions, _)| extensions.contains(&ext))
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        names.is_empty() || names.contains(&name)
+    }
+    /// Returns the names of the registered [Format]s.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formats.iter().map(|(name, _, _)| name.as_str())
+    }
+}
+impl Default for Formats {
+    /// Creates a registry of the builtin [Format]s.
+    fn default() -> Self {
+        let mut formats = Formats::new();
+        formats.register("png", &["png"], |width, height, info| {
+            let mut png = Png::new(width, height);
+            if let Some(png_info) = &info.png {
+                parse_png(&mut png, png_info)?;
+            }
+            Ok(Box::new(png))
+        });
+        formats.register("apng", &["apng", "png"], |width, height, info| {
+            let mut png = Png::new(width, height);
+            if let Some(png_info) = &info.png {
+                parse_png(&mut png, png_info)?;
+            }
+            Ok(Box::new(png))
+        });
+        formats.register("gif", &["gif"], |width, height, _| Ok(Box::new(Gif::new(width, height))));
+        formats.register("jpg", &["jpg", "jpeg"], |width, height, info| {
+            let mut jpg = Jpg::new(width, height);
+            if let Some(jpg_info) = &info.jpg {
+                parse_jpg(&mut jpg, jpg_info)?;
+            }
+            Ok(Box::new(jpg))
+        });
+        formats.register("webp", &["webp"], |width, height, info| {
+            let mut webp = Webp::new(width, height);
+            if let Some(webp_info) = &info.webp {
+                parse_webp(&mut webp, webp_info)?;
+            }
+            Ok(Box::new(webp))
+        });
+        formats.register("qoi", &["qoi"], |width, height, _| Ok(Box::new(Qoi::new(width, height))));
+        formats.register("bmp", &["bmp"], |width, height, _| Ok(Box::new(Bmp::new(width, height))));
+        formats.register("tga", &["tga"], |width, height, _| Ok(Box::new(Tga::new(width, height))));
+        formats.register("ppm", &["ppm"], |width, height, _| Ok(Box::new(Ppm::new(width, height))));
+        formats.register("pam", &["pam"], |width, height, _| Ok(Box::new(Pam::new(width, height))));
+        formats.register("svg", &["svg"], |width, height, info| {
+            let mut svg = Svg::new(width, height);
+            if let Some(svg_info) = &info.svg {
+                parse_svg(&mut svg, svg_info)?;
+            }
+            Ok(Box::new(svg))
+        });
+        formats.register("pdf", &["pdf"], |width, height, info| {
+            let mut pdf = Pdf::new(width, height);
+            if let Some(pdf_info) = &info.pdf {
+                parse_pdf(&mut pdf, pdf_info)?;
+            }
+            Ok(Box::new(pdf))
+        });
+        formats.register("tiff", &["tiff", "tif"], |width, height, info| {
+            let mut tiff = Tiff::new(width, height);
+            if let Some(tiff_info) = &info.tiff {
+                parse_tiff(&mut tiff, tiff_info)?;
+            }
+            Ok(Box::new(tiff))
+        });
+        formats
+    }
+}
+
+/// Returns true if every [Pixel] is opaque.
+pub fn is_opaque(pixels: &[Pixel]) -> bool {
+    pixels.iter().all(|pixel| pixel[3] == 255)
+}
+
+/// Converts [Pixel]s to 8-bit image data with the channels in the given order.
+pub fn channel_data(pixels: &[Pixel], channels: &[usize]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(pixels.len() * channels.len());
+    for pixel in pixels {
+        bytes.extend(channels.iter().map(|&i| pixel[i]));
+    }
+    bytes
+}
+
+/// Returns the width and height of image, failing if either is larger than max,
+/// the largest the format named name holds.
+fn checked_size<T: TryFrom<usize>>(image: &Image, name: &str, max: usize) -> Result<(T, T), Error> {
+    let (width, height) = (image.width, image.height);
+    match (T::try_from(width), T::try_from(height)) {
+        (Ok(w), Ok(h)) if width <= max && height <= max => Ok((w, h)),
+        _ => Err(Error::Invalid(format!(
+            "{name} images are at most {max} pixels wide and high, not {width}x{height}"
+        ))),
+    }
+}
+
+/// Returns the error of an encoder that failed for a reason other than writing.
+fn encode_error(err: impl std::fmt::Display) -> Error {
+    Error::Invalid(format!("cannot encode image: {err}"))
+}
+
+fn png_error(err: png::EncodingError) -> Error {
+    match err {
+        png::EncodingError::IoError(err) => Error::Write(err),
+        err => encode_error(err),
+    }
+}
+
+fn qoi_error(err: qoi::Error) -> Error {
+    match err {
+        qoi::Error::IoError(err) => Error::Write(err),
+        err => encode_error(err),
+    }
+}
+
+fn tiff_error(err: tiff::TiffError) -> Error {
+    match err {
+        tiff::TiffError::IoError(err) => Error::Write(err),
+        err => encode_error(err),
+    }
+}
+
+fn gif_error(err: gif::EncodingError) -> Error {
+    match err {
+        gif::EncodingError::Io(err) => Error::Write(err),
+        err => encode_error(err),
+    }
 }
 
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 /// Png Format.
+///
+/// Pushing frames makes the Png an animated Png.
 pub struct Png {
     image: Image,
-    color_type: png::ColorType,
+    color_type: Option<png::ColorType>,
     bit_depth: png::BitDepth,
+    frames: Vec<Vec<Pixel>>,
+    fps: f64,
+    plays: u32,
 }
 impl Png {
     /// Creates a new Png Image.
+    ///
+    /// The Color Type is picked when writing: opaque Images are written as
+    /// [Rgb][png::ColorType::Rgb], others as [Rgba][png::ColorType::Rgba].
     pub fn new(width: usize, height: usize,) -> Png {
         Png {
             image: Image::new(width, height),
-            color_type: png::ColorType::Rgba,
+            color_type: None,
             bit_depth: png::BitDepth::Eight,
+            frames: Vec::new(),
+            fps: 1.0,
+            plays: 0,
         }
     }
     /// Sets the Color Type of the Image.
     pub fn set_color_type(&mut self, color_type: png::ColorType) {
-        self.color_type = color_type
+        self.color_type = Some(color_type)
     }
     /// Sets the Bit Depth of the Image.
     pub fn set_bit_depth(&mut self, bit_depth: png::BitDepth) {
@@ -42,27 +281,87 @@ impl Png {
     }
 }
 impl Format for Png {
-    fn write(&mut self, filename: &str) {
-        let pth = format!("{filename}.png");
-        let path = Path::new(&pth);
-        let file = File::create(path).unwrap();
-        let w = &mut BufWriter::new(file);
-        let mut encoder = png::Encoder::new(
-            w,
-            self.image.width().try_into().unwrap(),
-            self.image.height().try_into().unwrap(),
-        );
-        encoder.set_color(self.color_type);
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let frames = if self.frames.is_empty() {
+            vec![self.image.as_pixels()]
+        } else {
+            self.frames.clone()
+        };
+        let color_type = match self.color_type {
+            Some(color_type) => color_type,
+            None if frames.iter().all(|pixels| is_opaque(pixels)) => png::ColorType::Rgb,
+            None => png::ColorType::Rgba,
+        };
+        let (width, height) = checked_size(&self.image, "png", i32::MAX as usize)?;
+        let data = frames
+            .iter()
+            .map(|pixels| png_data(pixels, color_type, self.bit_depth))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut encoder = png::Encoder::new(w, width, height);
+        encoder.set_color(color_type);
         encoder.set_depth(self.bit_depth);
-        let mut writer = encoder.write_header().unwrap();
-        writer
-            .write_image_data(self.image.to_vec().as_slice())
-            .unwrap();
-        //println!("{:?}", self.image[0][546][0]);
+        if !self.frames.is_empty() {
+            encoder.set_animated(frames.len() as u32, self.plays).map_err(png_error)?;
+            let (numerator, denominator) = frame_delay(self.fps);
+            encoder.set_frame_delay(numerator, denominator).map_err(png_error)?;
+        }
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        for data in data {
+            writer.write_image_data(data.as_slice()).map_err(png_error)?;
+        }
+        writer.finish().map_err(png_error)
     }
     fn image(&mut self) -> &mut Image {
         &mut self.image
     }
+    fn extension(&self) -> &'static str {
+        "png"
+    }
+    fn animation(&mut self) -> Option<&mut dyn Animation> {
+        Some(self)
+    }
+}
+impl Animation for Png {
+    fn set_timing(&mut self, fps: f64, plays: u32) {
+        self.fps = fps;
+        self.plays = plays;
+    }
+    fn push_frame(&mut self) {
+        self.frames.push(self.image.as_pixels())
+    }
+}
+
+/// Converts [Pixel]s to Png image data of the given Color Type and Bit Depth.
+///
+/// Indexed Color Types and Bit Depths other than 8 and 16 are not supported.
+pub fn png_data(pixels: &[Pixel], color_type: png::ColorType, bit_depth: png::BitDepth) -> Result<Vec<u8>, Error> {
+    let channels: &[fn(&Pixel) -> u8] = match color_type {
+        png::ColorType::Rgb => &[|p| p[0], |p| p[1], |p| p[2]],
+        png::ColorType::Rgba => &[|p| p[0], |p| p[1], |p| p[2], |p| p[3]],
+        png::ColorType::Grayscale => &[luma],
+        png::ColorType::GrayscaleAlpha => &[luma, |p| p[3]],
+        png::ColorType::Indexed => return Err(Error::Invalid("unsupported png color type Indexed".to_string())),
+    };
+    if !matches!(bit_depth, png::BitDepth::Eight | png::BitDepth::Sixteen) {
+        return Err(Error::Invalid(format!("unsupported png bit depth {}", bit_depth as u8)));
+    }
+    let mut bytes = Vec::with_capacity(pixels.len() * channels.len() * 2);
+    for pixel in pixels {
+        for channel in channels {
+            let value = channel(pixel);
+            match bit_depth {
+                png::BitDepth::Eight => bytes.push(value),
+                // Scale by 257 so that 255 maps to 65535.
+                _ => bytes.extend_from_slice(&(value as u16 * 257).to_be_bytes()),
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns the luma of a [Pixel] using the Rec. 601 weights.
+fn luma(pixel: &Pixel) -> u8 {
+    ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114 + 500) / 1000) as u8
 }
 
 #[allow(dead_code)]
@@ -71,35 +370,1091 @@ impl Format for Png {
 pub struct Jpg {
     image: Image,
     color_type: jpg::ColorType,
+    quality: u8,
+    sampling_factor: Option<jpg::SamplingFactor>,
+    progressive: bool,
+    optimized_huffman_tables: bool,
+    restart_interval: Option<u16>,
+    matte: Pixel,
 }
 impl Jpg {
     /// Creates a new Jpg Image.
     pub fn new(width: usize, height: usize) -> Jpg {
         Jpg {
             image: Image::new(width, height),
-            color_type: jpg::ColorType::Rgba,
+            color_type: jpg::ColorType::Rgb,
+            quality: 100,
+            sampling_factor: None,
+            progressive: false,
+            optimized_huffman_tables: false,
+            restart_interval: None,
+            matte: Pixel::new(),
         }
     }
     /// Sets the Color Type of Image.
+    ///
+    /// Only [Rgb][jpg::ColorType::Rgb] and [Luma][jpg::ColorType::Luma] are supported.
     pub fn set_color_type(&mut self, color_type: jpg::ColorType) {
         self.color_type = color_type
     }
+    /// Sets the Quality of Image, between 1 and 100.
+    pub fn set_quality(&mut self, quality: u8) {
+        self.quality = quality
+    }
+    /// Sets the Chroma Subsampling of Image.
+    ///
+    /// By default the encoder subsamples 4:2:0 for qualities below 90 and 4:4:4 otherwise.
+    pub fn set_sampling_factor(&mut self, sampling_factor: jpg::SamplingFactor) {
+        self.sampling_factor = Some(sampling_factor)
+    }
+    /// Enables or disables progressive encoding.
+    pub fn set_progressive(&mut self, progressive: bool) {
+        self.progressive = progressive
+    }
+    /// Enables or disables optimized Huffman tables.
+    pub fn set_optimized_huffman_tables(&mut self, optimized_huffman_tables: bool) {
+        self.optimized_huffman_tables = optimized_huffman_tables
+    }
+    /// Sets the number of MCUs between restart markers.
+    pub fn set_restart_interval(&mut self, restart_interval: u16) {
+        self.restart_interval = Some(restart_interval)
+    }
+    /// Sets the Matte Color transparent pixels are flattened onto. Defaults to white.
+    pub fn set_matte(&mut self, mut matte: Pixel) {
+        matte.set_alpha(255);
+        self.matte = matte
+    }
 }
 impl Format for Jpg {
-    fn write(&mut self, filename: &str) {
-        let pth = format!("{filename}.jpg");
-        // Create new encoder that writes to a file with maximum quality (100)
-        let encoder = jpg::Encoder::new_file(pth, 100).unwrap();
-
-        // Encode the data with dimension 2x2
-        let _ = encoder.encode(
-            self.image.to_vec().as_slice(),
-            self.image.width.try_into().unwrap(),
-            self.image.height.try_into().unwrap(),
-            self.color_type,
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let (width, height) = checked_size(&self.image, "jpg", u16::MAX as usize)?;
+        let mut encoder = jpg::Encoder::new(w, self.quality);
+        if let Some(sampling_factor) = self.sampling_factor {
+            encoder.set_sampling_factor(sampling_factor);
+        }
+        encoder.set_progressive(self.progressive);
+        encoder.set_optimized_huffman_tables(self.optimized_huffman_tables);
+        if let Some(restart_interval) = self.restart_interval {
+            encoder.set_restart_interval(restart_interval);
+        }
+
+        let mut bytes = Vec::new();
+        for pixel in self.image.as_pixels() {
+            let pixel = self.matte + pixel;
+            match self.color_type {
+                jpg::ColorType::Rgb => bytes.extend_from_slice(&[pixel[0], pixel[1], pixel[2]]),
+                jpg::ColorType::Luma => bytes.push(luma(&pixel)),
+                _ => return Err(Error::Invalid(format!("unsupported jpg color type {:?}", self.color_type))),
+            }
+        }
+        encoder
+            .encode(bytes.as_slice(), width, height, self.color_type)
+            .map_err(|err| match err {
+                jpg::EncodingError::IoError(err) => Error::Write(err),
+                err => encode_error(err),
+            })
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "jpg"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// WebP Format.
+///
+/// WebP is written lossless (VP8L) unless a quality is set, in which case the Image is
+/// compressed lossy (VP8) by the pure Rust encoder in [crate::vp8], with any transparency
+/// stored losslessly beside it. Near-lossless encoding rounds off the low bits of every
+/// channel according to its quality before the Image is losslessly compressed.
+pub struct Webp {
+    image: Image,
+    quality: Option<u8>,
+    near_lossless: Option<u8>,
+}
+impl Webp {
+    /// Creates a new lossless WebP Image.
+    pub fn new(width: usize, height: usize) -> Webp {
+        Webp {
+            image: Image::new(width, height),
+            quality: None,
+            near_lossless: None,
+        }
+    }
+    /// Keeps the colors of the Image as they are.
+    pub fn set_lossless(&mut self) {
+        self.quality = None;
+        self.near_lossless = None;
+    }
+    /// Rounds off the colors of the Image with a quality between 0 and 100 before
+    /// compressing it losslessly.
+    pub fn set_near_lossless(&mut self, quality: u8) {
+        self.quality = None;
+        self.near_lossless = Some(quality);
+    }
+    /// Compresses the Image lossy with a quality between 0 and 100.
+    pub fn set_quality(&mut self, quality: u8) {
+        self.quality = Some(quality);
+        self.near_lossless = None;
+    }
+    /// Writes the Image as lossy WebP, with an ALPH chunk if it is transparent.
+    fn write_lossy(&mut self, w: &mut dyn Write, quality: u8) -> Result<(), Error> {
+        // VP8 stores sizes in 14 bits.
+        let (width, height) = checked_size::<u32>(&self.image, "lossy webp", 16383)?;
+        let pixels = self.image.as_pixels();
+        let vp8 = crate::vp8::encode(&pixels, width as usize, height as usize, quality)?;
+        let mut chunks = Vec::new();
+        if !is_opaque(&pixels) {
+            let mut vp8x = vec![0x10, 0, 0, 0];
+            vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+            write_chunk(&mut chunks, b"VP8X", &vp8x);
+            // The alpha values are compressed as the green channel of a headerless VP8L image.
+            let mut alpha = Vec::new();
+            webp::WebPEncoder::new(&mut alpha)
+                .encode(&channel_data(&pixels, &[3]), width, height, webp::ColorType::L8)
+                .map_err(encode_error)?;
+            let size = u32::from_le_bytes([alpha[16], alpha[17], alpha[18], alpha[19]]) as usize;
+            let mut alph = vec![1];
+            alph.extend_from_slice(&alpha[25..20 + size]);
+            write_chunk(&mut chunks, b"ALPH", &alph);
+        }
+        write_chunk(&mut chunks, b"VP8 ", &vp8);
+        let size = u32::try_from(chunks.len() + 4)
+            .map_err(|_| encode_error("the image is too large for webp"))?;
+        w.write_all(b"RIFF").map_err(Error::Write)?;
+        w.write_all(&size.to_le_bytes()).map_err(Error::Write)?;
+        w.write_all(b"WEBP").map_err(Error::Write)?;
+        w.write_all(&chunks).map_err(Error::Write)
+    }
+}
+impl Format for Webp {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        if let Some(quality) = self.quality {
+            return self.write_lossy(w, quality);
+        }
+        // Lossless WebP stores sizes in 14 bits.
+        let (width, height) = checked_size(&self.image, "webp", 16384)?;
+        let mut pixels = self.image.as_pixels();
+        if let Some(quality) = self.near_lossless {
+            near_lossless(&mut pixels, quality);
+        }
+        let (color_type, bytes) = if is_opaque(&pixels) {
+            (webp::ColorType::Rgb8, channel_data(&pixels, &[0, 1, 2]))
+        } else {
+            (webp::ColorType::Rgba8, channel_data(&pixels, &[0, 1, 2, 3]))
+        };
+        webp::WebPEncoder::new(w)
+            .encode(bytes.as_slice(), width, height, color_type)
+            .map_err(|err| match err {
+                webp::EncodingError::IoError(err) => Error::Write(err),
+                err => encode_error(err),
+            })
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "webp"
+    }
+}
+
+/// Appends a RIFF chunk to bytes, padded to an even length.
+fn write_chunk(bytes: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(name);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+/// Rounds off the low bits of [Pixel]s, dropping one bit for every 20 points of quality under 100.
+///
+/// Fully transparent pixels are made black, as their color is never seen.
+pub fn near_lossless(pixels: &mut [Pixel], quality: u8) {
+    let bits = (100 - quality.min(100) as u32).div_ceil(20);
+    for pixel in pixels {
+        if pixel[3] == 0 {
+            *pixel = Pixel::from(&[0, 0, 0, 0]);
+            continue;
+        }
+        for i in 0..3 {
+            let value = pixel[i] as u32 + ((1 << bits) >> 1);
+            pixel[i] = ((value >> bits) << bits).min(255) as u8;
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Qoi Format.
+pub struct Qoi {
+    image: Image,
+}
+impl Qoi {
+    /// Creates a new Qoi Image.
+    pub fn new(width: usize, height: usize) -> Qoi {
+        Qoi {
+            image: Image::new(width, height),
+        }
+    }
+}
+impl Format for Qoi {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let pixels = self.image.as_pixels();
+        let bytes = if is_opaque(&pixels) {
+            channel_data(&pixels, &[0, 1, 2])
+        } else {
+            channel_data(&pixels, &[0, 1, 2, 3])
+        };
+        let (width, height) = checked_size(&self.image, "qoi", u32::MAX as usize)?;
+        let encoder = qoi::Encoder::new(&bytes, width, height).map_err(qoi_error)?;
+        encoder.encode_to_stream(&mut &mut *w).map_err(qoi_error)?;
+        Ok(())
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "qoi"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Bmp Format.
+///
+/// Opaque Images are written with 24 bits per pixel, others with 32 bits per
+/// pixel and a `BITMAPV4HEADER` describing the alpha channel.
+pub struct Bmp {
+    image: Image,
+}
+impl Bmp {
+    /// Creates a new Bmp Image.
+    pub fn new(width: usize, height: usize) -> Bmp {
+        Bmp {
+            image: Image::new(width, height),
+        }
+    }
+}
+impl Format for Bmp {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let (width, height) = checked_size::<i32>(&self.image, "bmp", i32::MAX as usize)?;
+        let (width, height) = (width as usize, height as usize);
+        let pixels = self.image.as_pixels();
+        let opaque = is_opaque(&pixels);
+        let (header_size, channels): (u32, &[usize]) = if opaque {
+            (40, &[2, 1, 0])
+        } else {
+            (108, &[2, 1, 0, 3])
+        };
+        // Rows are padded to 4 bytes and stored bottom-up.
+        let stride = (width * channels.len()).div_ceil(4) * 4;
+        let offset = 14 + header_size;
+        // The size of the file is stored in 32 bits.
+        let data_size = u32::try_from(stride * height).ok().filter(|size| size.checked_add(offset).is_some());
+        let Some(data_size) = data_size else {
+            return Err(Error::Invalid(format!("bmp images hold at most 4 GiB, not {width}x{height} pixels")));
+        };
+
+        let mut bytes = Vec::with_capacity((offset + data_size) as usize);
+        bytes.extend_from_slice(b"BM");
+        bytes.extend_from_slice(&(offset + data_size).to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&header_size.to_le_bytes());
+        bytes.extend_from_slice(&(width as i32).to_le_bytes());
+        bytes.extend_from_slice(&(height as i32).to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&(channels.len() as u16 * 8).to_le_bytes());
+        // BI_RGB or BI_BITFIELDS.
+        bytes.extend_from_slice(&(if opaque { 0u32 } else { 3 }).to_le_bytes());
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        // 72 DPI in pixels per metre.
+        bytes.extend_from_slice(&2835u32.to_le_bytes());
+        bytes.extend_from_slice(&2835u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        if !opaque {
+            for mask in [0x00ff0000u32, 0x0000ff00, 0x000000ff, 0xff000000] {
+                bytes.extend_from_slice(&mask.to_le_bytes());
+            }
+            // LCS_sRGB, followed by the unused endpoints and gamma.
+            bytes.extend_from_slice(b"BGRs");
+            bytes.extend_from_slice(&[0; 48]);
+        }
+        for row in pixels.chunks_exact(width).rev() {
+            let mut data = channel_data(row, channels);
+            data.resize(stride, 0);
+            bytes.extend_from_slice(&data);
+        }
+        w.write_all(&bytes).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "bmp"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Tga Format.
+pub struct Tga {
+    image: Image,
+}
+impl Tga {
+    /// Creates a new Tga Image.
+    pub fn new(width: usize, height: usize) -> Tga {
+        Tga {
+            image: Image::new(width, height),
+        }
+    }
+}
+impl Format for Tga {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let pixels = self.image.as_pixels();
+        let (channels, descriptor): (&[usize], u8) = if is_opaque(&pixels) {
+            (&[2, 1, 0], 0x20)
+        } else {
+            // 8 alpha bits.
+            (&[2, 1, 0, 3], 0x28)
+        };
+        let (width, height) = checked_size::<u16>(&self.image, "tga", u16::MAX as usize)?;
+        let mut bytes = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes.push(channels.len() as u8 * 8);
+        // Rows are stored top-down.
+        bytes.push(descriptor);
+        bytes.extend_from_slice(&channel_data(&pixels, channels));
+        w.write_all(&bytes).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "tga"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Binary Ppm Format.
+///
+/// Ppm has no alpha channel, so the Image is flattened onto white.
+pub struct Ppm {
+    image: Image,
+}
+impl Ppm {
+    /// Creates a new Ppm Image.
+    pub fn new(width: usize, height: usize) -> Ppm {
+        Ppm {
+            image: Image::new(width, height),
+        }
+    }
+}
+impl Format for Ppm {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let pixels: Vec<Pixel> = self
+            .image
+            .as_pixels()
+            .into_iter()
+            .map(|pixel| Pixel::new() + pixel)
+            .collect();
+        let mut bytes = format!("P6\n{} {}\n255\n", self.image.width, self.image.height).into_bytes();
+        bytes.extend_from_slice(&channel_data(&pixels, &[0, 1, 2]));
+        w.write_all(&bytes).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "ppm"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Pam Format.
+pub struct Pam {
+    image: Image,
+}
+impl Pam {
+    /// Creates a new Pam Image.
+    pub fn new(width: usize, height: usize) -> Pam {
+        Pam {
+            image: Image::new(width, height),
+        }
+    }
+}
+impl Format for Pam {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let pixels = self.image.as_pixels();
+        let mut bytes = format!(
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            self.image.width, self.image.height
+        )
+        .into_bytes();
+        bytes.extend_from_slice(&channel_data(&pixels, &[0, 1, 2, 3]));
+        w.write_all(&bytes).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "pam"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone)]
+/// Tiff Format.
+pub struct Tiff {
+    image: Image,
+    compression: tif::Compression,
+}
+impl Tiff {
+    /// Creates a new uncompressed Tiff Image.
+    pub fn new(width: usize, height: usize) -> Tiff {
+        Tiff {
+            image: Image::new(width, height),
+            compression: tif::Compression::Uncompressed,
+        }
+    }
+    /// Sets the Compression of the Image.
+    pub fn set_compression(&mut self, compression: tif::Compression) {
+        self.compression = compression
+    }
+}
+impl Format for Tiff {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let pixels = self.image.as_pixels();
+        let (width, height) = checked_size(&self.image, "tiff", u32::MAX as usize)?;
+        // Tiff offsets are written after the data, so the encoder needs to seek.
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut encoder = tif::TiffEncoder::new(&mut buf)
+                .map_err(tiff_error)?
+                .with_compression(self.compression);
+            if is_opaque(&pixels) {
+                let bytes = channel_data(&pixels, &[0, 1, 2]);
+                encoder
+                    .write_image::<tif::colortype::RGB8>(width, height, &bytes)
+                    .map_err(tiff_error)?;
+            } else {
+                let bytes = channel_data(&pixels, &[0, 1, 2, 3]);
+                encoder
+                    .write_image::<tif::colortype::RGBA8>(width, height, &bytes)
+                    .map_err(tiff_error)?;
+            }
+        }
+        w.write_all(buf.get_ref()).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "tiff"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Gif Format.
+///
+/// Every frame is quantized to its own 256 color palette, and pixels less
+/// than half opaque become transparent.
+pub struct Gif {
+    image: Image,
+    frames: Vec<Vec<Pixel>>,
+    fps: f64,
+    plays: u32,
+}
+impl Gif {
+    /// Creates a new Gif Image.
+    pub fn new(width: usize, height: usize) -> Gif {
+        Gif {
+            image: Image::new(width, height),
+            frames: Vec::new(),
+            fps: 1.0,
+            plays: 0,
+        }
+    }
+}
+impl Format for Gif {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        let (width, height) = checked_size(&self.image, "gif", u16::MAX as usize)?;
+        let frames = if self.frames.is_empty() {
+            vec![self.image.as_pixels()]
+        } else {
+            self.frames.clone()
+        };
+        let mut encoder = gif::Encoder::new(w, width, height, &[]).map_err(gif_error)?;
+        if !self.frames.is_empty() {
+            // The Netscape loop count excludes the first play.
+            match self.plays {
+                0 => encoder.set_repeat(gif::Repeat::Infinite).map_err(gif_error)?,
+                1 => {}
+                plays => encoder
+                    .set_repeat(gif::Repeat::Finite((plays - 1).try_into().unwrap_or(u16::MAX)))
+                    .map_err(gif_error)?,
+            }
+        }
+        for pixels in frames {
+            let mut bytes = channel_data(&pixels, &[0, 1, 2, 3]);
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut bytes, 10);
+            frame.delay = (100.0 / self.fps).round() as u16;
+            frame.dispose = gif::DisposalMethod::Background;
+            encoder.write_frame(&frame).map_err(gif_error)?;
+        }
+        encoder.into_inner().map_err(gif_error)?;
+        Ok(())
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "gif"
+    }
+    fn animation(&mut self) -> Option<&mut dyn Animation> {
+        Some(self)
+    }
+}
+impl Animation for Gif {
+    fn set_timing(&mut self, fps: f64, plays: u32) {
+        self.fps = fps;
+        self.plays = plays;
+    }
+    fn push_frame(&mut self) {
+        self.frames.push(self.image.as_pixels())
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Svg Format.
+///
+/// Objects are written as Svg elements. Raster Images are embedded as Png
+/// data URIs, or linked to their source file when linking is enabled.
+/// Text is written as text elements using its Font file as a web font.
+pub struct Svg {
+    image: Image,
+    document: svg::Document,
+    link_images: bool,
+    fonts: Vec<PathBuf>,
+}
+impl Svg {
+    /// Creates a new Svg Image.
+    pub fn new(width: usize, height: usize) -> Svg {
+        Svg {
+            image: Image::new(width, height),
+            document: svg::Document::new()
+                .set("width", width)
+                .set("height", height)
+                .set("viewBox", format!("0 0 {width} {height}")),
+            link_images: false,
+            fonts: Vec::new(),
+        }
+    }
+    /// Sets whether raster Images decoded from a file are linked instead of embedded.
+    ///
+    /// Linked Images are referenced by their path in the Manifest.
+    pub fn set_link_images(&mut self, link_images: bool) {
+        self.link_images = link_images
+    }
+}
+impl Format for Svg {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        svg::write(w, &self.document).map_err(Error::Write)
+    }
+    fn image(&mut self) -> &mut Image {
+        &mut self.image
+    }
+    fn extension(&self) -> &'static str {
+        "svg"
+    }
+    fn vector(&mut self) -> Option<&mut dyn Vector> {
+        Some(self)
+    }
+}
+impl Vector for Svg {
+    fn rect(&mut self, origin: Point, width: f64, height: f64, color: Pixel, properties: &Properties) {
+        let mut rect = svg::node::element::Rectangle::new()
+            .set("x", origin.x)
+            .set("y", origin.y)
+            .set("width", width)
+            .set("height", height)
+            .set("fill", format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]));
+        if color[3] != 255 {
+            rect = rect.set("fill-opacity", color[3] as f64 / 255.0);
+        }
+        self.document.append(svg_properties(rect, origin, width, height, properties));
+    }
+    fn image(&mut self, origin: Point, image: &mut Image, properties: &Properties) {
+        let href = match &image.src {
+            Some(src) if self.link_images => src.display().to_string(),
+            _ => format!("data:image/png;base64,{}", BASE64.encode(encode_png(image))),
+        };
+        let element = svg::node::element::Image::new()
+            .set("x", origin.x)
+            .set("y", origin.y)
+            .set("width", image.width)
+            .set("height", image.height)
+            .set("preserveAspectRatio", "none")
+            .set("href", href);
+        let (width, height) = (image.width as f64, image.height as f64);
+        self.document.append(svg_properties(element, origin, width, height, properties));
+    }
+    fn text(&mut self, origin: Point, text: &TextBox, color: Pixel, properties: &Properties) {
+        let font = text.font();
+        if !self.fonts.iter().any(|src| src == font.src()) {
+            self.fonts.push(font.src().to_path_buf());
+            self.document.append(svg::node::element::Style::new(format!(
+                "@font-face {{ font-family: \"{}\"; src: url(\"{}\"); }}",
+                font.id(),
+                font.src().display()
+            )));
+        }
+        let mut group = svg::node::element::Group::new()
+            .set("font-family", format!("\"{}\", sans-serif", font.id()))
+            .set("font-size", text.size)
+            .set("fill", format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]));
+        if color[3] != 255 {
+            group = group.set("fill-opacity", color[3] as f64 / 255.0);
+        }
+        for line in text.layout() {
+            let mut element = svg::node::element::Text::new(line.text)
+                .set("x", origin.x + line.x as f64)
+                .set("y", origin.y + line.baseline as f64)
+                .set("textLength", line.width)
+                .set("lengthAdjust", "spacingAndGlyphs");
+            if line.rtl {
+                // Right to left text ends on its left, so anchor its end at x.
+                element = element.set("direction", "rtl").set("text-anchor", "end");
+            }
+            group.append(element);
+        }
+        let (width, height) = (text.width as f64, text.height as f64);
+        self.document.append(svg_properties(group, origin, width, height, properties));
+    }
+}
+
+/// Sets the opacity and the transform of the Svg element of an [Object] from its [Properties].
+fn svg_properties<T: svg::Node>(mut element: T, origin: Point, width: f64, height: f64, properties: &Properties) -> T {
+    if properties.opacity < 1.0 {
+        element.assign("opacity", properties.opacity.max(0.0));
+    }
+    if !properties.is_untransformed() {
+        let cx = origin.x + width / 2.0;
+        let cy = origin.y + height / 2.0;
+        element.assign(
+            "transform",
+            format!(
+                "translate({cx} {cy}) rotate({}) scale({}) translate({} {})",
+                properties.rotation, properties.scale, -cx, -cy
+            ),
         );
     }
+    element
+}
+
+/// Encodes an [Image] as an 8-bit Rgba Png in memory.
+pub fn encode_png(image: &mut Image) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(
+        &mut bytes,
+        image.width.try_into().unwrap(),
+        image.height.try_into().unwrap(),
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&image.to_vec()).unwrap();
+    writer.finish().unwrap();
+    bytes
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+/// Pdf Format.
+///
+/// The Image is written as a single page, with rects as vector paths, text in subsets
+/// of its fonts embedded as Type0 fonts, and raster Images as Flate streams, or Dct
+/// streams when a Jpg quality is set.
+/// The page measures one point per pixel unless a DPI or a page size is set.
+pub struct Pdf {
+    image: Image,
+    content: Vec<u8>,
+    images: Vec<PdfImage>,
+    fonts: Vec<PdfFont>,
+    alphas: Vec<f32>,
+    dpi: f64,
+    page_size: Option<(f64, f64)>,
+    quality: Option<u8>,
+}
+#[derive(Clone, Debug)]
+/// Encoded samples of a raster Image in a Pdf.
+struct PdfImage {
+    width: usize,
+    height: usize,
+    dct: bool,
+    data: Vec<u8>,
+    mask: Option<Vec<u8>>,
+}
+#[derive(Clone, Debug)]
+/// Font of text in a Pdf and the text of the glyphs shown with it, by glyph id.
+struct PdfFont {
+    font: Font,
+    glyphs: BTreeMap<u16, String>,
+}
+impl Pdf {
+    /// Creates a new Pdf Image.
+    pub fn new(width: usize, height: usize) -> Pdf {
+        Pdf {
+            image: Image::new(width, height),
+            content: Vec::new(),
+            images: Vec::new(),
+            fonts: Vec::new(),
+            alphas: Vec::new(),
+            dpi: 72.0,
+            page_size: None,
+            quality: None,
+        }
+    }
+    /// Sets the resolution of the Image in dots per inch.
+    pub fn set_dpi(&mut self, dpi: f64) {
+        self.dpi = dpi
+    }
+    /// Sets the size of the page in points, stretching the Image over it.
+    pub fn set_page_size(&mut self, width: f64, height: f64) {
+        self.page_size = Some((width, height))
+    }
+    /// Sets the quality of raster Images, encoding them as Jpg.
+    pub fn set_quality(&mut self, quality: u8) {
+        self.quality = Some(quality)
+    }
+    /// Returns the name of the graphics state filling with alpha.
+    fn alpha(&mut self, alpha: f32) -> String {
+        let idx = match self.alphas.iter().position(|a| *a == alpha) {
+            Some(idx) => idx,
+            None => {
+                self.alphas.push(alpha);
+                self.alphas.len() - 1
+            }
+        };
+        format!("Gs{idx}")
+    }
+    /// Returns the index of the embedded font.
+    fn font(&mut self, font: &Font) -> usize {
+        match self.fonts.iter().position(|pdf_font| pdf_font.font.src() == font.src()) {
+            Some(idx) => idx,
+            None => {
+                self.fonts.push(PdfFont { font: font.clone(), glyphs: BTreeMap::new() });
+                self.fonts.len() - 1
+            }
+        }
+    }
+    /// Starts drawing an [Object] with its [Properties] applied to the graphics state.
+    fn begin(&mut self, content: &mut pdf_writer::Content, origin: Point, width: f64, height: f64, alpha: f32, properties: &Properties) {
+        content.save_state();
+        if alpha < 1.0 {
+            let name = self.alpha(alpha.max(0.0));
+            content.set_parameters(pdf_writer::Name(name.as_bytes()));
+        }
+        if !properties.is_untransformed() {
+            let cx = (origin.x + width / 2.0) as f32;
+            let cy = (origin.y + height / 2.0) as f32;
+            let (sin, cos) = (properties.rotation as f32).to_radians().sin_cos();
+            let scale = properties.scale as f32;
+            content.transform([1.0, 0.0, 0.0, 1.0, cx, cy]);
+            content.transform([cos * scale, sin * scale, -sin * scale, cos * scale, 0.0, 0.0]);
+            content.transform([1.0, 0.0, 0.0, 1.0, -cx, -cy]);
+        }
+    }
+}
+impl Format for Pdf {
+    fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        use pdf_writer::{Content, Filter, Finish, Name, Rect, Ref};
+
+        let (width, height) = (self.image.width as f32, self.image.height as f32);
+        let (page_width, page_height) = match self.page_size {
+            Some((page_width, page_height)) => (page_width as f32, page_height as f32),
+            None => (width * 72.0 / self.dpi as f32, height * 72.0 / self.dpi as f32),
+        };
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let page_id = Ref::new(3);
+        let content_id = Ref::new(4);
+        let image_id = |idx: usize| Ref::new(5 + 2 * idx as i32);
+        let mask_id = |idx: usize| Ref::new(6 + 2 * idx as i32);
+        // Each font takes a Type0 font, its CID font, descriptor, font file and ToUnicode CMap.
+        let fonts_start = 5 + 2 * self.images.len() as i32;
+        let font_id = |idx: usize, object: i32| Ref::new(fonts_start + 5 * idx as i32 + object);
+
+        let mut pdf = pdf_writer::Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id).kids([page_id]).count(1);
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        let mut x_objects = resources.x_objects();
+        for idx in 0..self.images.len() {
+            x_objects.pair(Name(format!("Im{idx}").as_bytes()), image_id(idx));
+        }
+        x_objects.finish();
+        let mut fonts = resources.fonts();
+        for idx in 0..self.fonts.len() {
+            fonts.pair(Name(format!("F{idx}").as_bytes()), font_id(idx, 0));
+        }
+        fonts.finish();
+        let mut ext_g_states = resources.ext_g_states();
+        for (idx, alpha) in self.alphas.iter().enumerate() {
+            ext_g_states
+                .insert(Name(format!("Gs{idx}").as_bytes()))
+                .start::<pdf_writer::writers::ExtGraphicsState>()
+                .non_stroking_alpha(*alpha);
+        }
+        ext_g_states.finish();
+        resources.finish();
+        page.finish();
+
+        for (idx, image) in self.images.iter().enumerate() {
+            let mut xobject = pdf.image_xobject(image_id(idx), &image.data);
+            xobject.filter(if image.dct { Filter::DctDecode } else { Filter::FlateDecode });
+            xobject.width(image.width as i32);
+            xobject.height(image.height as i32);
+            xobject.color_space().device_rgb();
+            xobject.bits_per_component(8);
+            if image.mask.is_some() {
+                xobject.s_mask(mask_id(idx));
+            }
+            xobject.finish();
+            if let Some(mask) = &image.mask {
+                let mut s_mask = pdf.image_xobject(mask_id(idx), mask);
+                s_mask.filter(Filter::FlateDecode);
+                s_mask.width(image.width as i32);
+                s_mask.height(image.height as i32);
+                s_mask.color_space().device_gray();
+                s_mask.bits_per_component(8);
+            }
+        }
+        for (idx, pdf_font) in self.fonts.iter().enumerate() {
+            pdf_font.write(&mut pdf, [0, 1, 2, 3, 4].map(|object| font_id(idx, object)));
+        }
+
+        // Objects are drawn in pixels from the top left corner, so flip and scale to the page.
+        let mut content = Content::new();
+        content.save_state();
+        content.transform([page_width / width, 0.0, 0.0, -page_height / height, 0.0, page_height]);
+        let mut bytes = content.finish().into_vec();
+        bytes.push(b'\n');
+        bytes.extend_from_slice(&self.content);
+        let mut content = Content::new();
+        content.restore_state();
+        bytes.extend_from_slice(&content.finish());
+        pdf.stream(content_id, &miniz_oxide::deflate::compress_to_vec_zlib(&bytes, 6))
+            .filter(Filter::FlateDecode);
+
+        w.write_all(&pdf.finish()).map_err(Error::Write)
+    }
     fn image(&mut self) -> &mut Image {
         &mut self.image
     }
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+    fn vector(&mut self) -> Option<&mut dyn Vector> {
+        Some(self)
+    }
+}
+impl Vector for Pdf {
+    fn rect(&mut self, origin: Point, width: f64, height: f64, color: Pixel, properties: &Properties) {
+        let mut content = pdf_writer::Content::new();
+        let alpha = color[3] as f32 / 255.0 * properties.opacity as f32;
+        self.begin(&mut content, origin, width, height, alpha, properties);
+        content.set_fill_rgb(color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0);
+        content.rect(origin.x as f32, origin.y as f32, width as f32, height as f32);
+        content.fill_nonzero();
+        content.restore_state();
+        self.content.extend_from_slice(&content.finish());
+        self.content.push(b'\n');
+    }
+    fn image(&mut self, origin: Point, image: &mut Image, properties: &Properties) {
+        let pixels = image.as_pixels();
+        let rgb = channel_data(&pixels, &[0, 1, 2]);
+        // Jpg sizes fit in 16 bits, so larger Images are compressed losslessly.
+        let jpg = match (self.quality, u16::try_from(image.width), u16::try_from(image.height)) {
+            (Some(quality), Ok(width), Ok(height)) => {
+                let mut data = Vec::new();
+                let encoder = jpg::Encoder::new(&mut data, quality);
+                encoder.encode(&rgb, width, height, jpg::ColorType::Rgb).ok().map(|_| data)
+            }
+            _ => None,
+        };
+        let (dct, data) = match jpg {
+            Some(data) => (true, data),
+            None => (false, miniz_oxide::deflate::compress_to_vec_zlib(&rgb, 6)),
+        };
+        let mask = (!is_opaque(&pixels))
+            .then(|| miniz_oxide::deflate::compress_to_vec_zlib(&channel_data(&pixels, &[3]), 6));
+        let (width, height) = (image.width, image.height);
+        self.images.push(PdfImage { width, height, dct, data, mask });
+
+        let mut content = pdf_writer::Content::new();
+        self.begin(&mut content, origin, width as f64, height as f64, properties.opacity as f32, properties);
+        // Images fill the unit square upwards, so flip them back.
+        content.transform([
+            width as f32,
+            0.0,
+            0.0,
+            -(height as f32),
+            origin.x as f32,
+            (origin.y + height as f64) as f32,
+        ]);
+        let name = format!("Im{}", self.images.len() - 1);
+        content.x_object(pdf_writer::Name(name.as_bytes()));
+        content.restore_state();
+        self.content.extend_from_slice(&content.finish());
+        self.content.push(b'\n');
+    }
+    fn text(&mut self, origin: Point, text: &TextBox, color: Pixel, properties: &Properties) {
+        let mut content = pdf_writer::Content::new();
+        let alpha = color[3] as f32 / 255.0 * properties.opacity as f32;
+        self.begin(&mut content, origin, text.width as f64, text.height as f64, alpha, properties);
+        content.set_fill_rgb(color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0);
+        let idx = self.font(text.font());
+        let font = text.font().font_ref();
+        let glyph_metrics = font.glyph_metrics(&[]);
+        let units = glyph_metrics.units_per_em() as f32;
+        content.begin_text();
+        content.set_font(pdf_writer::Name(format!("F{idx}").as_bytes()), text.size);
+        for line in text.layout() {
+            // Glyphs raised or lowered from the baseline start a new run.
+            let mut runs: Vec<(f32, f32, Vec<_>)> = Vec::new();
+            for ((glyph_id, x, y), cluster) in line.glyphs.into_iter().zip(line.clusters) {
+                // A glyph keeps the text it first showed.
+                let text = self.fonts[idx].glyphs.entry(glyph_id).or_default();
+                if text.is_empty() {
+                    *text = cluster;
+                }
+                match runs.last_mut() {
+                    Some((_, run_y, glyphs)) if *run_y == y => glyphs.push((glyph_id, x)),
+                    _ => runs.push((x, y, vec![(glyph_id, x)])),
+                }
+            }
+            for (run_x, y, glyphs) in runs {
+                // The page points down, so flip the text back up.
+                let x = origin.x as f32 + line.x + run_x;
+                let y = origin.y as f32 + line.baseline - y;
+                content.set_text_matrix([1.0, 0.0, 0.0, -1.0, x, y]);
+                let mut shown = content.show_positioned();
+                let mut items = shown.items();
+                let mut pen = run_x;
+                for (glyph_id, x) in glyphs {
+                    // Adjustments are in thousandths of the font size, positive to the left.
+                    if (x - pen).abs() > 0.001 {
+                        items.adjust((pen - x) * 1000.0 / text.size);
+                    }
+                    items.show(pdf_writer::Str(&glyph_id.to_be_bytes()));
+                    pen = x + glyph_metrics.advance_width(glyph_id) * text.size / units;
+                }
+            }
+        }
+        content.end_text();
+        content.restore_state();
+        self.content.extend_from_slice(&content.finish());
+        self.content.push(b'\n');
+    }
+}
+impl PdfFont {
+    /// Writes the Type0 font, its CID font, descriptor, subset font file and ToUnicode
+    /// CMap to the objects ids.
+    ///
+    /// The subset keeps the glyph ids of the font, so they are shown as its CIDs.
+    fn write(&self, pdf: &mut pdf_writer::Pdf, ids: [pdf_writer::Ref; 5]) {
+        use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+        use pdf_writer::{Filter, Finish, Name, Rect, Str};
+
+        let [type0_id, cid_id, descriptor_id, file_id, cmap_id] = ids;
+        let font = self.font.font_ref();
+        let metrics = font.metrics(&[]);
+        let glyph_metrics = font.glyph_metrics(&[]);
+        let scale = 1000.0 / metrics.units_per_em as f32;
+        // The subset keeps the glyph 0 shown for missing characters.
+        let mut glyph_ids: Vec<u16> = self.glyphs.keys().copied().collect();
+        if glyph_ids.first() != Some(&0) {
+            glyph_ids.insert(0, 0);
+        }
+        let data = subsetter::subset(self.font.data(), 0, subsetter::Profile::pdf(&glyph_ids))
+            .unwrap_or_else(|_| self.font.data().to_vec());
+        let true_type = font.table(swash::tag_from_bytes(b"glyf")).is_some();
+        // Subsets are named with a tag of six capitals, here derived from their glyphs.
+        let hash = glyph_ids.iter().fold(2166136261u32, |hash, id| (hash ^ *id as u32).wrapping_mul(16777619));
+        let tag: String = (0..6).map(|i| (b'A' + (hash >> (i * 5) & 31) as u8 % 26) as char).collect();
+        let name = font
+            .localized_strings()
+            .find_by_id(swash::StringId::PostScript, None)
+            .map_or_else(|| self.font.id().to_string(), |name| name.to_string());
+        let base_font = format!("{tag}+{}", name.replace(' ', ""));
+        let system_info = SystemInfo { registry: Str(b"Adobe"), ordering: Str(b"Identity"), supplement: 0 };
+
+        pdf.type0_font(type0_id)
+            .base_font(Name(base_font.as_bytes()))
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_id)
+            .to_unicode(cmap_id);
+        let mut cid_font = pdf.cid_font(cid_id);
+        cid_font.subtype(if true_type { CidFontType::Type2 } else { CidFontType::Type0 });
+        cid_font.base_font(Name(base_font.as_bytes()));
+        cid_font.system_info(system_info);
+        cid_font.font_descriptor(descriptor_id);
+        if true_type {
+            cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+        }
+        let mut widths = cid_font.widths();
+        for glyph_id in &glyph_ids {
+            widths.consecutive(*glyph_id, [glyph_metrics.advance_width(*glyph_id) * scale]);
+        }
+        widths.finish();
+        cid_font.finish();
+
+        let mut descriptor = pdf.font_descriptor(descriptor_id);
+        descriptor
+            .name(Name(base_font.as_bytes()))
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(Rect::new(0.0, -metrics.descent * scale, metrics.max_width * scale, metrics.ascent * scale))
+            .italic_angle(0.0)
+            .ascent(metrics.ascent * scale)
+            .descent(-metrics.descent * scale)
+            // Fonts without a cap height in their OS/2 table give zero.
+            .cap_height(if metrics.cap_height > 0.0 { metrics.cap_height } else { metrics.ascent } * scale)
+            .stem_v(80.0);
+        if true_type {
+            descriptor.font_file2(file_id);
+        } else {
+            descriptor.font_file3(file_id);
+        }
+        descriptor.finish();
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&data, 6);
+        let mut file = pdf.stream(file_id, &compressed);
+        file.filter(Filter::FlateDecode);
+        if true_type {
+            file.pair(Name(b"Length1"), data.len() as i32);
+        } else {
+            file.pair(Name(b"Subtype"), Name(b"OpenType"));
+        }
+        file.finish();
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+        for (glyph_id, text) in self.glyphs.iter().filter(|(glyph_id, text)| **glyph_id != 0 && !text.is_empty()) {
+            cmap.pair_with_multiple(*glyph_id, text.chars());
+        }
+        pdf.cmap(cmap_id, &cmap.finish()).name(Name(b"Custom")).system_info(system_info);
+    }
 }
@@ -5,16 +5,11 @@ use std::iter::{IntoIterator, Iterator};
 use std::ops::{Add, AddAssign};
 use std::ops::{Deref, DerefMut};
 use std::ops::{Index, IndexMut};
-use std::fs::read;
 use std::path::*;
 
-use file_format::FileFormat;
-use zune_core::colorspace::ColorSpace;
-use zune_core::options::DecoderOptions;
-use zune_jpeg::JpegDecoder;
-use zune_png::PngDecoder;
-
+use crate::decoder::*;
 use crate::object::*;
+use crate::parse::Error;
 
 #[allow(dead_code)]
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -388,6 +383,8 @@ pub struct Image {
     pub origin: Point,
     /// Layers in Image.
     pub layers: Vec<Layer>,
+    /// Source path of Image, if it was decoded from a file.
+    pub src: Option<PathBuf>,
 }
 impl Image {
     /// Creates a new [Image] instance.
@@ -397,6 +394,7 @@ impl Image {
             height,
             origin: Point::from((0, 0)),
             layers: vec![Layer::new(width, height)],
+            src: None,
         }
     }
     /// Collapses all layers to a single layer.
@@ -469,36 +467,8 @@ impl Image {
         bytes
     }
     /// Creates an Image from file on disk.
-    pub fn from_file<R: AsRef<Path>>(filename: R) -> Image {
-        let fmt = FileFormat::from_file(&filename).unwrap();
-
-        if fmt.media_type() == "image/png" {
-            let file_contents = read(filename).unwrap();
-            let options = DecoderOptions::default()
-                .png_set_add_alpha_channel(true)
-                .png_set_strip_to_8bit(true);
-            // use the above option to decode
-            let mut decoder = PngDecoder::new_with_options(&file_contents, options);
-        
-            let buf = decoder.decode_raw().unwrap();
-            let info = decoder.get_info().unwrap();
-            let width = info.width as usize;
-            let height = info.height as usize;
-            return Image::from(((width, height), (0,0), buf))
-        } 
-        else if fmt.media_type() == "image/jpeg" {
-            let file_contents = read(filename).unwrap();
-            let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGBA);
-        
-            let mut decoder = JpegDecoder::new_with_options(&file_contents, options);
-            let buf = decoder.decode().unwrap();
-            let info = decoder.info().unwrap();
-            let width = info.width as usize;
-            let height = info.height as usize;
-            return Image::from(((width, height), (0,0), buf))
-        } else {
-            panic!("I know it's an Image file, but I don't know what to do with it.")
-        }
+    pub fn from_file<R: AsRef<Path>>(filename: R) -> Result<Image, Error> {
+        Decoders::default().decode(filename, None)
     }
 }
 impl Default for Image {
@@ -513,6 +483,7 @@ impl From<((usize, usize), (usize, usize), Vec<u8>)> for Image {
             height: img.0 .1,
             origin: Point::from(img.1),
             layers: vec![Layer::from((img.0 .0, img.0 .1, img.2))],
+            src: None,
         }
     }
 }
@@ -0,0 +1,164 @@
+//! This module defines the flexbox style arrangement of the children of layout Objects.
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::unit::Bounds;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+/// Axis children are laid out along.
+pub enum Direction {
+    /// Left to right.
+    #[default]
+    Row,
+    /// Top to bottom.
+    Column,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+/// Distribution of the free space of a line along the main axis.
+pub enum Justify {
+    /// Children are packed at the start of the line.
+    #[default]
+    Start,
+    /// Children are packed at the centre of the line.
+    Center,
+    /// Children are packed at the end of the line.
+    End,
+    /// Free space is shared between children.
+    SpaceBetween,
+    /// Free space is shared around children, half as much at the ends.
+    SpaceAround,
+    /// Free space is shared evenly between children and the ends.
+    SpaceEvenly,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+/// Alignment of children along the cross axis of their line.
+pub enum Align {
+    /// Children are aligned at the start of the line.
+    #[default]
+    Start,
+    /// Children are centred on the line.
+    Center,
+    /// Children are aligned at the end of the line.
+    End,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// Arrangement of the children of a layout, in pixels.
+pub struct Flex {
+    /// Axis children are laid out along.
+    pub direction: Direction,
+    /// Space between children, and between lines.
+    pub gap: f64,
+    /// Space between the edges of the layout and its children.
+    pub padding: f64,
+    /// Distribution of the free space of a line along the main axis.
+    pub justify: Justify,
+    /// Alignment of children along the cross axis of their line.
+    pub align: Align,
+    /// Whether children overflowing the main axis wrap onto a new line.
+    pub wrap: bool,
+}
+impl Flex {
+    /// Returns the size along the main and cross axes of a size along x and y.
+    fn axes(&self, (width, height): (f64, f64)) -> (f64, f64) {
+        match self.direction {
+            Direction::Row => (width, height),
+            Direction::Column => (height, width),
+        }
+    }
+    /// Splits children of sizes into lines no longer than max along the main axis,
+    /// returning the indices of the first and past the last child of each line.
+    fn lines(&self, sizes: &[(f64, f64)], max: f64) -> Vec<(usize, usize)> {
+        let mut lines = Vec::new();
+        let (mut start, mut length) = (0, 0.0);
+        for (idx, size) in sizes.iter().enumerate() {
+            let main = self.axes(*size).0;
+            if self.wrap && idx > start && length + self.gap + main > max {
+                lines.push((start, idx));
+                (start, length) = (idx, main);
+            } else {
+                length += if idx > start { self.gap + main } else { main };
+            }
+        }
+        if start < sizes.len() {
+            lines.push((start, sizes.len()));
+        }
+        lines
+    }
+    /// Returns the main and cross lengths of a line of children of sizes.
+    fn line_size(&self, sizes: &[(f64, f64)]) -> (f64, f64) {
+        let main: f64 = sizes.iter().map(|size| self.axes(*size).0).sum();
+        let gaps = self.gap * sizes.len().saturating_sub(1) as f64;
+        let cross = sizes.iter().map(|size| self.axes(*size).1).fold(0.0, f64::max);
+        (main + gaps, cross)
+    }
+    /// Returns the size of a layout fitting children of sizes, wrapping lines at
+    /// max along the main axis.
+    pub fn measure(&self, sizes: &[(f64, f64)], max: f64) -> (f64, f64) {
+        let lines = self.lines(sizes, max - 2.0 * self.padding);
+        let (mut main, mut cross) = (0.0, 0.0);
+        for (start, end) in &lines {
+            let (line_main, line_cross) = self.line_size(&sizes[*start..*end]);
+            main = f64::max(main, line_main);
+            cross += line_cross;
+        }
+        cross += self.gap * lines.len().saturating_sub(1) as f64;
+        let (main, cross) = (main + 2.0 * self.padding, cross + 2.0 * self.padding);
+        match self.direction {
+            Direction::Row => (main, cross),
+            Direction::Column => (cross, main),
+        }
+    }
+    /// Returns the bounds of children of sizes arranged in the bounds of a layout.
+    pub fn arrange(&self, sizes: &[(f64, f64)], bounds: &Bounds) -> Vec<Bounds> {
+        let inner = Bounds::new(
+            bounds.x + self.padding,
+            bounds.y + self.padding,
+            (bounds.width - 2.0 * self.padding).max(0.0),
+            (bounds.height - 2.0 * self.padding).max(0.0),
+        );
+        let (inner_main, inner_cross) = self.axes((inner.width, inner.height));
+        let mut arranged = Vec::with_capacity(sizes.len());
+        let mut cross_start = 0.0;
+        for (start, end) in self.lines(sizes, inner_main) {
+            let line = &sizes[start..end];
+            let (line_main, mut line_cross) = self.line_size(line);
+            // A single line spans the layout, as in CSS.
+            if !self.wrap {
+                line_cross = line_cross.max(inner_cross);
+            }
+            let free = (inner_main - line_main).max(0.0);
+            let count = line.len() as f64;
+            let (mut main, spacing) = match self.justify {
+                Justify::Start => (0.0, self.gap),
+                Justify::Center => (free / 2.0, self.gap),
+                Justify::End => (free, self.gap),
+                Justify::SpaceBetween if line.len() > 1 => (0.0, self.gap + free / (count - 1.0)),
+                Justify::SpaceBetween => (0.0, self.gap),
+                Justify::SpaceAround => (free / count / 2.0, self.gap + free / count),
+                Justify::SpaceEvenly => (free / (count + 1.0), self.gap + free / (count + 1.0)),
+            };
+            for size in line {
+                let (child_main, child_cross) = self.axes(*size);
+                let cross = cross_start
+                    + match self.align {
+                        Align::Start => 0.0,
+                        Align::Center => (line_cross - child_cross) / 2.0,
+                        Align::End => line_cross - child_cross,
+                    };
+                arranged.push(match self.direction {
+                    Direction::Row => Bounds::new(inner.x + main, inner.y + cross, size.0, size.1),
+                    Direction::Column => Bounds::new(inner.x + cross, inner.y + main, size.0, size.1),
+                });
+                main += child_main + spacing;
+            }
+            cross_start += line_cross + self.gap;
+        }
+        arranged
+    }
+}
@@ -0,0 +1,43 @@
+#![warn(missing_docs)]
+//! Library for Generating Images from a Manifest.
+//!
+//! A Manifest can be rendered to an [Image] in-process with [render], or a
+//! [Canvas] can be built directly in Rust:
+//!
+//! ```no_run
+//! let manifest = rasm::parse::read_manifest(r#"
+//!     size = [1080, 1080]
+//!     color = [255, 255, 255, 255]
+//!     objects = [{ name = "rect", origin = [0, 40], offset = [100, 60], color = [20, 60, 120, 255] }]
+//! "#).unwrap();
+//! let image = rasm::render(&manifest).unwrap();
+//!
+//! let image = rasm::Canvas::builder()
+//!     .size(1080, 1080)
+//!     .rect((0.0, 40.0), (100.0, 60.0), [20, 60, 120, 255])
+//!     .render()
+//!     .unwrap();
+//! ```
+
+pub mod animation;
+pub mod asset;
+pub mod color;
+pub mod data;
+pub mod decoder;
+pub mod format;
+pub mod image;
+pub mod layout;
+pub mod object;
+pub mod parse;
+pub mod unit;
+pub mod util;
+pub mod vp8;
+pub mod palette;
+#[cfg(test)]
+pub mod tests;
+
+pub use crate::format::Format;
+pub use crate::image::Image;
+pub use crate::object::Object;
+pub use crate::parse::{render, Error};
+pub use crate::util::{Canvas, CanvasBuilder};
@@ -4,29 +4,123 @@
 //! # Usage
 //!
 //! ```bash
-//! rasm hadith.rasm.toml
+//! rasm hadith.manifest.toml
+//!
+//! rasm card.manifest.json
 //!
 //! rasm hadith/
+//!
+//! rasm hadith/ -o hadith.webp
+//!
+//! rasm hadith/ -o - | convert - hadith.gif
+//!
+//! generate-manifest | rasm - -o card.png
+//!
+//! rasm render hadith/ --set size=[540,540] --format webp --verbose
+//!
+//! rasm hadith/ --data hadiths.csv
+//!
+//! rasm watch hadith/
+//!
+//! rasm check hadith/
+//!
+//! rasm palette hadith/pic.jpg
+//!
+//! rasm init card/
+//!
+//! rasm info hadith/
+//!
+//! rasm schema > rasm.schema.json
 //! ```
 //!
 
-pub mod asset;
-pub mod format;
-pub mod image;
-pub mod object;
-pub mod parse;
-pub mod util;
-//pub mod palette;
-#[cfg(test)]
-pub mod tests;
-
 use std::env;
-use crate::parse::*;
+use std::process;
+
+use rasm::palette::{Palette, Target};
+use rasm::parse::*;
 
 /// The Generator.
 ///
 fn main() {
-    let file = parse_args(env::args());
-    // parse the Manifest file
-    parse_manifest(file);
+    let cli = match parse_args(env::args()) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("rasm: {err}");
+            eprintln!("Run rasm --help for usage.");
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(&cli) {
+        eprintln!("rasm: {err}");
+        process::exit(1);
+    }
+}
+
+/// Runs the subcommand of the command line.
+fn run(cli: &Cli) -> Result<(), Error> {
+    let quiet = cli.verbosity == Verbosity::Quiet;
+    match cli.command {
+        Command::Render => parse_manifest(cli)?,
+        Command::Watch => watch_manifest(cli)?,
+        Command::Check => {
+            let warnings = check_manifest(cli)?;
+            if !quiet {
+                for warning in &warnings {
+                    eprintln!("rasm: warning: {warning}");
+                }
+                println!("{}: ok", cli.file.display());
+            }
+        }
+        Command::Palette => {
+            let palette = Palette::from_image(load_image(cli)?).generate();
+            let mut swatches = palette.swatches().to_vec();
+            swatches.sort_by_key(|swatch| std::cmp::Reverse(swatch.population()));
+            let total: u32 = swatches.iter().map(|swatch| swatch.population()).sum();
+            let targets = [
+                ("light vibrant", Target::light_vibrant()),
+                ("vibrant", Target::vibrant()),
+                ("dark vibrant", Target::dark_vibrant()),
+                ("light muted", Target::light_muted()),
+                ("muted", Target::muted()),
+                ("dark muted", Target::dark_muted()),
+            ];
+            for swatch in swatches {
+                let (r, g, b) = swatch.rgb();
+                let names: Vec<&str> = targets
+                    .iter()
+                    .filter(|(_, target)| palette.get_swatch_for_target(*target) == Some(swatch))
+                    .map(|(name, _)| *name)
+                    .collect();
+                println!(
+                    "#{r:02x}{g:02x}{b:02x}  [{r}, {g}, {b}, 255]  {:5.1}%  {}",
+                    swatch.population() as f64 * 100.0 / total as f64,
+                    names.join(", ")
+                );
+            }
+        }
+        Command::Init => {
+            let file = init_manifest(&cli.file)?;
+            if !quiet {
+                println!("wrote {}", file.display());
+            }
+        }
+        Command::Info => match image_format(cli) {
+            Some(fmt) => {
+                let image = load_image(cli)?;
+                println!("{}: {} {}x{}", cli.file.display(), fmt.extension(), image.width, image.height);
+            }
+            None => {
+                let (manifests, _) = load_manifests(cli)?;
+                print!("{}", manifest_info(&manifests[0])?);
+                if manifests.len() > 1 {
+                    println!("rows: {}", manifests.len());
+                }
+            }
+        },
+        Command::Schema => println!("{}", manifest_schema()),
+        Command::Help => print!("{USAGE}"),
+        Command::Version => println!("rasm {}", env!("CARGO_PKG_VERSION")),
+    }
+    Ok(())
 }
@@ -1,6 +1,6 @@
 //! This module defines objects for the generator.
 use std::ops::{Add, AddAssign};
-use std::ops::{Index, IndexMut};
+use std::ops::{Index, IndexMut, Range};
 use std::ops::{Sub, SubAssign};
 
 use resize::Pixel::RGBA8;
@@ -8,30 +8,159 @@ use resize::Type::Lanczos3;
 //use rgb::RGBA8;
 use rgb::FromSlice;
 use swash::scale::StrikeWith;
-use swash::scale::{image::Image as Img, Render, ScaleContext, Source};
+use swash::scale::image::{Content, Image as Img};
+use swash::scale::{Render, ScaleContext, Source};
+use swash::shape::{Direction, ShapeContext};
+use swash::text::{BidiClass, Codepoint, Script};
 use swash::{zeno, FontRef, GlyphId};
 use zeno::{Format, Vector};
 
+use crate::asset::Font;
 use crate::image::*;
-//use crate::format::*;
+use crate::format::Png;
+use crate::parse::Error;
 use crate::util::*;
 
 /// The definition of an Object.
 pub trait Object {
     /// Draws the Object on the [Canvas].
-    fn draw(&mut self, canvas: &mut Canvas);
-    /// Resizes the [Object] using scale.
-    fn resize(&mut self, scale: [usize; 2]);
+    fn draw(&mut self, canvas: &mut Canvas) {
+        self.draw_with(canvas, &Properties::default())
+    }
+    /// Draws the Object on the [Canvas] with [Properties] applied.
+    fn draw_with(&mut self, canvas: &mut Canvas, properties: &Properties);
+    /// Resizes the [Object] using scale, failing if it would have no pixels.
+    fn resize(&mut self, scale: [usize; 2]) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Properties applied to an [Object] when drawing it.
+pub struct Properties {
+    /// Origin replacing the one of the Object.
+    pub origin: Option<Point>,
+    /// Color replacing the one of the Object.
+    pub color: Option<Pixel>,
+    /// Opacity, between 0 and 1.
+    pub opacity: f64,
+    /// Scale factor about the centre of the Object.
+    pub scale: f64,
+    /// Clockwise rotation in degrees about the centre of the Object.
+    pub rotation: f64,
+}
+impl Default for Properties {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Properties {
+    /// Creates Properties which leave the Object unchanged.
+    pub fn new() -> Properties {
+        Properties {
+            origin: None,
+            color: None,
+            opacity: 1.0,
+            scale: 1.0,
+            rotation: 0.0,
+        }
+    }
+    /// Returns true if the Object is neither scaled nor rotated.
+    pub fn is_untransformed(&self) -> bool {
+        self.scale == 1.0 && self.rotation % 360.0 == 0.0
+    }
+}
+
+/// Returns a [Pixel] with its alpha multiplied by opacity.
+pub fn fade(mut pixel: Pixel, opacity: f64) -> Pixel {
+    let alpha = (pixel[3] as f64 * opacity.clamp(0.0, 1.0)).round() as u8;
+    pixel.set_alpha(alpha);
+    pixel
+}
+
+/// Composites a sprite on a [Layer] with its top left corner at origin,
+/// scaled and rotated about its centre using bilinear sampling.
+pub fn composite(layer: &mut Layer, sprite: &Layer, origin: Point, properties: &Properties) {
+    let (rows, cols) = (sprite.height() as f64, sprite.width() as f64);
+    if rows == 0.0 || cols == 0.0 || properties.scale <= 0.0 {
+        return;
+    }
+    let centre = (origin.y + rows / 2.0, origin.x + cols / 2.0);
+    let (sin, cos) = properties.rotation.to_radians().sin_cos();
+    // Half extents of the transformed sprite.
+    let half_rows = (rows * cos.abs() + cols * sin.abs()) * properties.scale / 2.0;
+    let half_cols = (rows * sin.abs() + cols * cos.abs()) * properties.scale / 2.0;
+    let first_row = (centre.0 - half_rows).floor().max(0.0) as usize;
+    let last_row = ((centre.0 + half_rows).ceil().max(0.0) as usize).min(layer.height());
+    let first_col = (centre.1 - half_cols).floor().max(0.0) as usize;
+    let last_col = ((centre.1 + half_cols).ceil().max(0.0) as usize).min(layer.width());
+
+    let texel = |row: isize, col: isize| -> [f64; 4] {
+        if row < 0 || col < 0 || row >= rows as isize || col >= cols as isize {
+            return [0.0; 4];
+        }
+        let pixel = sprite[row as usize][col as usize];
+        let alpha = pixel[3] as f64 / 255.0;
+        // Premultiply so transparent neighbours don't darken edges.
+        [pixel[0] as f64 * alpha, pixel[1] as f64 * alpha, pixel[2] as f64 * alpha, alpha]
+    };
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            let dy = row as f64 + 0.5 - centre.0;
+            let dx = col as f64 + 0.5 - centre.1;
+            let y = (dy * cos - dx * sin) / properties.scale + rows / 2.0 - 0.5;
+            let x = (dy * sin + dx * cos) / properties.scale + cols / 2.0 - 0.5;
+            if y <= -1.0 || x <= -1.0 || y >= rows || x >= cols {
+                continue;
+            }
+            let (y0, x0) = (y.floor(), x.floor());
+            let (fy, fx) = (y - y0, x - x0);
+            let (y0, x0) = (y0 as isize, x0 as isize);
+            let mut sum = [0.0; 4];
+            for (dr, dc, weight) in [
+                (0, 0, (1.0 - fy) * (1.0 - fx)),
+                (0, 1, (1.0 - fy) * fx),
+                (1, 0, fy * (1.0 - fx)),
+                (1, 1, fy * fx),
+            ] {
+                let value = texel(y0 + dr, x0 + dc);
+                for i in 0..4 {
+                    sum[i] += value[i] * weight;
+                }
+            }
+            if sum[3] <= 0.0 {
+                continue;
+            }
+            let pixel = Pixel::from(&[
+                (sum[0] / sum[3]).round() as u8,
+                (sum[1] / sum[3]).round() as u8,
+                (sum[2] / sum[3]).round() as u8,
+                (sum[3] * 255.0).round() as u8,
+            ]);
+            layer[row][col] += fade(pixel, properties.opacity);
+        }
+    }
+}
+
+/// Returns the rows and columns of a box of size with its top left corner on the
+/// pixel at origin which lie on layer, counting from the corner of the box.
+///
+/// Parts of the box off the Layer, including at negative origins, are clipped.
+pub fn visible(layer: &Layer, origin: (isize, isize), size: (usize, usize)) -> (Range<usize>, Range<usize>) {
+    let clip = |origin: isize, length: usize, bound: usize| {
+        let start = (-origin).clamp(0, length as isize) as usize;
+        let end = (bound as isize - origin).clamp(start as isize, length as isize) as usize;
+        start..end
+    };
+    (clip(origin.1, size.1, layer.height()), clip(origin.0, size.0, layer.width()))
 }
 
 #[allow(dead_code)]
 #[derive(Debug, Copy, Clone, PartialEq)]
-/// A Point on a Cartesian plane.
+/// A Point on a Cartesian plane, in pixels which may be fractional.
 pub struct Point {
     /// x coordinate.
-    pub x: usize,
+    pub x: f64,
     /// y coordinate.
-    pub y: usize,
+    pub y: f64,
 }
 impl Default for Point {
     fn default() -> Self {
@@ -42,11 +171,27 @@ impl Default for Point {
 impl Point {
     /// Creates a new Point.
     pub fn new() -> Point {
-        Point { x: 0, y: 0 }
+        Point { x: 0.0, y: 0.0 }
+    }
+    /// Returns true if the Point lies on the corner of a pixel.
+    pub fn is_whole(&self) -> bool {
+        self.x.fract() == 0.0 && self.y.fract() == 0.0
+    }
+    /// Returns the pixel the Point lies on, which may be off an Image.
+    pub fn pixel(&self) -> (isize, isize) {
+        (self.x.floor() as isize, self.y.floor() as isize)
     }
 }
 impl From<(usize, usize)> for Point {
     fn from(point: (usize, usize)) -> Self {
+        Point {
+            x: point.0 as f64,
+            y: point.1 as f64,
+        }
+    }
+}
+impl From<(f64, f64)> for Point {
+    fn from(point: (f64, f64)) -> Self {
         Point {
             x: point.0,
             y: point.1,
@@ -54,7 +199,7 @@ impl From<(usize, usize)> for Point {
     }
 }
 impl Index<usize> for Point {
-    type Output = usize;
+    type Output = f64;
 
     fn index(&self, idx: usize) -> &Self::Output {
         match idx {
@@ -121,9 +266,9 @@ pub struct Rect {
     /// Color of Rectangle.
     pub color: Pixel,
     /// Width of Rectangle.
-    pub width: usize,
+    pub width: f64,
     /// Height of Rectangle.
-    pub height: usize,
+    pub height: f64,
 }
 impl Default for Rect {
     fn default() -> Self {
@@ -137,18 +282,23 @@ impl Rect {
         Rect {
             origin: Point::new(),
             color: Pixel::new(),
-            width: 0,
-            height: 0,
+            width: 0.0,
+            height: 0.0,
         }
     }
     /// Creates Rectangle using coordinates.
     pub fn with_coordinates(a: (usize, usize), c: (usize, usize)) -> Rect {
+        Rect::with_corners((a.0 as f64, a.1 as f64), (c.0 as f64, c.1 as f64))
+    }
+    /// Creates Rectangle from its top left corner a to its bottom right corner c,
+    /// in pixels which may be fractional.
+    pub fn with_corners(a: (f64, f64), c: (f64, f64)) -> Rect {
         let pixel: &[u8] = &[0, 0, 0, 255];
         Rect {
             origin: Point::from(a),
             color: Pixel::from(pixel),
-            width: c.1 - a.1,
-            height: c.0 - a.0,
+            width: (c.0 - a.0).max(0.0),
+            height: (c.1 - a.1).max(0.0),
         }
     }
     /// Sets color of Rectangle.
@@ -158,9 +308,11 @@ impl Rect {
     /// Paste Rectangle on [Image]
     pub fn paste(&self, img: &mut Image) {
         let mut layer = Layer::new(img.width(), img.height());
-        for rows in 0..self.height {
-            for pixels in 0..self.width {
-                layer[self.origin[0] + rows][self.origin[1] + pixels] = self.color;
+        let (x, y) = self.origin.pixel();
+        let (rows, cols) = visible(&layer, (x, y), (self.width as usize, self.height as usize));
+        for rows in rows {
+            for pixels in cols.clone() {
+                layer[(y + rows as isize) as usize][(x + pixels as isize) as usize] = self.color;
             }
         }
         img.add_layer(layer)
@@ -172,42 +324,78 @@ impl From<(usize, usize, usize, usize, u8, u8, u8, u8)> for Rect {
         Rect {
             origin: Point::from((i.0, i.1)),
             color: Pixel::from(pixel),
-            width: i.3 - i.1,
-            height: i.2 - i.0,
+            width: (i.2 - i.0) as f64,
+            height: (i.3 - i.1) as f64,
         }
     }
 }
 impl Object for Rect {
-    fn draw(&mut self, canvas: &mut Canvas) {
+    fn draw_with(&mut self, canvas: &mut Canvas, properties: &Properties) {
+        let origin = properties.origin.unwrap_or(self.origin);
+        if let Some(vector) = canvas.fmt().vector() {
+            let color = properties.color.unwrap_or(self.color);
+            vector.rect(origin, self.width, self.height, color, properties);
+            return;
+        }
         let layer = &mut canvas.fmt().image()[0];
-        for rows in 0..self.height {
-            for pixels in 0..self.width {
-                layer[self.origin[0] + rows][self.origin[1] + pixels] += self.color;
+        let color = fade(properties.color.unwrap_or(self.color), properties.opacity);
+        if !properties.is_untransformed() {
+            let mut sprite = Layer::new(self.width.round() as usize, self.height.round() as usize);
+            sprite.fill(color);
+            composite(layer, &sprite, origin, &Properties { opacity: 1.0, ..properties.clone() });
+            return;
+        }
+        // Pixels the edges pass through are covered by the fraction of them inside the Rectangle.
+        let coverage = |pixel: usize, start: f64, end: f64| {
+            (end.min(pixel as f64 + 1.0) - start.max(pixel as f64)).clamp(0.0, 1.0)
+        };
+        let (right, bottom) = (origin.x + self.width, origin.y + self.height);
+        let first_row = origin.y.floor().max(0.0) as usize;
+        let last_row = (bottom.ceil().max(0.0) as usize).min(layer.height());
+        let first_col = origin.x.floor().max(0.0) as usize;
+        let last_col = (right.ceil().max(0.0) as usize).min(layer.width());
+        for rows in first_row..last_row {
+            let height = coverage(rows, origin.y, bottom);
+            for pixels in first_col..last_col {
+                let covered = height * coverage(pixels, origin.x, right);
+                layer[rows][pixels] += if covered < 1.0 { fade(color, covered) } else { color };
             }
         }
     }
-    fn resize(&mut self, _scale: [usize; 2]) {}
+    fn resize(&mut self, _scale: [usize; 2]) -> Result<(), Error> {
+        Ok(())
+    }
 }
 impl Object for Image {
-    fn draw(&mut self, canvas: &mut Canvas) {
+    fn draw_with(&mut self, canvas: &mut Canvas, properties: &Properties) {
+        let origin = properties.origin.unwrap_or(self.origin);
+        if let Some(vector) = canvas.fmt().vector() {
+            vector.image(origin, self, properties);
+            return;
+        }
         let layer = &mut canvas.fmt().image()[0];
         let lay = &self.layers[0];
-        for rows in 0..self.height {
-            for pixels in 0..self.width {
-                layer[self.origin[0] + rows][self.origin[1] + pixels] += lay[rows][pixels];
+        // Images between pixels are resampled onto them.
+        if !properties.is_untransformed() || !origin.is_whole() {
+            composite(layer, lay, origin, properties);
+            return;
+        }
+        let (x, y) = origin.pixel();
+        let (rows, cols) = visible(layer, (x, y), (self.width, self.height));
+        for rows in rows {
+            for pixels in cols.clone() {
+                layer[(y + rows as isize) as usize][(x + pixels as isize) as usize] +=
+                    fade(lay[rows][pixels], properties.opacity);
             }
         }
     }
-    fn resize(&mut self, scale: [usize; 2]) {
+    fn resize(&mut self, scale: [usize; 2]) -> Result<(), Error> {
         let w1 = self.width;
         let h1 = self.height;
-        if scale[0] == 0 && scale[1] == 0 {
-            panic!()
-        }
         let mut w2 = scale[0];
         let mut h2 = scale[1];
         if w1 == w2 && h1 == h2 {
-            return;
+            return Ok(());
         }
         if scale[0] == 0 {
             w2 = (w1 as f64 * (h2 as f64 / h1 as f64)) as usize;
@@ -215,8 +403,9 @@ impl Object for Image {
         if scale[1] == 0 {
             h2 = (h1 as f64 * (w2 as f64 / w1 as f64)) as usize;
         }
-        self.width = w2;
-        self.height = h2;
+        if w2 == 0 || h2 == 0 {
+            return Err(Error::Invalid(format!("cannot resize a {w1}x{h1} image to {w2}x{h2} pixels")));
+        }
 
         // Don't forget to fill `src` with image data (RGB8).
         let _src = self.to_vec();
@@ -225,10 +414,11 @@ impl Object for Image {
         let mut _dst = vec![0; w2 * h2 * 4];
         let dst = _dst.as_rgba_mut();
         // Create reusable instance.
-        let mut resizer = resize::new(w1, h1, w2, h2, RGBA8, Lanczos3).unwrap();
+        let resize_error = |err| Error::Invalid(format!("cannot resize a {w1}x{h1} image: {err}"));
+        let mut resizer = resize::new(w1, h1, w2, h2, RGBA8, Lanczos3).map_err(resize_error)?;
         // Do resize without heap allocations.
         // Might be executed multiple times for different `src` or `dst`.
-        let _ = resizer.resize(src, dst);
+        resizer.resize(src, dst).map_err(resize_error)?;
         //println!("{:?}", r);
         //println!("{}, {} / {}, {}", w1, h1, w2, h2);
         let mut buf = Vec::new();
@@ -237,32 +427,175 @@ impl Object for Image {
                 buf.push(i)
             }
         }
-        self.layers = vec![Layer::from((w2, h2, buf))]
+        self.width = w2;
+        self.height = h2;
+        self.layers = vec![Layer::from((w2, h2, buf))];
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+/// A line of a [TextBox] laid out for drawing.
+pub struct TextLine {
+    /// Text of the line.
+    pub text: String,
+    /// Distance of the left end of the line from the left edge of the TextBox.
+    pub x: f32,
+    /// Distance of the baseline from the top edge of the TextBox.
+    pub baseline: f32,
+    /// Advance width of the line.
+    pub width: f32,
+    /// True if the line runs right to left.
+    pub rtl: bool,
+    /// Glyphs of the line, positioned from the left end of its baseline, y pointing up.
+    pub glyphs: Vec<(GlyphId, f32, f32)>,
+    /// Text each glyph shows: the characters of its cluster for the first glyph of
+    /// the cluster, and nothing for the others.
+    pub clusters: Vec<String>,
+}
+
 #[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 /// A Text Box.
+///
+/// Text is shaped with its Font, wrapped at spaces to the width of the box
+/// and aligned to the side its script starts from.
 pub struct TextBox {
-    /// Text content.
+    /// Text content. Line feeds start new paragraphs.
     pub content: String,
     /// Point of origin.
-    pub origin: (usize, usize),
-    /// 
-    pub offset: (usize, usize),
-    /// Image representation of Text box characters
-    pub glyphs: Vec<Img>,
+    pub origin: Point,
+    /// Width of Text box.
+    pub width: usize,
+    /// Height of Text box.
+    pub height: usize,
+    /// Font of Text.
+    pub font: Option<Font>,
+    /// Size of Text in pixels per em.
+    pub size: f32,
+    /// Color of Text.
+    pub color: Pixel,
 }
 impl TextBox {
     /// Creates a new Text box.
     pub fn new(content: String, origin: (usize, usize), offset: (usize, usize)) -> TextBox {
         TextBox {
             content,
-            origin,
-            offset,
-            glyphs: vec![],
+            origin: Point::from(origin),
+            width: offset.0 - origin.0,
+            height: offset.1 - origin.1,
+            font: None,
+            size: 16.0,
+            color: Pixel::from(&[0, 0, 0, 255]),
+        }
+    }
+    /// Sets font and size in pixels per em of Text.
+    pub fn set_font(&mut self, font: Font, size: f32) {
+        self.font = Some(font);
+        self.size = size
+    }
+    /// Sets color of Text.
+    pub fn set_color(&mut self, pixel: Pixel) {
+        self.color = pixel
+    }
+    /// Returns the Font of Text, panicking if it has none.
+    pub fn font(&self) -> &Font {
+        self.font.as_ref().expect("text box has no font")
+    }
+    /// Shapes and wraps the content into lines.
+    pub fn layout(&self) -> Vec<TextLine> {
+        let font = self.font().font_ref();
+        let metrics = font.metrics(&[]).scale(self.size);
+        let line_height = metrics.ascent + metrics.descent + metrics.leading;
+        let mut context = ShapeContext::new();
+        let mut lines = Vec::new();
+        for paragraph in self.content.lines() {
+            // The first character with a strong direction sets the direction of the paragraph.
+            let rtl = paragraph
+                .chars()
+                .map(|ch| ch.bidi_class())
+                .find(|class| matches!(class, BidiClass::L | BidiClass::R | BidiClass::AL))
+                .is_some_and(|class| class != BidiClass::L);
+            let mut text = String::new();
+            for word in paragraph.split(' ') {
+                if text.is_empty() {
+                    text.push_str(word);
+                    continue;
+                }
+                let candidate = format!("{text} {word}");
+                let (_, _, width) = shape(&mut context, font, self.size, &candidate, rtl);
+                if width > self.width as f32 {
+                    lines.push((std::mem::replace(&mut text, word.to_string()), rtl));
+                } else {
+                    text = candidate;
+                }
+            }
+            lines.push((text, rtl));
+        }
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (text, rtl))| {
+                let (glyphs, clusters, width) = shape(&mut context, font, self.size, &text, rtl);
+                TextLine {
+                    text,
+                    x: if rtl { self.width as f32 - width } else { 0.0 },
+                    baseline: metrics.ascent + idx as f32 * line_height,
+                    width,
+                    rtl,
+                    glyphs,
+                    clusters,
+                }
+            })
+            .collect()
+    }
+    /// Returns the width of the widest line and the height of the lines of the Text.
+    pub fn measure(&self) -> (f32, f32) {
+        let metrics = self.font().font_ref().metrics(&[]).scale(self.size);
+        let lines = self.layout();
+        let width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        (width, lines.len() as f32 * (metrics.ascent + metrics.descent + metrics.leading))
+    }
+    /// Renders the Text in color on a [Layer] the size of the Text box.
+    pub fn render(&self, color: Pixel) -> Layer {
+        let font = self.font().font_ref();
+        let mut context = ScaleContext::new();
+        let mut layer = Layer::new(self.width, self.height);
+        layer.fill(Pixel::from(&[0, 0, 0, 0]));
+        for line in self.layout() {
+            for (glyph_id, x, y) in line.glyphs {
+                let x = line.x + x;
+                let y = (line.baseline - y).round();
+                let Some(glyph) = TextBox::render_glyph(&mut context, &font, self.size, false, glyph_id, x, 0.0) else {
+                    continue;
+                };
+                let left = x.floor() as isize + glyph.placement.left as isize;
+                let top = y as isize - glyph.placement.top as isize;
+                let (width, height) = (glyph.placement.width as usize, glyph.placement.height as usize);
+                for rows in 0..height {
+                    for pixels in 0..width {
+                        let (row, col) = (top + rows as isize, left + pixels as isize);
+                        if row < 0 || col < 0 || row as usize >= layer.height() || col as usize >= layer.width() {
+                            continue;
+                        }
+                        let idx = rows * width + pixels;
+                        let below = &mut layer[row as usize][col as usize];
+                        *below = match glyph.content {
+                            Content::Color if below[3] == 0 => Pixel::from(&glyph.data[idx * 4..idx * 4 + 4]),
+                            Content::Color => *below + Pixel::from(&glyph.data[idx * 4..idx * 4 + 4]),
+                            // Keep the color straight where glyphs overlap, as compositing on
+                            // transparent pixels would darken it.
+                            _ => {
+                                let mut pixel = fade(color, glyph.data[idx] as f64 / 255.0);
+                                pixel.set_alpha(pixel[3].max(below[3]));
+                                pixel
+                            }
+                        };
+                    }
+                }
+            }
         }
+        layer
     }
     /// Render a Glyph character.
     pub fn render_glyph(
@@ -285,11 +618,125 @@ impl TextBox {
             Source::ColorBitmap(StrikeWith::BestFit),
             Source::Outline,
         ])
-        // Select a subpixel format
-        .format(Format::Subpixel)
+        // Coverage only, as the Image may not be shown on an Lcd
+        .format(Format::Alpha)
         // Apply the fractional offset
         .offset(offset)
         // Render the image
         .render(&mut scaler, glyph_id)
     }
 }
+impl Object for TextBox {
+    fn draw_with(&mut self, canvas: &mut Canvas, properties: &Properties) {
+        let origin = properties.origin.unwrap_or(self.origin);
+        let color = properties.color.unwrap_or(self.color);
+        if let Some(vector) = canvas.fmt().vector() {
+            vector.text(origin, self, color, properties);
+            return;
+        }
+        let sprite = self.render(color);
+        let layer = &mut canvas.fmt().image()[0];
+        if !properties.is_untransformed() {
+            composite(layer, &sprite, origin, properties);
+            return;
+        }
+        // Glyphs are placed on whole pixels, as their baselines are.
+        let (x, y) = (origin.x.round() as isize, origin.y.round() as isize);
+        let (rows, cols) = visible(layer, (x, y), (self.width, self.height));
+        for rows in rows {
+            for pixels in cols.clone() {
+                layer[(y + rows as isize) as usize][(x + pixels as isize) as usize] +=
+                    fade(sprite[rows][pixels], properties.opacity);
+            }
+        }
+    }
+    fn resize(&mut self, _scale: [usize; 2]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Objects drawn through the alpha of mask Objects.
+///
+/// Vector formats draw the Objects unmasked.
+pub struct Masked {
+    /// Objects drawn, in order.
+    pub objects: Vec<Box<dyn Object>>,
+    /// Objects whose alpha masks the others.
+    pub mask: Vec<Box<dyn Object>>,
+}
+impl Masked {
+    /// Creates Objects masked by the Objects of mask.
+    pub fn new(objects: Vec<Box<dyn Object>>, mask: Vec<Box<dyn Object>>) -> Masked {
+        Masked { objects, mask }
+    }
+}
+impl Object for Masked {
+    fn draw_with(&mut self, canvas: &mut Canvas, properties: &Properties) {
+        if canvas.fmt().vector().is_some() {
+            for object in &mut self.objects {
+                object.draw_with(canvas, properties);
+            }
+            return;
+        }
+        let sprite = offscreen(canvas, |canvas| {
+            for object in &mut self.objects {
+                object.draw_with(canvas, properties);
+            }
+        });
+        let mask = offscreen(canvas, |canvas| {
+            for object in &mut self.mask {
+                object.draw(canvas);
+            }
+        });
+        let layer = &mut canvas.fmt().image()[0];
+        for rows in 0..layer.height() {
+            for pixels in 0..layer.width() {
+                let alpha = mask[rows][pixels][3];
+                if alpha > 0 {
+                    layer[rows][pixels] += fade(sprite[rows][pixels], alpha as f64 / 255.0);
+                }
+            }
+        }
+    }
+    fn resize(&mut self, _scale: [usize; 2]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Draws on a transparent [Layer] the size of canvas, at its frame.
+fn offscreen(canvas: &Canvas, draw: impl FnOnce(&mut Canvas)) -> Layer {
+    let mut offscreen = Canvas::with_format(Box::new(Png::new(canvas.width, canvas.height)));
+    offscreen.frame = canvas.frame;
+    offscreen.fmt().image()[0].fill(Pixel::from(&[0, 0, 0, 0]));
+    draw(&mut offscreen);
+    offscreen.fmt().image()[0].clone()
+}
+
+/// Shapes a run of text in one direction, returning its positioned glyphs, the text each shows and its advance width.
+fn shape(context: &mut ShapeContext, font: FontRef, size: f32, text: &str, rtl: bool) -> (Vec<(GlyphId, f32, f32)>, Vec<String>, f32) {
+    let script = text
+        .chars()
+        .map(|ch| ch.script())
+        .find(|script| !matches!(script, Script::Common | Script::Inherited | Script::Unknown))
+        .unwrap_or(Script::Latin);
+    let direction = if rtl { Direction::RightToLeft } else { Direction::LeftToRight };
+    let mut shaper = context.builder(font).script(script).direction(direction).size(size).build();
+    shaper.add_str(text);
+    let mut clusters = Vec::new();
+    shaper.shape_with(|cluster| clusters.push((cluster.glyphs.to_vec(), &text[cluster.source.to_range()])));
+    // Clusters come in logical order, so reverse right to left runs into visual order.
+    if rtl {
+        clusters.reverse();
+    }
+    let mut glyphs = Vec::new();
+    let mut texts = Vec::new();
+    let mut advance = 0.0;
+    for (cluster, source) in clusters {
+        for (idx, glyph) in cluster.iter().enumerate() {
+            glyphs.push((glyph.id, advance + glyph.x, glyph.y));
+            texts.push(if idx == 0 { source.to_string() } else { String::new() });
+            advance += glyph.advance;
+        }
+    }
+    (glyphs, texts, advance)
+}
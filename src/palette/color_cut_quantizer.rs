@@ -6,6 +6,9 @@ use std::collections::{BinaryHeap, HashMap};
 const QUANTIZE_WORD_WIDTH: u32 = 5;
 const QUANTIZE_WORD_MAX: u8 = (1 << QUANTIZE_WORD_WIDTH) - 1;
 
+/// A quantized color.
+type Rgb = (u8, u8, u8);
+
 pub struct ColorCutQuantizer {
     pixels: Vec<Pixel>,
     max_colors: usize,
@@ -13,7 +16,7 @@ pub struct ColorCutQuantizer {
 }
 
 struct Vbox<'a> {
-    colors: &'a mut [(Pixel, u32)],
+    colors: &'a mut [(Rgb, u32)],
     population: u32,
     red_range: (u8, u8),
     green_range: (u8, u8),
@@ -39,7 +42,11 @@ impl ColorCutQuantizer {
         // begin by generating a histogram of quantized pixel values
         let mut hist = HashMap::new();
         for pixel in self.pixels.iter() {
-            let pixel = pixel.map(|channel| modify_width(channel, 8, QUANTIZE_WORD_WIDTH) as u8);
+            let pixel = (
+                modify_width(pixel[0], 8, QUANTIZE_WORD_WIDTH),
+                modify_width(pixel[1], 8, QUANTIZE_WORD_WIDTH),
+                modify_width(pixel[2], 8, QUANTIZE_WORD_WIDTH),
+            );
             *hist.entry(pixel).or_insert(0) += 1;
         }
 
@@ -48,7 +55,7 @@ impl ColorCutQuantizer {
         let mut colors = hist
             .into_iter()
             .filter_map(|(pixel, count)| {
-                if self.should_ignore_color(pixel_to_rgb(&pixel)) {
+                if self.should_ignore_color(pixel) {
                     None
                 } else {
                     Some((pixel, count))
@@ -59,7 +66,7 @@ impl ColorCutQuantizer {
         // the colors have to be ordered at this point, so order them by combining their channels into a single integer
         // where the red channel is the most signifcant and the blue the least
         colors.sort_by_key(|(pixel, _)| {
-            let (r, g, b) = pixel_to_rgb(pixel);
+            let (r, g, b) = *pixel;
             ((r as u32) << (QUANTIZE_WORD_WIDTH + QUANTIZE_WORD_WIDTH)) | ((g as u32) << QUANTIZE_WORD_WIDTH) | b as u32
         });
 
@@ -67,14 +74,14 @@ impl ColorCutQuantizer {
             // there are less colors than requested, no need for further processing; just return each color as a swatch
             colors
                 .into_iter()
-                .map(|(pixel, count)| Swatch::new(pixel_to_rgb(&pixel), count))
+                .map(|(pixel, count)| Swatch::new(pixel, count))
                 .collect()
         } else {
             self.quantize_pixels(colors)
         }
     }
 
-    fn quantize_pixels(self, mut colors: Vec<(Pixel, u32)>) -> Vec<Swatch> {
+    fn quantize_pixels(self, mut colors: Vec<(Rgb, u32)>) -> Vec<Swatch> {
         // create a priority queue of Vboxes with the first one containing all the given colors. Vbox comparison is
         // based on their volume, reversed, so the queue always pops the largest Vbox by volume first
 
@@ -124,7 +131,7 @@ impl ColorCutQuantizer {
 }
 
 impl<'a> Vbox<'a> {
-    fn new(colors: &'a mut [(Pixel, u32)]) -> Self {
+    fn new(colors: &'a mut [(Rgb, u32)]) -> Self {
         // compute the boundaries of the Vbox to tightly fit around the colors within it
 
         let mut population = 0;
@@ -134,7 +141,7 @@ impl<'a> Vbox<'a> {
         let (mut min_blue, mut max_blue) = (QUANTIZE_WORD_MAX, 0);
 
         for (pixel, count) in colors.iter() {
-            let (r, g, b) = pixel_to_rgb(pixel);
+            let (r, g, b) = *pixel;
             population += count;
 
             if r < min_red {
@@ -198,9 +205,9 @@ impl<'a> Vbox<'a> {
         let longest_dimension = self.get_longest_dimension();
 
         self.colors.sort_by(|(lhs, _), (rhs, _)| match longest_dimension {
-            Component::Red => pixel_to_rgb(lhs).0.cmp(&pixel_to_rgb(rhs).0),
-            Component::Green => pixel_to_rgb(lhs).1.cmp(&pixel_to_rgb(rhs).1),
-            Component::Blue => pixel_to_rgb(lhs).2.cmp(&pixel_to_rgb(rhs).2),
+            Component::Red => lhs.0.cmp(&rhs.0),
+            Component::Green => lhs.1.cmp(&rhs.1),
+            Component::Blue => lhs.2.cmp(&rhs.2),
         });
     }
 
@@ -248,7 +255,7 @@ impl<'a> Vbox<'a> {
             self.colors
                 .iter()
                 .fold((0, 0, 0, 0), |(pop, red_sum, green_sum, blue_sum), (pixel, count)| {
-                    let (r, g, b) = pixel_to_rgb(pixel);
+                    let (r, g, b) = *pixel;
 
                     (
                         pop + count,
@@ -291,11 +298,6 @@ impl PartialOrd for Vbox<'_> {
     }
 }
 
-fn pixel_to_rgb(pixel: &Pixel) -> (u8, u8, u8) {
-    let rgb = pixel.to_rgb();
-    (rgb.0[0], rgb.0[1], rgb.0[2])
-}
-
 fn modify_width(value: u8, current_width: u32, target_width: u32) -> u8 {
     if target_width > current_width {
         value.wrapping_shl(target_width - current_width)
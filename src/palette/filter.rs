@@ -7,14 +7,14 @@ const WHITE_MIN_LIGHTNESS: f32 = 0.95;
 /// remove final color swatches that may have their average color end up as filtered. This trait allows the library
 /// consumer to implement custom filters.
 ///
-/// See [`crate::PaletteBuilder::add_filter`] on how to add filters to the quantization process.
+/// See [`crate::palette::PaletteBuilder::add_filter`] on how to add filters to the quantization process.
 pub trait Filter {
     /// Return whether a given color should be allowed or not. The same color is given in both sRGB and HSL for
     /// convenience.
     fn is_allowed(&self, rgb: (u8, u8, u8), hsl: (f32, f32, f32)) -> bool;
 }
 
-/// The default filter included in every [`crate::PaletteBuilder`] by default.
+/// The default filter included in every [`crate::palette::PaletteBuilder`] by default.
 ///
 /// This filter will disallow colors very close to black, colors very close to white, and colors near the red I line,
 /// whatever that is.
@@ -41,12 +41,11 @@ pub use crate::palette::{
 use crate::palette::color_cut_quantizer::ColorCutQuantizer;
 //use image::{math::Rect, GenericImageView, ImageBuffer};
 use crate::image::*;
-use crate::shape::*;
+use crate::object::Object;
 use std::collections::{HashMap, HashSet};
 
 /// A color palette derived from an image.
 #[derive(Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Palette {
     swatches: Vec<Swatch>,
     targets: Vec<Target>,
@@ -59,10 +58,19 @@ pub struct PaletteBuilder {
     targets: Vec<Target>,
     maximum_color_count: usize,
     resize_area: Option<u32>,
-    region: Option<Rect>,
+    region: Option<Region>,
     filters: Vec<Box<dyn Filter>>,
 }
 
+/// A region of an image in pixels.
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
 impl Palette {
     /// Return a new [`PaletteBuilder`] from a given image buffer.
     pub fn from_image(image: Image) -> PaletteBuilder {
@@ -170,10 +178,6 @@ impl PaletteBuilder {
         }
     }
 
-    pub fn from_swatches() -> Self {
-        unimplemented!()
-    }
-
     /// Set the desired area to shrink the image to before quantizing. Set to `None` to disable shrinking.
     ///
     /// By default the image will be shrunk to an area of 112 by 112 pixels, as defined in the
@@ -190,7 +194,7 @@ impl PaletteBuilder {
     /// area in the shrunk image. By default, the entire image is used to generate the palette.
     pub fn region(self, x: u32, y: u32, width: u32, height: u32) -> Self {
         Self {
-            region: Some(Rect { x, y, width: width.try_into().unwrap(), height: height.try_into().unwrap() }),
+            region: Some(Region { x, y, width, height }),
             ..self
         }
     }
@@ -250,23 +254,26 @@ impl PaletteBuilder {
 
                 region.x = (region.x as f32 * scale).floor() as u32;
                 region.y = (region.y as f32 * scale).floor() as u32;
-                region.width = ((region.width as f32 * scale) as u32 + region.x).min((self.image.width() - region.x).try_into().unwrap());
-                region.height = ((region.height as f32 * scale) as u32 + region.y).min((self.image.height() - region.y).try_into().unwrap());
+                region.width = ((region.width as f32 * scale) as u32 + region.x).min(self.image.width() as u32 - region.x);
+                region.height = ((region.height as f32 * scale) as u32 + region.y).min(self.image.height() as u32 - region.y);
 
                 self.region = Some(region);
             }
         }
 
-        // get pixels in the requested region, or in the entire image
-        let pixels = if let Some(region) = self.region {
-            self.image
-                .view(region.x, region.y, region.width, region.height)
-                .pixels()
-                .map(|(_, _, p)| p)
-                .collect()
-        } else {
-            self.image.pixels().copied().collect()
-        };
+        // get the mostly opaque pixels in the requested region, or in the entire image
+        let region = self.region.unwrap_or(Region {
+            x: 0,
+            y: 0,
+            width: self.image.width() as u32,
+            height: self.image.height() as u32,
+        });
+        self.image.collapse();
+        let layer = &self.image[0];
+        let pixels = (region.y..region.y + region.height)
+            .flat_map(|row| (region.x..region.x + region.width).map(move |col| layer[row as usize][col as usize]))
+            .filter(|pixel| pixel[3] >= 128)
+            .collect();
 
         // quantize pixels, get swatches
         let quantizer = ColorCutQuantizer::new(pixels, self.maximum_color_count, self.filters);
@@ -294,7 +301,7 @@ impl PaletteBuilder {
     }
 
     fn scale_image_down(&mut self) -> bool {
-        let (width, height) = self.image.dimensions();
+        let (width, height) = (self.image.width() as u32, self.image.height() as u32);
         let area = width * height;
 
         let scale_ratio = match self.resize_area {
@@ -303,14 +310,13 @@ impl PaletteBuilder {
         };
 
         if scale_ratio > 0.0 {
-            self.image = image::imageops::resize(
-                &self.image,
-                (width as f32 * scale_ratio).ceil() as u32,
-                (height as f32 * scale_ratio).ceil() as u32,
-                image::imageops::FilterType::Nearest,
-            );
-
-            true
+            // The ceiled size has pixels, so a failed resize leaves the Image unscaled.
+            self.image
+                .resize([
+                    (width as f32 * scale_ratio).ceil() as usize,
+                    (height as f32 * scale_ratio).ceil() as usize,
+                ])
+                .is_ok()
         } else {
             false
         }
@@ -1,5 +1,5 @@
+/// A color of a [Palette](crate::palette::Palette) and the number of pixels it stands for.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Swatch {
     red: u8,
     blue: u8,
@@ -8,6 +8,7 @@ pub struct Swatch {
 }
 
 impl Swatch {
+    /// Creates a Swatch of a color standing for population pixels.
     pub fn new((red, green, blue): (u8, u8, u8), population: u32) -> Swatch {
         Self {
             red,
@@ -17,14 +18,17 @@ impl Swatch {
         }
     }
 
+    /// Returns the color in sRGB.
     pub fn rgb(self) -> (u8, u8, u8) {
         (self.red, self.green, self.blue)
     }
 
+    /// Returns the color in HSL, with the hue in degrees.
     pub fn hsl(self) -> (f32, f32, f32) {
-        crate::rgb_to_hsl(self.rgb())
+        crate::palette::rgb_to_hsl(self.rgb())
     }
 
+    /// Returns the number of pixels the color stands for.
     pub fn population(self) -> u32 {
         self.population
     }
@@ -20,8 +20,8 @@ const MIN_NORMAL_LUMA: f32 = 0.3;
 const TARGET_NORMAL_LUMA: f32 = 0.5;
 const MAX_NORMAL_LUMA: f32 = 0.7;
 
+/// A target saturation and lightness a [Swatch](crate::palette::Swatch) of a palette is picked for.
 #[derive(Debug, Clone, Copy)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Target {
     name: u64,
     // min, target, max
@@ -34,6 +34,7 @@ pub struct Target {
 }
 
 impl Target {
+    /// Returns the preset targets included in every palette.
     pub fn default_targets() -> [Target; 6] {
         [
             Target::light_vibrant(),
@@ -45,6 +46,7 @@ impl Target {
         ]
     }
 
+    /// Returns the preset target of a light and saturated color.
     pub fn light_vibrant() -> Target {
         Target {
             name: 0,
@@ -54,6 +56,7 @@ impl Target {
         }
     }
 
+    /// Returns the preset target of a saturated color.
     pub fn vibrant() -> Target {
         Target {
             name: 1,
@@ -63,6 +66,7 @@ impl Target {
         }
     }
 
+    /// Returns the preset target of a dark and saturated color.
     pub fn dark_vibrant() -> Target {
         Target {
             name: 2,
@@ -72,6 +76,7 @@ impl Target {
         }
     }
 
+    /// Returns the preset target of a light and desaturated color.
     pub fn light_muted() -> Target {
         Target {
             name: 3,
@@ -81,6 +86,7 @@ impl Target {
         }
     }
 
+    /// Returns the preset target of a desaturated color.
     pub fn muted() -> Target {
         Target {
             name: 4,
@@ -90,6 +96,7 @@ impl Target {
         }
     }
 
+    /// Returns the preset target of a dark and desaturated color.
     pub fn dark_muted() -> Target {
         Target {
             name: 5,
@@ -99,6 +106,7 @@ impl Target {
         }
     }
 
+    /// Creates a target of any saturation and lightness.
     pub fn new() -> Self {
         Self {
             name: rand::random(),
@@ -131,42 +139,52 @@ impl Target {
         }
     }
 
+    /// Returns the minimum saturation of a color for the target.
     pub fn minimum_saturation(self) -> f32 {
         self.saturation_targets.0
     }
 
+    /// Returns the ideal saturation of a color for the target.
     pub fn target_saturation(self) -> f32 {
         self.saturation_targets.1
     }
 
+    /// Returns the maximum saturation of a color for the target.
     pub fn maximum_saturation(self) -> f32 {
         self.saturation_targets.2
     }
 
+    /// Returns the minimum lightness of a color for the target.
     pub fn minimum_lightness(self) -> f32 {
         self.lightness_targets.0
     }
 
+    /// Returns the ideal lightness of a color for the target.
     pub fn target_lightness(self) -> f32 {
         self.lightness_targets.1
     }
 
+    /// Returns the maximum lightness of a color for the target.
     pub fn maximum_lightness(self) -> f32 {
         self.lightness_targets.2
     }
 
+    /// Returns the weight of the saturation when scoring a color.
     pub fn saturation_weight(self) -> f32 {
         self.weights.0
     }
 
+    /// Returns the weight of the lightness when scoring a color.
     pub fn lightness_weight(self) -> f32 {
         self.weights.1
     }
 
+    /// Returns the weight of the population when scoring a color.
     pub fn population_weight(self) -> f32 {
         self.weights.2
     }
 
+    /// Returns true if a color picked for the target cannot be picked for another.
     pub fn is_exclusive(self) -> bool {
         self.is_exclusive
     }
@@ -1,34 +1,221 @@
 //! This module defines structs and functions for parsing the Manifest.
+use schemars::JsonSchema;
 use serde::Deserialize;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::Read;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
 use std::path::*;
+use std::thread;
+use std::time::{Duration, Instant};
 //use std::borrow::Cow::Borrowed;
-use std::env::Args;
 use std::env;
 
+use file_format::{FileFormat, Kind};
+
+use crate::animation::Easing;
+use crate::asset::{modified, AssetCache, Font};
+use crate::color;
+use crate::data::{fill, parse_value, read_rows, Row};
+use crate::decoder::Decoders;
+use crate::format::{Format, Formats};
+use crate::image::Image;
+use crate::layout::{Align, Direction, Justify};
+use crate::unit::{Anchor, Length};
 use crate::util::*;
 
 #[allow(unused_imports)]
 use crate::object::*;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 /// The representation of the Manifest file.
 pub struct Manifest {
+    /// Manifests this one is based on, relative to it. Their tables are merged,
+    /// later ones and this one taking precedence, and their Objects are
+    /// overridden by the Objects with the same `id`.
+    ///
+    /// Once read, lists every Manifest included, directly or not.
+    pub include: Option<Vec<PathBuf>>,
     /// The format of the generated Image.
-    pub format: String,
+    /// If omitted, it is inferred from the extension of `output`.
+    pub format: Option<String>,
+    /// Filename of the generated Image, relative to the Manifest. `-` writes the Image to stdout.
+    /// Defaults to the Manifest filename with the format's extension.
+    pub output: Option<PathBuf>,
     /// Size of the generated Image.
-    pub size: [usize; 2],
+    /// It may be omitted if every entry of `outputs` has a size.
+    pub size: Option<[usize; 2]>,
     /// Background Color of the generated Image.
+    #[serde(deserialize_with = "color::deserialize")]
+    #[schemars(schema_with = "color::schema")]
     pub color: [u8; 4],
+    /// Resolution of the generated Image in dots per inch, converting `pt` lengths to pixels.
+    /// Defaults to 72, one point per pixel.
+    pub dpi: Option<f64>,
+    /// Animation of the generated Image.
+    pub animation: Option<AnimationInfo>,
+    /// Encoding options of the formats.
+    #[serde(flatten)]
+    pub options: FormatInfo,
+    /// Images generated from the Manifest, replacing the single Image
+    /// described by `format`, `output` and `size`.
+    pub outputs: Option<Vec<OutputInfo>>,
     /// Assets used during generation of the Image.
     pub assets: Option<Vec<AssetInfo>>,
     /// Objects in the generated Image.
     pub objects: Vec<ObjectInfo>,
+    /// Rows of data, each rendering the outputs with its values
+    /// replacing the `{{column}}` placeholders of the Manifest.
+    pub data: Option<DataInfo>,
+    /// Variables, whose values replace the strings `$name` anywhere in the Manifest.
+    #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
+    pub vars: Option<toml::Table>,
+    /// Styles by name, whose fields are given to the Objects naming them
+    /// in their `style` unless the Objects set the fields.
+    #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
+    pub styles: Option<toml::Table>,
+    /// Components by name, each an array of `objects` drawn in place of an Object
+    /// with the name of the component.
+    ///
+    /// The strings `$param` of the objects are replaced by the values of the
+    /// `params` table of the Object, or else of the component.
+    #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
+    pub components: Option<toml::Table>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the data of batch rendering.
+pub struct DataInfo {
+    /// Csv or Json file of the rows, relative to the Manifest.
+    /// A Csv file has a header naming the columns, and a Json file is an array of objects.
+    pub src: Option<PathBuf>,
+    /// Rows given in the Manifest, used if there is no `src`.
+    #[schemars(with = "Option<Vec<serde_json::Map<String, serde_json::Value>>>")]
+    pub rows: Option<Vec<Row>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of an output Image's details.
+///
+/// Omitted details are taken from the Manifest.
+pub struct OutputInfo {
+    /// Filename of the Image, relative to the Manifest.
+    pub filename: Option<PathBuf>,
+    /// The format of the Image.
+    /// If omitted, it is inferred from the extension of `filename`.
+    pub format: Option<String>,
+    /// Size of the Image.
+    pub size: Option<[usize; 2]>,
+    /// Scale factor applied to the size of the Image.
+    pub scale: Option<f64>,
+    /// Encoding options of the formats. A table given here replaces the one of the Manifest.
+    #[serde(flatten)]
+    pub options: FormatInfo,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the encoding options of each format.
+pub struct FormatInfo {
+    /// Png encoding options.
+    pub png: Option<PngInfo>,
+    /// Jpg encoding options.
+    pub jpg: Option<JpgInfo>,
+    /// WebP encoding options.
+    pub webp: Option<WebpInfo>,
+    /// Tiff encoding options.
+    pub tiff: Option<TiffInfo>,
+    /// Svg encoding options.
+    pub svg: Option<SvgInfo>,
+    /// Pdf encoding options.
+    pub pdf: Option<PdfInfo>,
+}
+impl FormatInfo {
+    /// Returns the encoding options, taking the tables omitted from self from other.
+    pub fn or(&self, other: &FormatInfo) -> FormatInfo {
+        FormatInfo {
+            png: self.png.clone().or_else(|| other.png.clone()),
+            jpg: self.jpg.clone().or_else(|| other.jpg.clone()),
+            webp: self.webp.clone().or_else(|| other.webp.clone()),
+            tiff: self.tiff.clone().or_else(|| other.tiff.clone()),
+            svg: self.svg.clone().or_else(|| other.svg.clone()),
+            pdf: self.pdf.clone().or_else(|| other.pdf.clone()),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the Png encoding options.
+pub struct PngInfo {
+    /// Color Type of the Png, one of `rgb`, `rgba`, `gray` or `gray_alpha`.
+    /// If omitted, opaque Images are written as `rgb` and others as `rgba`.
+    pub color_type: Option<String>,
+    /// Bit Depth of the Png, either `8` or `16`.
+    pub bit_depth: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the Jpg encoding options.
+pub struct JpgInfo {
+    /// Quality of the Jpg, between 1 and 100. Defaults to 100.
+    pub quality: Option<u8>,
+    /// Chroma Subsampling of the Jpg, one of `4:4:4`, `4:2:2` or `4:2:0`.
+    pub sampling: Option<String>,
+    /// Whether the Jpg is progressive.
+    pub progressive: Option<bool>,
+    /// Whether the Huffman tables are optimized for the Image.
+    pub optimize_huffman: Option<bool>,
+    /// Number of MCUs between restart markers.
+    pub restart_interval: Option<u16>,
+    /// Color transparent pixels are flattened onto. Defaults to white.
+    #[serde(default, deserialize_with = "color::deserialize_option")]
+    #[schemars(schema_with = "color::schema")]
+    pub matte: Option<[u8; 4]>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// This is the representation of the WebP encoding options.
+///
+/// WebP is written lossless (VP8L) unless a `quality` is set.
+pub struct WebpInfo {
+    /// Quality of lossy (VP8) compression, between 0 and 100. Transparency is kept
+    /// losslessly. If omitted, the WebP is lossless.
+    pub quality: Option<u8>,
+    /// Quality of near-lossless preprocessing, between 0 and 100, rounding off the low bits
+    /// of colors before lossless compression to make the file smaller. 100 keeps every bit.
+    /// If omitted, the colors are kept as they are.
+    pub near_lossless: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the Tiff encoding options.
+pub struct TiffInfo {
+    /// Compression of the Tiff, one of `none`, `lzw`, `deflate` or `packbits`.
+    pub compression: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the Svg encoding options.
+pub struct SvgInfo {
+    /// How raster Images are included, either `embed` or `link`. Defaults to `embed`.
+    ///
+    /// Linked Images are referenced by their path in the Manifest,
+    /// so the Svg must be kept next to the Manifest.
+    pub images: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+/// This is the representation of the Pdf encoding options.
+pub struct PdfInfo {
+    /// Resolution of the Image in dots per inch. Defaults to 72, one point per pixel.
+    pub dpi: Option<f64>,
+    /// Size of the page in millimetres, replacing the size derived from the DPI.
+    pub page_size: Option<[f64; 2]>,
+    /// Quality of raster Images encoded as Jpg, between 1 and 100.
+    /// If omitted, raster Images are compressed losslessly.
+    pub quality: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 /// This is the representation of an Asset's details.
 pub struct AssetInfo {
     /// String for hashing the Asset.
@@ -37,98 +224,1571 @@ pub struct AssetInfo {
     pub src: PathBuf,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[schemars(transform = object_schema)]
 /// This is the representation of an Object's details.
+///
+/// Fields common to all Objects are kept here, and those particular to the
+/// kind of Object in its [ObjectSpec].
 pub struct ObjectInfo {
-    /// Name of [Object].
-    pub name: String,
-    /// Source path of [Object].
-    pub src: Option<PathBuf>,
-    /// Asset id of [Object]
+    /// Kind of [Object] and its fields, named by `name`.
+    #[serde(flatten)]
+    pub spec: ObjectSpec,
+    /// Identifier of [Object], by which a Manifest overrides the Objects of those it includes,
+    /// and other Objects are placed next to or masked by it.
+    pub id: Option<String>,
+    /// Stacking order of [Object]. Objects of higher z are drawn over those of lower z,
+    /// and Objects of equal z in the order they are placed. Defaults to the z of its
+    /// layout, or zero.
+    pub z: Option<i32>,
+    /// Id of the asset of the Manifest [Object] uses, whose file is loaded before drawing.
     pub asset: Option<String>,
-    /// Color of [Object].
-    pub color: Option<[u8; 4]>,
-    /// Text Content of [Object].
-    pub content: Option<String>,
-    /// Resize [Object] to coordinates with x and y values represented as percentages of width and height of Image respectively.
-    /// If either of x value, y value is zero, Ratio of [Object] is maintained. 
-    pub resize: Option<[f64; 2]>,
-    /// Origin of [Object] with x and y values represented as percentages of width and height of Image respectively.
-    pub origin: Option<(f64, f64)>,
+    /// Name of the style of [Object], giving the fields it does not set.
+    pub style: Option<String>,
+    /// Origin of [Object] with x and y values represented as percentages of width and height of Image
+    /// respectively, or as [Lengths][Length]. `em` is relative to the font size of [Object].
+    ///
+    /// It is measured from the `parent_anchor` of Image to the `anchor` of [Object].
+    pub origin: Option<(Length, Length)>,
     /// Coordinates of end point of diagonal from origin.
-    pub offset: Option<(f64, f64)>,
+    pub offset: Option<(Length, Length)>,
+    /// Point of [Object] placed at origin, one of `top-left`, `top`, `top-right`, `left`, `center`,
+    /// `right`, `bottom-left`, `bottom` or `bottom-right`. Defaults to `top-left`.
+    pub anchor: Option<Anchor>,
+    /// Point of Image origin is measured from. Defaults to `top-left`.
+    pub parent_anchor: Option<Anchor>,
+    /// Distance from the right edge of Image to the right edge of [Object], replacing the x value of origin.
+    pub right: Option<Length>,
+    /// Distance from the bottom edge of Image to the bottom edge of [Object], replacing the y value of origin.
+    pub bottom: Option<Length>,
+    /// Id of an Object placed before, below which [Object] is placed. Origin, anchors and
+    /// edges are then measured from the box of that Object, the anchors defaulting to
+    /// `top-left` of [Object] at `bottom-left` of it.
+    pub below: Option<String>,
+    /// Id of an Object placed before, above which [Object] is placed, its `bottom-left`
+    /// at `top-left` of that Object by default.
+    pub above: Option<String>,
+    /// Id of an Object placed before, left of which [Object] is placed, its `top-right`
+    /// at `top-left` of that Object by default.
+    pub left_of: Option<String>,
+    /// Id of an Object placed before, right of which [Object] is placed, its `top-left`
+    /// at `top-right` of that Object by default.
+    pub right_of: Option<String>,
+    /// Width and height of [Object], replacing the diagonal from origin to offset.
+    ///
+    /// Text and layouts without a size or offset fit their content.
+    pub size: Option<(Length, Length)>,
+    /// Id of an Object placed before, through whose alpha [Object] is drawn on raster Images.
+    /// The mask is drawn as well, unless its opacity is zero, which the mask ignores.
+    pub mask: Option<String>,
+    /// Opacity of [Object], between 0 and 1.
+    pub opacity: Option<f64>,
+    /// Scale factor of [Object] about its centre.
+    pub scale: Option<f64>,
+    /// Clockwise rotation of [Object] in degrees about its centre.
+    pub rotation: Option<f64>,
+    /// Keyframes animating the properties of [Object].
+    pub keyframes: Option<Vec<KeyframeInfo>>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "name", rename_all = "lowercase", deny_unknown_fields)]
+/// This is the representation of the kind of an Object and the fields particular to it.
+///
+/// Fields neither common to all Objects nor known to the kind are rejected.
+pub enum ObjectSpec {
+    /// A rectangle, given by a size, or by an origin and an offset.
+    Rect {
+        /// Color of the rectangle.
+        #[serde(deserialize_with = "color::deserialize")]
+        #[schemars(schema_with = "color::schema")]
+        color: [u8; 4],
+    },
+    /// A raster Image.
+    Image {
+        /// Source path of the Image.
+        src: PathBuf,
+        /// Frame of an animated source, or entry of an icon source.
+        frame: Option<usize>,
+        /// Resize the Image to a width and height, as percentages of width and height of Image or [Lengths][Length].
+        /// If either of width, height is zero, Ratio of the Image is maintained.
+        resize: Option<[Length; 2]>,
+    },
+    /// A box of text.
+    Text {
+        /// Font file of the text.
+        font: PathBuf,
+        /// Text content. Defaults to none.
+        #[serde(default)]
+        content: String,
+        /// Font size of the text, as a percentage of height of Image or a [Length].
+        /// `em` is relative to the default font size of 5 percent of height of Image.
+        font_size: Option<Length>,
+        /// Color of the text. Defaults to black.
+        #[serde(default, deserialize_with = "color::deserialize_option")]
+        #[schemars(schema_with = "color::schema")]
+        color: Option<[u8; 4]>,
+    },
+    /// A container arranging its children in rows or columns.
+    Layout {
+        /// Objects arranged by the layout, whose origins, anchors, edges and neighbours are ignored.
+        /// Their lengths are relative to the layout less its padding.
+        children: Vec<ObjectInfo>,
+        /// Axis the children are arranged along, either `row` or `column`. Defaults to `row`.
+        direction: Option<Direction>,
+        /// Space between the children, and between their lines.
+        gap: Option<Length>,
+        /// Space between the edges of the layout and its children.
+        padding: Option<Length>,
+        /// Distribution of the free space of a line, one of `start`, `center`, `end`,
+        /// `space-between`, `space-around` or `space-evenly`. Defaults to `start`.
+        justify: Option<Justify>,
+        /// Alignment of the children across their line, one of `start`, `center` or `end`.
+        /// Defaults to `start`.
+        align: Option<Align>,
+        /// Whether the children overflowing a line wrap onto a new line. Defaults to false.
+        #[serde(default)]
+        wrap: bool,
+        /// Color drawn behind the children.
+        #[serde(default, deserialize_with = "color::deserialize_option")]
+        #[schemars(schema_with = "color::schema")]
+        color: Option<[u8; 4]>,
+    },
 }
 
-/// Args Parser.qa
-pub fn parse_args(mut args: Args) -> PathBuf {
-    let file_from_arg;
-    if args.len() - 1 < 1 {
-        std::process::exit(1);
+impl ObjectSpec {
+    /// Returns the name of the kind of Object.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectSpec::Rect { .. } => "rect",
+            ObjectSpec::Image { .. } => "image",
+            ObjectSpec::Text { .. } => "text",
+            ObjectSpec::Layout { .. } => "layout",
+        }
     }
-    else {
-        file_from_arg = args.nth(1).unwrap();
+
+    /// Returns the children of a layout, or none.
+    pub fn children(&self) -> &[ObjectInfo] {
+        match self {
+            ObjectSpec::Layout { children, .. } => children,
+            _ => &[],
+        }
     }
+}
 
-    let file_path = PathBuf::from(file_from_arg);
-    let mut file;
-    if file_path.is_relative() {
-        file = env::current_dir().unwrap();
-        file.push(file_path)
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// This is the representation of the Animation's details.
+pub struct AnimationInfo {
+    /// Number of frames.
+    pub frames: usize,
+    /// Frames per second.
+    pub fps: f64,
+    /// Number of times the animation plays, zero playing forever. Defaults to zero.
+    pub loops: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// This is the representation of a Keyframe's details.
+///
+/// Properties omitted from a keyframe are interpolated between the keyframes defining them.
+pub struct KeyframeInfo {
+    /// Frame of the keyframe, counting from zero.
+    pub frame: usize,
+    /// Origin of [Object] with x and y values represented as percentages of width and height of Image
+    /// respectively, or as [Lengths][Length].
+    pub origin: Option<(Length, Length)>,
+    /// Opacity of [Object], between 0 and 1.
+    pub opacity: Option<f64>,
+    /// Scale factor of [Object] about its centre.
+    pub scale: Option<f64>,
+    /// Clockwise rotation of [Object] in degrees about its centre.
+    pub rotation: Option<f64>,
+    /// Color of a rect [Object].
+    #[serde(default, deserialize_with = "color::deserialize_option")]
+    #[schemars(schema_with = "color::schema")]
+    pub color: Option<[u8; 4]>,
+    /// Easing of the transition to the next keyframe, one of `linear`, `step`,
+    /// `ease`, `ease-in`, `ease-out` or `ease-in-out`. Defaults to `linear`.
+    pub easing: Option<String>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// Subcommand of the command line.
+pub enum Command {
+    /// Renders the output Images of a Manifest.
+    #[default]
+    Render,
+    /// Renders the output Images of a Manifest again whenever its files change.
+    Watch,
+    /// Checks that a Manifest can be rendered.
+    Check,
+    /// Prints the dominant colors of an Image or of the Image of a Manifest.
+    Palette,
+    /// Writes a starter Manifest.
+    Init,
+    /// Prints the size, outputs and Objects of a Manifest, or the size of an Image.
+    Info,
+    /// Prints the Json Schema of Manifests.
+    Schema,
+    /// Prints the usage.
+    Help,
+    /// Prints the version.
+    Version,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, PartialOrd)]
+/// How much the command line prints besides errors.
+pub enum Verbosity {
+    /// Prints nothing but errors.
+    Quiet,
+    /// Prints results and warnings.
+    #[default]
+    Normal,
+    /// Also prints what is written and how long it took.
+    Verbose,
+}
+
+#[derive(Debug, Default)]
+/// This is the representation of the command line arguments.
+pub struct Cli {
+    /// Subcommand to run.
+    pub command: Command,
+    /// Path of the Manifest file. `-` reads the Manifest from stdin.
+    pub file: PathBuf,
+    /// Filename of the generated Image, replacing the one of the Manifest.
+    /// `-` writes the Image to stdout.
+    pub output: Option<PathBuf>,
+    /// Format of the generated Images, replacing the ones of the Manifest.
+    pub format: Option<String>,
+    /// Values of keys of the Manifest to replace, as dotted keys and Toml values.
+    pub set: Vec<(String, String)>,
+    /// Csv or Json file of rows, replacing the data of the Manifest.
+    pub data: Option<PathBuf>,
+    /// How much to print.
+    pub verbosity: Verbosity,
+}
+
+/// Usage of the command line.
+pub const USAGE: &str = "\
+Generates images from a manifest.
+
+Usage: rasm [COMMAND] [OPTIONS] [FILE]
+
+Commands:
+  render   Render the outputs of a manifest (default)
+  watch    Render the outputs of a manifest again whenever it or its files change
+  check    Check that a manifest can be rendered, decoding its files
+  palette  Print the dominant colors of an image or of the image of a manifest
+  init     Write a starter Rasm.toml in a directory
+  info     Print the size, outputs and objects of a manifest, or the size of an image
+  schema   Print the JSON Schema of manifests, for editors to complete and validate them
+
+FILE is a manifest in toml, json or yaml, a directory holding a Rasm.toml,
+Rasm.json or Rasm.yaml, a name completed to NAME.manifest.toml, .json or
+.yaml, or - to read the manifest from stdin.
+
+Options:
+  -o, --output <FILE>      Write the image to FILE, in the format of its
+                           extension, or to stdout if FILE is -
+  -f, --format <FORMAT>    Encode the images in FORMAT
+  -s, --set <KEY=VALUE>    Replace the value of a key of the manifest, as in
+                           size=[540,540] or objects.0.color=[0,0,0,255]
+  -d, --data <FILE>        Render the outputs once per row of a csv or json
+                           FILE, filling the {{column}} placeholders
+  -v, --verbose            Print what is written and how long it took
+  -q, --quiet              Print nothing but errors
+  -h, --help               Print this help
+  -V, --version            Print the version
+";
+
+/// Args Parser.
+///
+/// Returns the message of the error if the arguments are invalid.
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Cli, String> {
+    let mut cli = Cli::default();
+    let mut command = None;
+    let mut file_from_arg = None;
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        // Long options take their value after `=` or as the next argument.
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+        match name.as_str() {
+            "-o" | "--output" => cli.output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => cli.format = Some(value()?),
+            "-s" | "--set" => {
+                let set = value()?;
+                match set.split_once('=') {
+                    Some((key, value)) => cli.set.push((key.trim().to_string(), value.trim().to_string())),
+                    None => return Err(format!("{name} needs a KEY=VALUE, not {set}")),
+                }
+            }
+            "-d" | "--data" => cli.data = Some(PathBuf::from(value()?)),
+            "-v" | "--verbose" => cli.verbosity = Verbosity::Verbose,
+            "-q" | "--quiet" => cli.verbosity = Verbosity::Quiet,
+            "-h" | "--help" => command = Some(Command::Help),
+            "-V" | "--version" => command = Some(Command::Version),
+            "-" => file_from_arg = Some(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ if command.is_none() && file_from_arg.is_none() => {
+                command = Some(match arg.as_str() {
+                    "render" => Command::Render,
+                    "watch" => Command::Watch,
+                    "check" => Command::Check,
+                    "palette" => Command::Palette,
+                    "init" => Command::Init,
+                    "info" => Command::Info,
+                    "schema" => Command::Schema,
+                    "help" => Command::Help,
+                    // `rasm dir/` and `rasm file` render.
+                    _ => {
+                        file_from_arg = Some(arg);
+                        Command::Render
+                    }
+                })
+            }
+            _ if file_from_arg.is_none() => file_from_arg = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+    cli.command = command.unwrap_or_default();
+    if matches!(cli.command, Command::Help | Command::Version | Command::Schema) {
+        return Ok(cli);
+    }
+    // Output and data paths given on the command line are relative to the working directory.
+    cli.output = cli.output.map(|output| match output.as_os_str() == "-" {
+        true => output,
+        false => env::current_dir().unwrap().join(output),
+    });
+    cli.data = cli.data.map(|data| env::current_dir().unwrap().join(data));
+    let Some(file_from_arg) = file_from_arg else {
+        return match cli.command {
+            // A starter Manifest is written in the working directory by default.
+            Command::Init => {
+                cli.file = env::current_dir().unwrap();
+                Ok(cli)
+            }
+            _ => Err("no manifest given".to_string()),
+        };
+    };
+    // `-` reads the Manifest from stdin.
+    if file_from_arg == "-" {
+        cli.file = PathBuf::from(file_from_arg);
+        return Ok(cli);
+    }
+    let file = env::current_dir().unwrap().join(file_from_arg);
+    cli.file = if cli.command == Command::Init || file.is_file() || Syntax::from_path(&file).is_some() {
+        file
+    } else if file.is_dir() {
+        manifest_file(&file, "Rasm")
     } else {
-        file = file_path
+        // A name without its `.manifest.toml` extension.
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        manifest_file(file.parent().unwrap(), &format!("{name}.manifest"))
+    };
+    Ok(cli)
+}
+
+/// Returns the Manifest file of dir named stem and the first extension of [Syntax]
+/// there is one for, or else the Toml one.
+fn manifest_file(dir: &Path, stem: &str) -> PathBuf {
+    Syntax::EXTENSIONS
+        .iter()
+        .map(|(extension, _)| dir.join(format!("{stem}.{extension}")))
+        .find(|file| file.is_file())
+        .unwrap_or_else(|| dir.join(format!("{stem}.toml")))
+}
+
+/// Returns the default filename of the Image generated from the Manifest file,
+/// replacing its `.manifest.toml` or `.toml` extension, or the Json or Yaml one,
+/// with the extension of the format.
+pub fn default_output(file: &Path, extension: &str) -> PathBuf {
+    let name = file.file_name().unwrap().to_string_lossy();
+    let stem = Syntax::EXTENSIONS
+        .iter()
+        .find_map(|(ext, _)| {
+            name.strip_suffix(&format!(".manifest.{ext}"))
+                .or_else(|| name.strip_suffix(&format!(".{ext}")))
+        })
+        .unwrap_or(&name);
+    file.with_file_name(format!("{stem}.{extension}"))
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// Syntax a Manifest is written in.
+pub enum Syntax {
+    /// Toml, the default.
+    #[default]
+    Toml,
+    /// Json.
+    Json,
+    /// Yaml.
+    Yaml,
+}
+impl Syntax {
+    /// Extensions of Manifest files and their syntaxes, in the order a directory is searched.
+    pub const EXTENSIONS: [(&str, Syntax); 4] =
+        [("toml", Syntax::Toml), ("json", Syntax::Json), ("yaml", Syntax::Yaml), ("yml", Syntax::Yaml)];
+
+    /// Returns the syntax of a Manifest file by its extension, if it has a known one.
+    pub fn from_path(file: &Path) -> Option<Syntax> {
+        let extension = file.extension()?.to_str()?;
+        Syntax::EXTENSIONS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            .map(|(_, syntax)| *syntax)
     }
 
-    //let mut filename = Borrowed("rasm-design");
-    //let temp = file.clone();
-    //let tmp;
-    // if it's a directory use it as output name.
-    if file.is_dir() {
-        //filename = temp.file_name().unwrap().to_string_lossy();
-        file.push("Rasm.toml")
+    /// Guesses the syntax of the text of a Manifest from its first line besides comments.
+    ///
+    /// Json starts with `{`, and Yaml with `---` or a key followed by `:` rather than `=`.
+    pub fn sniff(text: &str) -> Syntax {
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        if line.starts_with('{') {
+            return Syntax::Json;
+        }
+        if line.starts_with("---") {
+            return Syntax::Yaml;
+        }
+        match (line.find('='), line.find(':')) {
+            (None, Some(_)) => Syntax::Yaml,
+            (Some(equals), Some(colon)) if colon < equals => Syntax::Yaml,
+            _ => Syntax::Toml,
+        }
+    }
+
+    /// Returns the syntax of the Manifest file by its extension, or else of its text.
+    pub fn of(file: &Path, text: &str) -> Syntax {
+        Syntax::from_path(file).unwrap_or_else(|| Syntax::sniff(text))
     }
-    // if it's regular file use it as output name.
-    else if !file.ends_with(".manifest.toml") {
-        file.set_extension("manifest.toml");
-        if file.is_file() {
-            //tmp = temp.file_name().unwrap().to_string_lossy();
-            //filename = Borrowed(tmp.strip_suffix(".manifest.toml").unwrap())
+
+    /// Parses the text of a Manifest into a table, returning the message of the error if it is invalid.
+    ///
+    /// Json and Yaml are read into the same table as Toml, so their Manifests are
+    /// included, styled and checked alike.
+    pub fn parse(self, text: &str) -> Result<toml::Table, String> {
+        match self {
+            Syntax::Toml => text.parse::<toml::Table>().map_err(|err| err.to_string()),
+            Syntax::Json => serde_json::from_str::<toml::Table>(text).map_err(|err| err.to_string()),
+            Syntax::Yaml => serde_norway::from_str::<toml::Table>(text).map_err(|err| err.to_string()),
         }
-    } else {
-        panic!("Err")
     }
-    file
 }
 
-/// Parses the Manifest file
-pub fn parse_manifest(file: PathBuf) {
-    let f = File::open(&file).unwrap();
-    let mut reader = BufReader::new(f);
+#[derive(Debug)]
+/// Error of reading or rendering a Manifest.
+pub enum Error {
+    /// A field of the Manifest has the wrong type.
+    Manifest(toml::de::Error),
+    /// The Manifest is not valid Toml, Json or Yaml.
+    Syntax(String),
+    /// A file of the Manifest could not be read.
+    Io(PathBuf, io::Error),
+    /// The Manifest describes an Image that cannot be rendered.
+    Invalid(String),
+    /// An encoded Image could not be written.
+    Write(io::Error),
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Manifest(err) => write!(f, "invalid manifest: {err}"),
+            Error::Syntax(message) => write!(f, "invalid manifest: {message}"),
+            Error::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            Error::Invalid(message) => f.write_str(message),
+            Error::Write(err) => write!(f, "cannot write image: {err}"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Manifest(err) => Some(err),
+            Error::Io(_, err) | Error::Write(err) => Some(err),
+            Error::Syntax(_) | Error::Invalid(_) => None,
+        }
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Error::Manifest(err)
+    }
+}
+
+/// Reads a Manifest from its text, in the [Syntax] it is sniffed to be written in.
+pub fn read_manifest(text: &str) -> Result<Manifest, Error> {
+    read_manifest_with(text, &[])
+}
+
+/// Reads a Manifest from its text, replacing the values of keys.
+///
+/// Keys are dotted paths indexing arrays by number, as in `objects.0.color`.
+/// Values are Toml, or strings if they are not valid Toml, whatever the syntax of the Manifest.
+/// Included Manifests and data are resolved from the working directory, and the
+/// Manifest of the first row of its data is returned.
+pub fn read_manifest_with(text: &str, set: &[(String, String)]) -> Result<Manifest, Error> {
+    Ok(read_manifests(text, set, None, Path::new(""))?.remove(0))
+}
+
+/// Reads the table of a Manifest from its text in syntax, merging the Manifests it
+/// includes from base_dir and replacing the values of keys.
+fn read_table(syntax: Syntax, text: &str, set: &[(String, String)], base_dir: &Path) -> Result<toml::Value, Error> {
+    let mut table = syntax.parse(text).map_err(Error::Syntax)?;
+    let included = include(&mut table, base_dir, INCLUDE_DEPTH)?;
+    if !included.is_empty() {
+        let included = included.iter().map(|file| toml::Value::String(file.to_string_lossy().into_owned()));
+        table.insert("include".to_string(), toml::Value::Array(included.collect()));
+    }
+    let mut table = toml::Value::Table(table);
+    set_values(&mut table, set)?;
+    Ok(table)
+}
+
+/// How deep Manifests may include others, and components draw others.
+const INCLUDE_DEPTH: usize = 16;
+
+/// Merges the Manifests included by table under it, read from base_dir.
+///
+/// Relative source paths of the included Manifests are rebased to be relative to table.
+/// Returns the files included, directly or not, relative to base_dir.
+fn include(table: &mut toml::Table, base_dir: &Path, depth: usize) -> Result<Vec<PathBuf>, Error> {
+    let includes = match table.remove("include") {
+        Some(includes) => includes.try_into::<Vec<PathBuf>>()?,
+        None => return Ok(Vec::new()),
+    };
+    let mut merged = toml::Table::new();
+    let mut files = Vec::new();
+    for path in includes {
+        let file = base_dir.join(&path);
+        if depth == 0 {
+            return Err(Error::Invalid(format!("{} includes itself", file.display())));
+        }
+        let text = fs::read_to_string(&file).map_err(|err| Error::Io(file.clone(), err))?;
+        let mut base = Syntax::of(&file, &text)
+            .parse(&text)
+            .map_err(|err| Error::Syntax(format!("{}: {err}", file.display())))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let nested = include(&mut base, file.parent().unwrap(), depth - 1)?;
+        rebase(&mut base, dir);
+        files.push(path.clone());
+        files.extend(nested.iter().map(|nested| dir.join(nested)));
+        merge(&mut merged, base);
+    }
+    merge(&mut merged, std::mem::take(table));
+    *table = merged;
+    Ok(files)
+}
+
+/// Prefixes the relative source paths of the data, assets, Objects and components of table with dir.
+fn rebase(table: &mut toml::Table, dir: &Path) {
+    if let Some(data) = table.get_mut("data") {
+        rebase_fields(data, &["src"], dir);
+    }
+    for asset in table.get_mut("assets").and_then(|assets| assets.as_array_mut()).into_iter().flatten() {
+        rebase_fields(asset, &["src"], dir);
+    }
+    rebase_objects(table.get_mut("objects"), dir);
+    let components = table.get_mut("components").and_then(|components| components.as_table_mut());
+    for (_, component) in components.into_iter().flatten() {
+        rebase_objects(component.get_mut("objects"), dir);
+    }
+}
+
+/// Prefixes the relative paths of fields of table with dir.
+fn rebase_fields(table: &mut toml::Value, fields: &[&str], dir: &Path) {
+    for field in fields {
+        if let Some(toml::Value::String(src)) = table.get_mut(field) {
+            *src = dir.join(&*src).to_string_lossy().into_owned();
+        }
+    }
+}
+
+/// Prefixes the relative source paths of objects and their children with dir.
+fn rebase_objects(objects: Option<&mut toml::Value>, dir: &Path) {
+    for object in objects.and_then(|objects| objects.as_array_mut()).into_iter().flatten() {
+        rebase_fields(object, &["src", "font"], dir);
+        rebase_objects(object.get_mut("children"), dir);
+    }
+}
+
+/// Merges table into base, replacing its values but merging tables and
+/// overriding Objects by their `id`.
+fn merge(base: &mut toml::Table, table: toml::Table) {
+    for (key, value) in table {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(table)) => merge(base, table),
+            (Some(toml::Value::Array(base)), toml::Value::Array(objects)) if key == "objects" => {
+                let id = |object: &toml::Value| object.get("id").and_then(|id| id.as_str()).map(str::to_string);
+                for object in objects {
+                    let inherited = base.iter_mut().find(|base| id(&object).is_some() && id(base) == id(&object));
+                    match (inherited, object) {
+                        (Some(toml::Value::Table(inherited)), toml::Value::Table(object)) => inherited.extend(object),
+                        (_, object) => base.push(object),
+                    }
+                }
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
 
-    let mut read_file = String::new();
-    let _ = reader.read_to_string(&mut read_file);
-    let parsed_file = toml::from_str::<Manifest>(&read_file);
-    let manifest;
-    match parsed_file {
-        Ok(_manifest) => {
-            manifest = _manifest;
+/// Reads a Manifest from its text once per row of its data, replacing the
+/// values of keys and then the `{{column}}` placeholders with the values of the row.
+///
+/// The rows are read from data if given, or else from the data of the Manifest,
+/// whose `src` is relative to base_dir. A Manifest without data is read once.
+/// The [Syntax] of the Manifest is sniffed from its text.
+pub fn read_manifests(
+    text: &str,
+    set: &[(String, String)],
+    data: Option<&Path>,
+    base_dir: &Path,
+) -> Result<Vec<Manifest>, Error> {
+    read_manifests_in(Syntax::sniff(text), text, set, data, base_dir)
+}
+
+/// Reads a Manifest written in syntax as [read_manifests] does.
+pub fn read_manifests_in(
+    syntax: Syntax,
+    text: &str,
+    set: &[(String, String)],
+    data: Option<&Path>,
+    base_dir: &Path,
+) -> Result<Vec<Manifest>, Error> {
+    let table = read_table(syntax, text, set, base_dir)?;
+    let data_info = match table.get("data") {
+        Some(data_info) => data_info.clone().try_into::<DataInfo>()?,
+        None => DataInfo::default(),
+    };
+    let rows = match (data, data_info.src, data_info.rows) {
+        (Some(file), _, _) => read_rows(file)?,
+        (None, Some(src), _) => read_rows(&base_dir.join(src))?,
+        (None, None, Some(rows)) => rows,
+        (None, None, None) => return Ok(vec![manifest_from(table)?]),
+    };
+    if rows.is_empty() {
+        return Err(Error::Invalid("data has no rows".to_string()));
+    }
+    rows.iter()
+        .map(|row| {
+            let mut table = table.clone();
+            if let toml::Value::Table(table) = &mut table {
+                for (_, value) in table.iter_mut().filter(|(key, _)| *key != "data") {
+                    fill(value, row)?;
+                }
+            }
+            manifest_from(table)
+        })
+        .collect()
+}
+
+/// Converts the table of a Manifest to a Manifest, replacing its variables
+/// and giving its Objects the fields of their styles.
+fn manifest_from(mut table: toml::Value) -> Result<Manifest, Error> {
+    let toml::Value::Table(manifest) = &mut table else {
+        unreachable!()
+    };
+    if let Some(toml::Value::Table(components)) = manifest.get("components").cloned() {
+        if let Some(toml::Value::Array(objects)) = manifest.get_mut("objects") {
+            *objects = expand_components(std::mem::take(objects), &components, INCLUDE_DEPTH)?;
+        }
+    }
+    if let Some(toml::Value::Table(vars)) = manifest.get("vars").cloned() {
+        for (_, value) in manifest.iter_mut().filter(|(key, _)| *key != "vars") {
+            substitute_vars(value, &vars, vars.len())?;
+        }
+    }
+    let styles = match manifest.get("styles") {
+        Some(toml::Value::Table(styles)) => styles.clone(),
+        _ => toml::Table::new(),
+    };
+    apply_styles(manifest.get_mut("objects"), &styles, "objects")?;
+    check_fields(manifest.get("objects"), "objects")?;
+    Ok(table.try_into::<Manifest>()?)
+}
+
+/// Checks that objects and their children have the fields their kind requires and
+/// no others, naming the first that does not by its path, as in `objects[1].children[0]`.
+fn check_fields(objects: Option<&toml::Value>, path: &str) -> Result<(), Error> {
+    let objects = objects.and_then(|objects| objects.as_array());
+    for (idx, object) in objects.into_iter().flatten().enumerate() {
+        let path = format!("{path}[{idx}]");
+        check_fields(object.get("children"), &format!("{path}.children"))?;
+        if let Err(err) = object.clone().try_into::<ObjectInfo>() {
+            return Err(Error::Invalid(format!("{path}: {}", object_error(object, &err))));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the message of the error of reading object.
+///
+/// An unknown field is reported with every field the kind of object accepts, as the
+/// schema of Objects lists them, rather than only the fields particular to its kind.
+fn object_error(object: &toml::Value, err: &toml::de::Error) -> String {
+    let message = err.message();
+    let name = object.get("name").and_then(|name| name.as_str());
+    let field = message.strip_prefix("unknown field `").and_then(|rest| rest.split('`').next());
+    let (Some(name), Some(field)) = (name, field) else {
+        return message.to_string();
+    };
+    let schema = schemars::schema_for!(ObjectInfo);
+    let kinds = schema.get("oneOf").and_then(|kinds| kinds.as_array());
+    let kind = kinds
+        .into_iter()
+        .flatten()
+        .find(|kind| kind.pointer("/properties/name/const").and_then(|name| name.as_str()) == Some(name));
+    let Some(serde_json::Value::Object(properties)) = kind.and_then(|kind| kind.get("properties")) else {
+        return message.to_string();
+    };
+    let expected: Vec<_> = properties.keys().filter(|key| *key != "name").map(|key| format!("`{key}`")).collect();
+    format!("unknown field `{field}`, expected one of {}", expected.join(", "))
+}
+
+/// Gives objects and their children the fields of their styles which they do not set.
+///
+/// The path of objects names them in errors.
+fn apply_styles(objects: Option<&mut toml::Value>, styles: &toml::Table, path: &str) -> Result<(), Error> {
+    let objects = objects.and_then(|objects| objects.as_array_mut());
+    for (idx, object) in objects.into_iter().flatten().enumerate() {
+        let Some(object) = object.as_table_mut() else {
+            continue;
+        };
+        if let Some(style) = object.get("style").and_then(|style| style.as_str()) {
+            let Some(toml::Value::Table(fields)) = styles.get(style) else {
+                return Err(Error::Invalid(format!("{path}[{idx}]: unknown style {style}")));
+            };
+            for (key, value) in fields {
+                object.entry(key).or_insert_with(|| value.clone());
+            }
+        }
+        apply_styles(object.get_mut("children"), styles, &format!("{path}[{idx}].children"))?;
+    }
+    Ok(())
+}
+
+/// Replaces the Objects named after components with the objects of the components,
+/// up to depth deep.
+///
+/// The ids of the objects are prefixed with the id of the Object, as in `lockup.logo`.
+fn expand_components(objects: Vec<toml::Value>, components: &toml::Table, depth: usize) -> Result<Vec<toml::Value>, Error> {
+    let mut expanded = Vec::new();
+    for mut object in objects {
+        let name = object.get("name").and_then(|name| name.as_str()).unwrap_or_default();
+        let Some(component) = components.get(name) else {
+            if let Some(toml::Value::Array(children)) = object.get_mut("children") {
+                *children = expand_components(std::mem::take(children), components, depth)?;
+            }
+            expanded.push(object);
+            continue;
+        };
+        if depth == 0 {
+            return Err(Error::Invalid(format!("component {name} draws itself")));
+        }
+        let mut params = match component.get("params") {
+            Some(toml::Value::Table(params)) => params.clone(),
+            _ => toml::Table::new(),
+        };
+        if let Some(toml::Value::Table(given)) = object.get("params") {
+            params.extend(given.clone());
+        }
+        let Some(toml::Value::Array(parts)) = component.get("objects") else {
+            return Err(Error::Invalid(format!("component {name} has no objects")));
+        };
+        let mut parts = parts.clone();
+        for part in &mut parts {
+            substitute_vars(part, &params, params.len())?;
+            let id = object.get("id").and_then(|id| id.as_str());
+            if let (Some(id), Some(toml::Value::String(part_id))) = (id, part.get_mut("id")) {
+                *part_id = format!("{id}.{part_id}");
+            }
+        }
+        expanded.extend(expand_components(parts, components, depth - 1)?);
+    }
+    Ok(expanded)
+}
+
+/// Replaces the strings `$name` below value with the values of the variables of vars.
+///
+/// Variables may refer to others, up to depth deep.
+fn substitute_vars(value: &mut toml::Value, vars: &toml::Table, depth: usize) -> Result<(), Error> {
+    match value {
+        toml::Value::String(text) => {
+            if let Some(var) = text.strip_prefix('$').and_then(|name| vars.get(name)) {
+                if depth == 0 {
+                    return Err(Error::Invalid(format!("variable {text} refers to itself")));
+                }
+                let mut var = var.clone();
+                substitute_vars(&mut var, vars, depth - 1)?;
+                *value = var;
+            }
+        }
+        toml::Value::Array(values) => {
+            for value in values {
+                substitute_vars(value, vars, depth)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                substitute_vars(value, vars, depth)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces the values of the dotted keys of set in table.
+fn set_values(table: &mut toml::Value, set: &[(String, String)]) -> Result<(), Error> {
+    for (key, value) in set {
+        let keys: Vec<&str> = key.split('.').collect();
+        if !set_value(table, &keys, parse_value(value)) {
+            return Err(Error::Invalid(format!("cannot set {key}")));
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the value at the dotted keys below entry, adding missing tables.
+///
+/// Returns false if a key names a field of a value which is neither a table nor an array.
+fn set_value(entry: &mut toml::Value, keys: &[&str], value: toml::Value) -> bool {
+    let Some((key, keys)) = keys.split_first() else {
+        *entry = value;
+        return true;
+    };
+    let entry = match entry {
+        toml::Value::Table(table) => table
+            .entry(*key)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new())),
+        toml::Value::Array(elements) => match key.parse::<usize>().ok().and_then(|idx| elements.get_mut(idx)) {
+            Some(element) => element,
+            None => return false,
         },
-        Err(err) => {
-            println!("{}", err.message());
-            println!("{:?}", err.span());
-            std::process::exit(1);
+        _ => return false,
+    };
+    set_value(entry, keys, value)
+}
+
+/// Reads the Manifest named on the command line once per row of its data,
+/// replacing the values of keys and the formats of its outputs as it asks.
+///
+/// Its [Syntax] is told by the extension of its file, or else sniffed from its text.
+/// Returns the Manifests and the directory their relative paths are resolved from,
+/// which for a Manifest read from stdin is the working directory.
+pub fn load_manifests(cli: &Cli) -> Result<(Vec<Manifest>, PathBuf), Error> {
+    let (text, base_dir) = if cli.file.as_os_str() == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| Error::Io(cli.file.clone(), err))?;
+        (text, env::current_dir().unwrap())
+    } else {
+        let text = fs::read_to_string(&cli.file).map_err(|err| Error::Io(cli.file.clone(), err))?;
+        (text, cli.file.parent().unwrap().to_path_buf())
+    };
+    let syntax = Syntax::of(&cli.file, &text);
+    let mut manifests = read_manifests_in(syntax, &text, &cli.set, cli.data.as_deref(), &base_dir)?;
+    if let Some(format) = &cli.format {
+        for manifest in &mut manifests {
+            manifest.format = Some(format.clone());
+            for output in manifest.outputs.iter_mut().flatten() {
+                output.format = Some(format.clone());
+            }
+        }
+    }
+    Ok((manifests, base_dir))
+}
+
+/// Reads the Manifest named on the command line as [load_manifests] does,
+/// returning the Manifest of the first row of its data.
+pub fn load_manifest(cli: &Cli) -> Result<(Manifest, PathBuf), Error> {
+    let (mut manifests, base_dir) = load_manifests(cli)?;
+    Ok((manifests.remove(0), base_dir))
+}
+
+/// Writes the Image of the Manifest encoded in format to w.
+///
+/// Relative source paths of Objects are resolved from base_dir, and their
+/// files are loaded through cache.
+pub fn write_output(
+    manifest: &Manifest,
+    mut format: Box<dyn Format>,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+    w: &mut dyn Write,
+) -> Result<(), Error> {
+    if manifest.animation.is_some() && format.animation().is_none() {
+        return Err(Error::Invalid(format!("{} images cannot be animated", format.extension())));
+    }
+    let mut canvas = parse_canvas(manifest, format, base_dir, cache)?;
+    match &manifest.animation {
+        Some(animation) => canvas.write_animation(
+            w,
+            animation.frames,
+            animation.fps,
+            animation.loops.unwrap_or(0),
+        ),
+        None => canvas.write(w),
+    }
+}
+
+/// Renders the first frame of the first output Image of the Manifest.
+///
+/// Relative source paths of Objects are resolved from the working directory.
+pub fn render(manifest: &Manifest) -> Result<Image, Error> {
+    render_in(manifest, Path::new("."))
+}
+
+/// Renders the first frame of the first output Image of the Manifest,
+/// resolving relative source paths of Objects from base_dir.
+///
+/// The Image is drawn as a raster, whatever the output format.
+pub fn render_in(manifest: &Manifest, base_dir: &Path) -> Result<Image, Error> {
+    check_renderable(manifest, base_dir)?;
+    let output_info = parse_outputs(manifest)?.remove(0);
+    let [width, height] = output_info.size.unwrap();
+    let format = Formats::default().get("png", width, height, &FormatInfo::default())?;
+    let mut canvas = parse_canvas(manifest, format, base_dir, &mut AssetCache::new())?;
+    canvas.render();
+    Ok(canvas.fmt().image().clone())
+}
+
+/// Checks the fields and files the Objects of the Manifest need to be drawn.
+fn check_renderable(manifest: &Manifest, base_dir: &Path) -> Result<(), Error> {
+    let invalid = |message: String| Err(Error::Invalid(message));
+    let sizes = manifest.outputs.iter().flatten().map(|output| output.size);
+    if manifest.size.is_none() && (manifest.outputs.is_none() || sizes.clone().any(|size| size.is_none())) {
+        return invalid("no image size".to_string());
+    }
+    if manifest.outputs.as_ref().is_some_and(|outputs| outputs.is_empty()) {
+        return invalid("outputs is empty".to_string());
+    }
+    if let Some([0, _] | [_, 0]) = manifest.size {
+        return invalid("size is zero".to_string());
+    }
+    if let Some(dpi) = manifest.dpi.filter(|dpi| *dpi <= 0.0) {
+        return invalid(format!("dpi {dpi} is not positive"));
+    }
+    if let Some(animation) = &manifest.animation {
+        if animation.frames == 0 {
+            return invalid("animation has no frames".to_string());
+        }
+        if animation.fps <= 0.0 {
+            return invalid(format!("animation fps {} is not positive", animation.fps));
+        }
+    }
+    let formats = Formats::default();
+    let outputs = manifest.outputs.iter().flatten();
+    for format in manifest.format.iter().chain(outputs.clone().flat_map(|output| &output.format)) {
+        if !formats.names().any(|name| name == format) {
+            return invalid(format!("unknown image format {format}"));
+        }
+    }
+    for (idx, output) in outputs.enumerate() {
+        if let Some(scale) = output.scale.filter(|scale| *scale <= 0.0) {
+            return invalid(format!("outputs[{idx}]: scale {scale} is not positive"));
+        }
+    }
+    let frames = manifest.animation.as_ref().map(|animation| animation.frames);
+    check_objects(&manifest.objects, "objects", base_dir, frames, &mut Vec::new())
+}
+
+/// Checks the placement and files objects and their children need to be drawn.
+///
+/// Keyframes must fall within the frames of the animation, if any.
+/// Objects may only refer to the ids of Objects placed before them, which are added to placed.
+/// The path of objects names them in errors.
+fn check_objects(
+    objects: &[ObjectInfo],
+    path: &str,
+    base_dir: &Path,
+    frames: Option<usize>,
+    placed: &mut Vec<String>,
+) -> Result<(), Error> {
+    let file = |src: &PathBuf| -> Result<(), Error> {
+        let path = base_dir.join(src);
+        match std::fs::metadata(&path) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(Error::Io(path, err)),
+        }
+    };
+    for (idx, object_info) in objects.iter().enumerate() {
+        let name = object_info.spec.name();
+        let sized = object_info.size.is_some() || object_info.offset.is_some();
+        let required: &[(&str, bool)] = match object_info.spec {
+            ObjectSpec::Rect { .. } => &[
+                ("offset", sized),
+                ("origin", object_info.size.is_some() || object_info.origin.is_some()),
+            ],
+            _ => &[],
+        };
+        if let Some((field, _)) = required.iter().find(|(_, present)| !present) {
+            return Err(Error::Invalid(format!("{path}[{idx}]: {name} has no {field}")));
+        }
+        let sides = [
+            ("below", &object_info.below),
+            ("above", &object_info.above),
+            ("left_of", &object_info.left_of),
+            ("right_of", &object_info.right_of),
+        ];
+        if sides.iter().filter(|(_, id)| id.is_some()).count() > 1 {
+            return Err(Error::Invalid(format!(
+                "{path}[{idx}]: {name} is placed next to more than one object"
+            )));
+        }
+        check_ranges(object_info).map_err(|err| Error::Invalid(format!("{path}[{idx}]: {err}")))?;
+        for (key, keyframe) in object_info.keyframes.iter().flatten().enumerate() {
+            let invalid = |err: String| Error::Invalid(format!("{path}[{idx}].keyframes[{key}]: {err}"));
+            if let Some(frames) = frames.filter(|frames| keyframe.frame >= *frames) {
+                return Err(invalid(format!("frame {} is not before the frame count {frames}", keyframe.frame)));
+            }
+            check_properties(keyframe.opacity, keyframe.scale).map_err(invalid)?;
+            if let Some(easing) = keyframe.easing.as_ref().filter(|easing| Easing::from_name(easing).is_none()) {
+                return Err(invalid(format!("unknown easing {easing}")));
+            }
+        }
+        for (field, id) in sides.into_iter().chain([("mask", &object_info.mask)]) {
+            if let Some(id) = id {
+                if !placed.contains(id) {
+                    return Err(Error::Invalid(format!("{path}[{idx}]: {field} {id} is not placed before")));
+                }
+            }
+        }
+        match &object_info.spec {
+            ObjectSpec::Image { src, .. } => file(src)?,
+            ObjectSpec::Text { font, .. } => file(font)?,
+            ObjectSpec::Layout { children, .. } => {
+                check_objects(children, &format!("{path}[{idx}].children"), base_dir, frames, placed)?
+            }
+            ObjectSpec::Rect { .. } => {}
+        }
+        if let Some(id) = &object_info.id {
+            placed.push(id.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the lengths and properties of an Object are within their ranges.
+fn check_ranges(object_info: &ObjectInfo) -> Result<(), String> {
+    check_properties(object_info.opacity, object_info.scale)?;
+    let (font_size, resize, gap, padding) = match &object_info.spec {
+        ObjectSpec::Image { resize, .. } => (None, *resize, None, None),
+        ObjectSpec::Text { font_size, .. } => (*font_size, None, None, None),
+        ObjectSpec::Layout { gap, padding, .. } => (None, None, *gap, *padding),
+        ObjectSpec::Rect { .. } => (None, None, None, None),
+    };
+    let sizes = [
+        ("font_size", font_size.into_iter().collect::<Vec<_>>()),
+        ("size", object_info.size.iter().flat_map(|(width, height)| [*width, *height]).collect()),
+        ("resize", resize.into_iter().flatten().collect()),
+        ("gap", gap.into_iter().collect()),
+        ("padding", padding.into_iter().collect()),
+    ];
+    for (field, lengths) in sizes {
+        if lengths.iter().any(|length| length.value() < 0.0) {
+            return Err(format!("{field} is negative"));
+        }
+    }
+    if font_size.is_some_and(|size| size.is_zero()) {
+        return Err("font_size is zero".to_string());
+    }
+    if let Some([width, height]) = resize {
+        if width.is_zero() && height.is_zero() {
+            return Err("resize is zero".to_string());
+        }
+        // Lengths relative to the parent are checked once resized, as they are only known then.
+        for length in [width, height] {
+            if let Length::Px(value) = length {
+                if value > 0.0 && value < 0.5 {
+                    return Err(format!("resize {value}px is smaller than a pixel"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that an opacity is between 0 and 1, and a scale is positive.
+fn check_properties(opacity: Option<f64>, scale: Option<f64>) -> Result<(), String> {
+    if let Some(opacity) = opacity.filter(|opacity| !(0.0..=1.0).contains(opacity)) {
+        return Err(format!("opacity {opacity} is not between 0 and 1"));
+    }
+    if let Some(scale) = scale.filter(|scale| *scale <= 0.0) {
+        return Err(format!("scale {scale} is not positive"));
+    }
+    Ok(())
+}
+
+/// Checks that the Images, fonts and used assets of the Manifest can be decoded,
+/// returning warnings about the assets no Object uses.
+pub fn check_files(manifest: &Manifest, base_dir: &Path) -> Result<Vec<String>, Error> {
+    let mut images = Vec::new();
+    let mut fonts = Vec::new();
+    decoded_files(&manifest.objects, &mut images, &mut fonts);
+    let used = used_assets(&manifest.objects);
+    let assets = manifest.assets.as_deref().unwrap_or_default();
+    if let Some(id) = used.iter().find(|id| !assets.iter().any(|asset_info| asset_info.id == ***id)) {
+        return Err(Error::Invalid(format!("no asset {id}")));
+    }
+    let mut warnings = Vec::new();
+    for asset_info in assets {
+        if !used.contains(&&asset_info.id) {
+            warnings.push(format!("asset {} ({}) is not used", asset_info.id, asset_info.src.display()));
+            continue;
+        }
+        let file = base_dir.join(&asset_info.src);
+        match FileFormat::from_file(&file).map_err(|err| Error::Io(file.clone(), err))?.kind() {
+            Kind::Font => fonts.push(&asset_info.src),
+            _ => images.push((&asset_info.src, None)),
+        }
+    }
+    for (src, frame) in images {
+        check_image(&base_dir.join(src), frame)?;
+    }
+    for src in fonts {
+        let file = base_dir.join(src);
+        let data = fs::read(&file).map_err(|err| Error::Io(file.clone(), err))?;
+        if !Font::is_font(&data) {
+            return Err(Error::Invalid(format!("{} is not a font", file.display())));
+        }
+    }
+    Ok(warnings)
+}
+
+/// Collects the Image sources and frames and the fonts of objects and their children.
+fn decoded_files<'a>(
+    objects: &'a [ObjectInfo],
+    images: &mut Vec<(&'a PathBuf, Option<usize>)>,
+    fonts: &mut Vec<&'a PathBuf>,
+) {
+    for object_info in objects {
+        match &object_info.spec {
+            ObjectSpec::Image { src, frame, .. } => images.push((src, *frame)),
+            ObjectSpec::Text { font, .. } => fonts.push(font),
+            _ => {}
+        }
+        decoded_files(object_info.spec.children(), images, fonts);
+    }
+}
+
+/// Returns the asset ids objects and their children use, once each.
+pub fn used_assets(objects: &[ObjectInfo]) -> Vec<&String> {
+    let mut used = Vec::new();
+    for object_info in objects {
+        for id in object_info.asset.iter().chain(used_assets(object_info.spec.children())) {
+            if !used.contains(&id) {
+                used.push(id);
+            }
         }
     }
-    let mut canvas = Canvas::new(manifest.format, manifest.size[0], manifest.size[1]);
-    canvas.new_rect((0.0, 0.0), (100.0, 100.0), manifest.color);
-    if manifest.assets.is_some() {
-        parse_assets(manifest.assets.unwrap());
+    used
+}
+
+/// Checks that frame of the Image file can be decoded.
+fn check_image(file: &Path, frame: Option<usize>) -> Result<(), Error> {
+    let fmt = FileFormat::from_file(file).map_err(|err| Error::Io(file.to_path_buf(), err))?;
+    if fmt.kind() != Kind::Image {
+        return Err(Error::Invalid(format!("{}: not an image but {}", file.display(), fmt.name())));
+    }
+    Decoders::default().decode(file, frame)?;
+    Ok(())
+}
+
+/// Writes the Image of the Manifest text to w.
+///
+/// Included Manifests, data and relative source paths of Objects are resolved from base_dir.
+/// The Manifest must have a single output and a single row of data, and its filename is ignored.
+pub fn write_manifest(text: &str, base_dir: &Path, w: &mut dyn Write) -> Result<(), Error> {
+    let mut manifests = read_manifests(text, &[], None, base_dir)?;
+    if manifests.len() > 1 {
+        return Err(Error::Invalid(format!("cannot write {} rows of data to a single writer", manifests.len())));
+    }
+    let manifest = manifests.remove(0);
+    check_renderable(&manifest, base_dir)?;
+    let outputs = parse_outputs(&manifest)?;
+    if outputs.len() > 1 {
+        return Err(Error::Invalid(format!("cannot write {} outputs to a single writer", outputs.len())));
+    }
+    write_output(&manifest, parse_format(&outputs[0])?, base_dir, &mut AssetCache::new(), w)
+}
+
+/// Writes the output Images of the Manifest to their files.
+///
+/// Filenames are relative to base_dir. Outputs without a filename are named
+/// after the Manifest file, or written to stdout if there is none.
+/// The output of the command line replaces the filename of the Manifest,
+/// which must then have a single output.
+/// If row is given, filenames are numbered after it, as in `card-2.png`.
+fn write_outputs(
+    manifest: &Manifest,
+    base_dir: &Path,
+    file: Option<&Path>,
+    row: Option<usize>,
+    cli: &Cli,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let mut outputs = parse_outputs(manifest)?;
+    if let Some(output) = &cli.output {
+        if outputs.len() > 1 {
+            return Err(Error::Invalid(format!(
+                "cannot write {} outputs to {}",
+                outputs.len(),
+                output.display()
+            )));
+        }
+        outputs[0].filename = Some(output.clone());
+        // The extension of the output names the format, unless one is given on the command line.
+        let formats = Formats::default();
+        if let (None, Some(name)) = (&cli.format, formats.from_extension(output)) {
+            if !outputs[0].format.as_ref().is_some_and(|format| formats.fits_extension(format, output)) {
+                outputs[0].format = Some(name.to_string());
+            }
+        }
+    }
+    for output_info in outputs {
+        let start = Instant::now();
+        let format = parse_format(&output_info)?;
+        let extension = format.extension();
+        let filename = match (&output_info.filename, file) {
+            (Some(filename), _) if filename.as_os_str() == "-" => None,
+            (Some(filename), _) => Some(base_dir.join(filename)),
+            (None, Some(file)) => Some(default_output(file, extension)),
+            (None, None) => None,
+        };
+        let filename = match (filename, row) {
+            (Some(filename), Some(row)) => Some(numbered(&filename, row)),
+            (None, Some(_)) => return Err(Error::Invalid("cannot write rows of data to stdout".to_string())),
+            (filename, None) => filename,
+        };
+        let mut w: Box<dyn Write> = match &filename {
+            Some(filename) => match File::create(filename) {
+                Ok(f) => Box::new(BufWriter::new(f)),
+                Err(err) => return Err(Error::Io(filename.clone(), err)),
+            },
+            None => Box::new(BufWriter::new(io::stdout().lock())),
+        };
+        let path = || filename.clone().unwrap_or_else(|| PathBuf::from("-"));
+        match write_output(manifest, format, base_dir, cache, &mut w) {
+            Err(Error::Write(err)) => return Err(Error::Io(path(), err)),
+            result => result?,
+        }
+        w.flush().map_err(|err| Error::Io(path(), err))?;
+        if cli.verbosity == Verbosity::Verbose {
+            let [width, height] = output_info.size.unwrap();
+            let name = filename.map_or("stdout".to_string(), |filename| filename.display().to_string());
+            eprintln!("wrote {name} ({extension} {width}x{height}) in {:.0?}", start.elapsed());
+        }
+    }
+    Ok(())
+}
+
+/// Returns filename with `-row` appended to its stem.
+fn numbered(filename: &Path, row: usize) -> PathBuf {
+    let stem = filename.file_stem().unwrap_or_default().to_string_lossy();
+    match filename.extension() {
+        Some(extension) => filename.with_file_name(format!("{stem}-{row}.{}", extension.to_string_lossy())),
+        None => filename.with_file_name(format!("{stem}-{row}")),
+    }
+}
+
+/// Writes the output Images of the Manifests of the rows of data to their files.
+///
+/// If the rows name their outputs alike, the filenames are numbered after the rows,
+/// counting from one.
+fn write_rows(
+    manifests: &[Manifest],
+    base_dir: &Path,
+    file: Option<&Path>,
+    cli: &Cli,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let filenames = |manifest: &Manifest| -> Result<Vec<Option<PathBuf>>, Error> {
+        Ok(parse_outputs(manifest)?.into_iter().map(|output| output.filename).collect())
+    };
+    let numbered = manifests.len() > 1 && (cli.output.is_some() || filenames(&manifests[0])? == filenames(&manifests[1])?);
+    for (idx, manifest) in manifests.iter().enumerate() {
+        write_outputs(manifest, base_dir, file, numbered.then_some(idx + 1), cli, cache)?;
+    }
+    Ok(())
+}
+
+/// Checks that the Manifests of the rows of data can be rendered.
+fn check_rows(manifests: &[Manifest], base_dir: &Path) -> Result<(), Error> {
+    for (idx, manifest) in manifests.iter().enumerate() {
+        check_renderable(manifest, base_dir).map_err(|err| match manifests.len() {
+            1 => err,
+            _ => Error::Invalid(format!("row {}: {err}", idx + 1)),
+        })?;
+    }
+    Ok(())
+}
+
+/// Parses the Manifest named on the command line and writes its output Images,
+/// once per row of its data.
+pub fn parse_manifest(cli: &Cli) -> Result<(), Error> {
+    let (manifests, base_dir) = load_manifests(cli)?;
+    check_rows(&manifests, &base_dir)?;
+    let file = (cli.file.as_os_str() != "-").then_some(cli.file.as_path());
+    write_rows(&manifests, &base_dir, file, cli, &mut AssetCache::new())
+}
+
+/// Returns the files the included Manifests, data, Objects and assets of the Manifest are loaded from.
+pub fn manifest_files(manifest: &Manifest, base_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let includes = manifest.include.iter().flatten();
+    let data = manifest.data.iter().flat_map(|data_info| &data_info.src);
+    let assets = manifest.assets.iter().flatten().map(|asset_info| &asset_info.src);
+    let mut objects = Vec::new();
+    object_sources(&manifest.objects, &mut objects);
+    for src in includes.chain(data).chain(assets).chain(objects) {
+        let file = base_dir.join(src);
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Collects the source and font paths of objects and their children into sources.
+fn object_sources<'a>(objects: &'a [ObjectInfo], sources: &mut Vec<&'a PathBuf>) {
+    for object_info in objects {
+        match &object_info.spec {
+            ObjectSpec::Image { src, .. } => sources.push(src),
+            ObjectSpec::Text { font, .. } => sources.push(font),
+            _ => {}
+        }
+        object_sources(object_info.spec.children(), sources);
+    }
+}
+
+/// Renders the Manifest named on the command line whenever it or a file it
+/// loads changes, printing how long it took or what went wrong.
+///
+/// Polls the modification times of the files until interrupted.
+/// Decoded and resized Images are cached between renders.
+pub fn watch_manifest(cli: &Cli) -> Result<(), Error> {
+    if cli.file.as_os_str() == "-" {
+        return Err(Error::Invalid("cannot watch a manifest read from stdin".to_string()));
+    }
+    let stamps = |files: &[PathBuf]| files.iter().map(|file| modified(file)).collect::<Vec<_>>();
+    let mut cache = AssetCache::new();
+    let watched = || [Some(cli.file.clone()), cli.data.clone()].into_iter().flatten().collect::<Vec<_>>();
+    let mut files = watched();
+    let mut seen = Vec::new();
+    loop {
+        if stamps(&files) != seen {
+            let start = Instant::now();
+            let loads = cache.loads();
+            let manifests = load_manifests(cli);
+            files = watched();
+            if let Ok((manifests, base_dir)) = &manifests {
+                for file in manifests.iter().flat_map(|manifest| manifest_files(manifest, base_dir)) {
+                    if !files.contains(&file) {
+                        files.push(file);
+                    }
+                }
+            }
+            // Stamp the files before rendering, so changes made meanwhile render again.
+            seen = stamps(&files);
+            let result = manifests.and_then(|(manifests, base_dir)| {
+                check_rows(&manifests, &base_dir)?;
+                write_rows(&manifests, &base_dir, Some(&cli.file), cli, &mut cache)
+            });
+            match result {
+                Ok(()) if cli.verbosity != Verbosity::Quiet => eprintln!(
+                    "rendered {} in {:.0?} (files loaded: {})",
+                    cli.file.display(),
+                    start.elapsed(),
+                    cache.loads() - loads
+                ),
+                Ok(()) => {}
+                Err(err) => eprintln!("rasm: {err}"),
+            }
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Checks that the Manifest named on the command line can be rendered, decoding its
+/// files, and returns warnings about what it does not use.
+pub fn check_manifest(cli: &Cli) -> Result<Vec<String>, Error> {
+    let (manifests, base_dir) = load_manifests(cli)?;
+    check_rows(&manifests, &base_dir)?;
+    let mut warnings = Vec::new();
+    for manifest in &manifests {
+        for warning in check_files(manifest, &base_dir)? {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Closes the schema of each kind of Object to its fields and those common to all
+/// Objects, rejecting others as reading does, and adds instances of components,
+/// named by any other name.
+fn object_schema(schema: &mut schemars::Schema) {
+    let common = schema.get("properties").and_then(|properties| properties.as_object()).cloned().unwrap_or_default();
+    let Some(serde_json::Value::Array(kinds)) = schema.get_mut("oneOf") else {
+        return;
+    };
+    let mut names = Vec::new();
+    for kind in kinds.iter_mut() {
+        names.extend(kind.pointer("/properties/name/const").cloned());
+        if let Some(serde_json::Value::Object(properties)) = kind.get_mut("properties") {
+            for (field, property) in &common {
+                properties.entry(field.clone()).or_insert_with(|| property.clone());
+            }
+        }
+        kind["additionalProperties"] = false.into();
+    }
+    kinds.push(serde_json::json!({
+        "description": "An instance of a component, drawn as the objects of the component of its name.",
+        "type": "object",
+        "properties": {
+            "name": { "type": "string", "not": { "enum": names } },
+            "id": common.get("id"),
+            "params": {
+                "description": "Values of the `$param` strings of the objects of the component.",
+                "type": ["object", "null"],
+            },
+        },
+        "required": ["name"],
+        "additionalProperties": false,
+    }));
+}
+
+/// Returns the Json Schema of Manifests, with which editors complete and validate them.
+///
+/// Values replaced by variables or placeholders are validated as the strings they are written as.
+pub fn manifest_schema() -> String {
+    serde_json::to_string_pretty(&schemars::schema_for!(Manifest)).unwrap()
+}
+
+/// Returns the format of the file named on the command line if it is an Image
+/// rather than a Manifest.
+pub fn image_format(cli: &Cli) -> Option<FileFormat> {
+    if cli.file.as_os_str() == "-" {
+        return None;
     }
-    for object_info in manifest.objects {
-        match object_info.name.as_str() {
-            "rect" => parse_rect(&mut canvas, object_info),
-            "image" => parse_image(&mut canvas, object_info, file.clone()),
-            &_ => panic!("unknown object"),
+    let fmt = FileFormat::from_file(&cli.file).ok()?;
+    (fmt.kind() == Kind::Image).then_some(fmt)
+}
+
+/// Reads the Image named on the command line, or renders the first frame of
+/// the first output Image of the Manifest named on it.
+pub fn load_image(cli: &Cli) -> Result<Image, Error> {
+    if image_format(cli).is_some() {
+        return Image::from_file(&cli.file);
+    }
+    let (manifest, base_dir) = load_manifest(cli)?;
+    render_in(&manifest, &base_dir)
+}
+
+/// Describes the output Images, the Objects and the animation of a Manifest.
+pub fn manifest_info(manifest: &Manifest) -> Result<String, Error> {
+    let mut info = String::new();
+    if let Some([width, height]) = manifest.size {
+        info.push_str(&format!("size: {width}x{height}\n"));
+    }
+    info.push_str("outputs:\n");
+    for output_info in parse_outputs(manifest)? {
+        let [width, height] = output_info.size.unwrap();
+        let filename = output_info
+            .filename
+            .as_ref()
+            .map_or("(default)".to_string(), |filename| filename.display().to_string());
+        let format = output_info
+            .format
+            .clone()
+            .or_else(|| {
+                let filename = output_info.filename.as_ref()?;
+                Formats::default().from_extension(filename).map(str::to_string)
+            })
+            .unwrap_or_else(|| "?".to_string());
+        info.push_str(&format!("  {filename} {format} {width}x{height}\n"));
+    }
+    let mut names: Vec<(&str, usize)> = Vec::new();
+    for object_info in &manifest.objects {
+        let name = object_info.spec.name();
+        match names.iter_mut().find(|(other, _)| *other == name) {
+            Some((_, count)) => *count += 1,
+            None => names.push((name, 1)),
         }
     }
-    canvas.save(file.to_str().unwrap());
+    let names: Vec<String> = names.iter().map(|(name, count)| format!("{count} {name}")).collect();
+    info.push_str(&format!("objects: {} ({})\n", manifest.objects.len(), names.join(", ")));
+    if let Some(animation) = &manifest.animation {
+        info.push_str(&format!("animation: {} frames at {} fps\n", animation.frames, animation.fps));
+    }
+    Ok(info)
+}
+
+/// Starter Manifest written by `rasm init`.
+pub const STARTER_MANIFEST: &str = r##"# Rasm manifest. Coordinates are percentages of the width and height of the image,
+# or lengths in px, %, vw, vh, pt or em as in "24px".
+# `rasm schema > rasm.schema.json` writes a schema editors complete it with.
+format = "png"
+size = [1080, 1080]
+color = "white"
+
+# Colors are [r, g, b, a] arrays, hex, CSS names, rgb(), hsl() or oklch(),
+# and "$name" refers to a variable.
+[vars]
+brand = "#00964b"
+
+[[objects]]
+name = "rect"
+color = "$brand"
+origin = [0, 40]
+offset = [100, 60]
+
+# [[objects]]
+# name = "image"
+# src = "picture.jpg"
+# origin = [0, 0]
+# anchor = "center"
+# parent_anchor = "center"
+# resize = [100, 0]
+
+# [[objects]]
+# name = "text"
+# font = "font.ttf"
+# font_size = 5
+# content = "Hello"
+# origin = [5, 42]
+# offset = [95, 58]
+# color = "white"
+"##;
+
+/// Writes the starter Manifest to Rasm.toml in dir, creating dir if needed.
+///
+/// Returns the path of the Manifest. An existing Manifest is never replaced.
+pub fn init_manifest(dir: &Path) -> Result<PathBuf, Error> {
+    let file = dir.join("Rasm.toml");
+    if file.exists() {
+        return Err(Error::Invalid(format!("{} already exists", file.display())));
+    }
+    fs::create_dir_all(dir).map_err(|err| Error::Io(dir.to_path_buf(), err))?;
+    fs::write(&file, STARTER_MANIFEST).map_err(|err| Error::Io(file.clone(), err))?;
+    Ok(file)
 }
@@ -1,10 +1,23 @@
+//! Tests for the generator.
+use crate::format::*;
 use crate::image::*;
 use crate::object::*;
+use crate::parse::{ObjectInfo, ObjectSpec};
+use crate::util::*;
 
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::Path;
 
+/// Returns the color of a rect, text or layout object.
+fn color(object_info: &ObjectInfo) -> Option<[u8; 4]> {
+    match &object_info.spec {
+        ObjectSpec::Rect { color } => Some(*color),
+        ObjectSpec::Text { color, .. } | ObjectSpec::Layout { color, .. } => *color,
+        ObjectSpec::Image { .. } => None,
+    }
+}
+
 #[test]
 fn create_png() {
     let mut img = Image::new(1080, 1080);
@@ -21,9 +34,9 @@ fn create_png() {
     rect3.paste(&mut img);
     rect4.paste(&mut img);
 
-    let path = Path::new(r"test.png");
-    let file = File::create(path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let path = std::env::temp_dir().join("rasm-create.png");
+    let file = File::create(&path).unwrap();
+    let w = &mut BufWriter::new(file);
     let mut encoder = png::Encoder::new(w, 1080, 1080); // Width is 2 pixels and height is 1.
     encoder.set_color(png::ColorType::Rgba);
     encoder.set_depth(png::BitDepth::Eight);
@@ -45,3 +58,1140 @@ fn create_png() {
                                                                //println!("{:?}", img[0][0][0][0]);
                                                                //println!("{:?}", img.to_vec().as_slice()[0])
 }
+
+#[test]
+fn png_color_type_and_bit_depth() {
+    use crate::format::*;
+
+    let filename = std::env::temp_dir().join("rasm-png-gray16.png");
+    let mut png = Png::new(2, 1);
+    png.image()[0][0][0] = Pixel::from(&[255, 0, 0, 255]);
+    png.set_color_type(png::ColorType::Grayscale);
+    png.set_bit_depth(png::BitDepth::Sixteen);
+    png.write(&mut File::create(&filename).unwrap()).unwrap();
+
+    let file = File::open(&filename).unwrap();
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(&buf[..4], &[76, 76, 255, 255]);
+
+    // Opaque Images drop the alpha channel by default.
+    let mut png = Png::new(2, 1);
+    png.write(&mut File::create(&filename).unwrap()).unwrap();
+    let file = File::open(&filename).unwrap();
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Rgb);
+
+    // Unsupported combinations are errors, written before anything else.
+    let mut png = Png::new(2, 1);
+    png.set_color_type(png::ColorType::Indexed);
+    let mut bytes = Vec::new();
+    assert_eq!(png.write(&mut bytes).unwrap_err().to_string(), "unsupported png color type Indexed");
+    png.set_color_type(png::ColorType::Grayscale);
+    png.set_bit_depth(png::BitDepth::Four);
+    assert_eq!(png.write(&mut bytes).unwrap_err().to_string(), "unsupported png bit depth 4");
+    assert!(bytes.is_empty());
+}
+
+#[test]
+fn jpg_matte() {
+    use crate::format::*;
+    use zune_core::colorspace::ColorSpace;
+    use zune_core::options::DecoderOptions;
+    use zune_jpeg::JpegDecoder;
+
+    let filename = std::env::temp_dir().join("rasm-jpg-matte.jpg");
+    let mut jpg = Jpg::new(16, 16);
+    jpg.image()[0].fill(Pixel::from(&[0, 0, 0, 0]));
+    jpg.set_matte(Pixel::from(&[0, 0, 255, 255]));
+    jpg.set_quality(90);
+    jpg.set_sampling_factor(jpeg_encoder::SamplingFactor::R_4_2_0);
+    jpg.set_progressive(true);
+    jpg.set_optimized_huffman_tables(true);
+    jpg.write(&mut File::create(&filename).unwrap()).unwrap();
+
+    let file_contents = std::fs::read(&filename).unwrap();
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGB);
+    let pixels = JpegDecoder::new_with_options(&file_contents, options).decode().unwrap();
+    assert!(pixels[0] < 8 && pixels[1] < 8 && pixels[2] > 247);
+}
+
+#[test]
+fn webp_near_lossless_with_alpha() {
+    use crate::format::*;
+
+    let filename = std::env::temp_dir().join("rasm-webp-near-lossless.webp");
+    let mut webp = Webp::new(4, 4);
+    webp.image()[0][0][0] = Pixel::from(&[13, 200, 99, 128]);
+    webp.set_near_lossless(50);
+    webp.write(&mut File::create(&filename).unwrap()).unwrap();
+
+    let file = File::open(&filename).unwrap();
+    let mut decoder = image_webp::WebPDecoder::new(std::io::BufReader::new(file)).unwrap();
+    assert!(decoder.has_alpha());
+    let mut buf = vec![0; decoder.output_buffer_size().unwrap()];
+    decoder.read_image(&mut buf).unwrap();
+    assert_eq!(&buf[..8], &[16, 200, 96, 128, 255, 255, 255, 255]);
+}
+
+#[test]
+fn webp_lossy_with_alpha() {
+    use crate::format::*;
+
+    let mut webp = Webp::new(40, 24);
+    for y in 0..24 {
+        for x in 0..40 {
+            let alpha = if x < 20 { 255 } else { 0 };
+            webp.image()[0][y][x] = Pixel::from(&[(x * 6) as u8, 200, (y * 10) as u8, alpha]);
+        }
+    }
+    webp.set_quality(90);
+    let mut bytes = Vec::new();
+    webp.write(&mut bytes).unwrap();
+    assert!(bytes.windows(4).any(|chunk| chunk == b"VP8 "));
+
+    let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!(decoder.dimensions(), (40, 24));
+    assert!(decoder.has_alpha());
+    let mut buf = vec![0; decoder.output_buffer_size().unwrap()];
+    decoder.read_image(&mut buf).unwrap();
+    for (i, pixel) in buf.chunks(4).enumerate() {
+        let (x, y) = (i % 40, i / 40);
+        assert_eq!(pixel[3], if x < 20 { 255 } else { 0 });
+        let expected = [x * 6, 200, y * 10];
+        for (&value, expected) in pixel.iter().zip(expected) {
+            assert!(value.abs_diff(expected as u8) <= 12, "{pixel:?} at {x}x{y}");
+        }
+    }
+}
+
+#[test]
+fn format_registry() {
+    use crate::format::*;
+    use crate::parse::FormatInfo;
+
+    let formats = Formats::default();
+    assert_eq!(formats.from_extension("card.JPEG"), Some("jpg"));
+    assert_eq!(formats.from_extension("card.tif"), Some("tiff"));
+    assert_eq!(formats.from_extension("card.toml"), None);
+
+    let dir = std::env::temp_dir();
+    for name in ["qoi", "bmp", "tga", "ppm", "pam", "tiff"] {
+        let mut format = formats.get(name, 3, 2, &FormatInfo::default()).unwrap();
+        format.image()[0][1][2] = Pixel::from(&[10, 20, 30, 40]);
+        let filename = dir.join(format!("rasm-registry.{}", format.extension()));
+        format.write(&mut File::create(&filename).unwrap()).unwrap();
+        let bytes = std::fs::read(&filename).unwrap();
+        let magic: &[u8] = match name {
+            "qoi" => b"qoif",
+            "bmp" => b"BM",
+            "tga" => &[0, 0, 2],
+            "ppm" => b"P6\n3 2\n255\n",
+            "pam" => b"P7\nWIDTH 3\nHEIGHT 2\nDEPTH 4\n",
+            _ => b"II*\0",
+        };
+        assert!(bytes.starts_with(magic), "{name}");
+    }
+
+    let qoi = std::fs::read(dir.join("rasm-registry.qoi")).unwrap();
+    let (header, pixels) = qoi::decode_to_vec(qoi).unwrap();
+    assert_eq!((header.width, header.height), (3, 2));
+    assert_eq!(&pixels[20..24], &[10, 20, 30, 40]);
+}
+
+#[test]
+fn decoder_registry_round_trip() {
+    use crate::decoder::*;
+    use crate::format::*;
+    use crate::parse::FormatInfo;
+
+    let formats = Formats::default();
+    let decoders = Decoders::default();
+    let dir = std::env::temp_dir();
+    for (name, color) in [
+        ("bmp", [10, 20, 30, 40]),
+        ("bmp", [10, 20, 30, 255]),
+        ("qoi", [10, 20, 30, 40]),
+        ("tiff", [10, 20, 30, 40]),
+        ("webp", [10, 20, 30, 40]),
+        ("png", [10, 20, 30, 40]),
+    ] {
+        let mut format = formats.get(name, 3, 2, &FormatInfo::default()).unwrap();
+        format.image()[0][1][2] = Pixel::from(&color);
+        let filename = dir.join(format!("rasm-decode.{}", format.extension()));
+        format.write(&mut File::create(&filename).unwrap()).unwrap();
+        let img = decoders.decode(&filename, None).unwrap();
+        assert_eq!((img.width, img.height), (3, 2), "{name}");
+        assert_eq!(img[0][1][2], Pixel::from(&color), "{name}");
+        assert_eq!(img[0][0][0], Pixel::from(&[255, 255, 255, 255]), "{name}");
+    }
+}
+
+#[test]
+fn decode_gif_frame() {
+    use crate::decoder::*;
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut bytes, 2, 1, &[255, 0, 0, 0, 0, 255]).unwrap();
+        let mut frame = gif::Frame::from_indexed_pixels(2, 1, vec![0, 0], None);
+        encoder.write_frame(&frame).unwrap();
+        frame = gif::Frame::from_indexed_pixels(1, 1, vec![1], None);
+        frame.left = 1;
+        encoder.write_frame(&frame).unwrap();
+    }
+    let img = from_gif(&bytes, None).unwrap();
+    assert_eq!(img[0][0][1], Pixel::from(&[255, 0, 0, 255]));
+    let img = from_gif(&bytes, Some(1)).unwrap();
+    assert_eq!(img[0][0][0], Pixel::from(&[255, 0, 0, 255]));
+    assert_eq!(img[0][0][1], Pixel::from(&[0, 0, 255, 255]));
+
+    let file = std::env::temp_dir().join("rasm-frames.gif");
+    std::fs::write(&file, &bytes).unwrap();
+    let err = Decoders::default().decode(&file, Some(2)).unwrap_err();
+    assert_eq!(err.to_string(), format!("{} frame 2: gif has 2 frames", file.display()));
+    std::fs::write(&file, &bytes[..bytes.len() / 2]).unwrap();
+    assert!(Decoders::default().decode(&file, None).is_err());
+    assert!(from_ico(&bytes, Some(3)).is_err());
+
+    let formats: [Box<dyn crate::format::Format>; 5] = [
+        Box::new(crate::format::Png::new(3, 2)),
+        Box::new(crate::format::Jpg::new(3, 2)),
+        Box::new(crate::format::Bmp::new(3, 2)),
+        Box::new(crate::format::Qoi::new(3, 2)),
+        Box::new(crate::format::Webp::new(3, 2)),
+    ];
+    for mut format in formats {
+        let name = format.extension();
+        let file = std::env::temp_dir().join(format!("rasm-frames.{name}"));
+        format.write(&mut std::fs::File::create(&file).unwrap()).unwrap();
+        Decoders::default().decode(&file, Some(0)).unwrap();
+        let err = Decoders::default().decode(&file, Some(1)).unwrap_err();
+        let message = match name {
+            "png" => "only the first frame of a png can be decoded".to_string(),
+            name => format!("{name} has 1 frame"),
+        };
+        assert_eq!(err.to_string(), format!("{} frame 1: {message}", file.display()));
+    }
+}
+
+#[test]
+fn keyframes_and_easing() {
+    use crate::animation::*;
+
+    let mut track = Track::new();
+    track.insert(10, 1.0, Easing::Linear);
+    track.insert(0, 0.0, Easing::from_name("ease-in-out").unwrap());
+    track.insert(20, 3.0, Easing::Step);
+    assert!(track.at(0).unwrap().abs() < 1e-6);
+    assert!((track.at(5).unwrap() - 0.5).abs() < 1e-6);
+    assert!(track.at(2).unwrap() < 0.2);
+    assert_eq!(track.at(15), Some(2.0));
+    assert_eq!(track.at(25), Some(3.0));
+}
+
+#[test]
+fn animated_gif_and_apng() {
+    use crate::parse::{parse_manifest, Cli};
+
+    let dir = std::env::temp_dir().join("rasm-animation");
+    std::fs::create_dir_all(&dir).unwrap();
+    for (format, output) in [("gif", "card.gif"), ("apng", "card.png")] {
+        let manifest = dir.join(format!("{format}.manifest.toml"));
+        std::fs::write(
+            &manifest,
+            format!(
+                r#"
+format = "{format}"
+output = "{output}"
+size = [8, 8]
+color = [255, 255, 255, 255]
+[animation]
+frames = 3
+fps = 10
+loops = 2
+[[objects]]
+name = "rect"
+color = [255, 0, 0, 255]
+origin = [0, 0]
+offset = [50, 50]
+[[objects.keyframes]]
+frame = 0
+color = [255, 0, 0, 255]
+[[objects.keyframes]]
+frame = 2
+color = [0, 0, 255, 255]
+rotation = 90
+"#
+            ),
+        )
+        .unwrap();
+        parse_manifest(&Cli { file: manifest, ..Default::default() }).unwrap();
+    }
+
+    let file = File::open(dir.join("card.gif")).unwrap();
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(file).unwrap();
+    assert_eq!(decoder.repeat(), gif::Repeat::Finite(1));
+    let first = decoder.read_next_frame().unwrap().unwrap().buffer.to_vec();
+    assert_eq!(&first[..4], &[255, 0, 0, 255]);
+    decoder.read_next_frame().unwrap().unwrap();
+    let last = decoder.read_next_frame().unwrap().unwrap();
+    assert_eq!(last.delay, 10);
+    assert_eq!(&last.buffer[..4], &[0, 0, 255, 255]);
+    assert!(decoder.read_next_frame().unwrap().is_none());
+
+    let file = File::open(dir.join("card.png")).unwrap();
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    let animation = reader.info().animation_control.unwrap();
+    assert_eq!((animation.num_frames, animation.num_plays), (3, 2));
+}
+
+#[test]
+fn svg_output() {
+    let path = std::env::temp_dir().join("rasm-vector.svg");
+    let mut canvas = Canvas::new("svg", 20, 20).unwrap();
+    canvas.new_rect((0.0, 0.0), (100.0, 100.0), [255, 0, 0, 128]);
+    let image = Image::from(((2, 2), (0, 0), vec![0; 16]));
+    canvas.add_image((50.0, 50.0), image);
+    canvas.save(path.to_str().unwrap()).unwrap();
+
+    let document = std::fs::read_to_string(&path).unwrap();
+    assert!(document.contains(r#"viewBox="0 0 20 20""#));
+    assert!(document.contains(r##"<rect fill="#ff0000" fill-opacity="0.5019607843137255" height="20" width="20" x="0" y="0"/>"##));
+    assert!(document.contains(r#"href="data:image/png;base64,"#));
+    assert!(document.contains(r#"x="10" y="10""#));
+}
+
+#[test]
+fn pdf_output() {
+    let path = std::env::temp_dir().join("rasm-print.pdf");
+    let mut pdf = Pdf::new(20, 20);
+    pdf.set_dpi(144.0);
+    let mut canvas = Canvas::with_format(Box::new(pdf));
+    canvas.new_rect((0.0, 0.0), (100.0, 100.0), [255, 0, 0, 128]);
+    let image = Image::from(((2, 2), (0, 0), vec![0; 16]));
+    canvas.add_image((50.0, 50.0), image);
+    canvas.save(path.to_str().unwrap()).unwrap();
+
+    let document = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+    assert!(document.starts_with("%PDF-"));
+    assert!(document.contains("/MediaBox [0 0 10 10]"));
+    assert!(document.contains("/Im0 5 0 R"));
+    assert!(document.contains("/SMask 6 0 R"));
+    assert!(document.contains("/ca 0.5019608"));
+
+    let mut pdf = Pdf::new(70000, 1);
+    pdf.set_quality(80);
+    let mut canvas = Canvas::with_format(Box::new(pdf));
+    canvas.add_image((0.0, 0.0), Image::new(70000, 1));
+    canvas.add_image((0.0, 0.0), Image::new(2, 1));
+    canvas.save(path.to_str().unwrap()).unwrap();
+    let document = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+    assert!(document.contains("/Width 70000"));
+    assert_eq!((document.matches("/FlateDecode").count(), document.matches("/DCTDecode").count()), (2, 1));
+
+    // The repository has no font, so text is checked where the system has one.
+    let font_file = Path::new("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf");
+    let Ok(data) = std::fs::read(font_file) else {
+        return;
+    };
+    let font = crate::asset::Font::new(font_file.to_path_buf(), data).unwrap();
+    let mut canvas = Canvas::with_format(Box::new(Pdf::new(200, 100)));
+    canvas.new_text((0.0, 0.0), (100.0, 50.0), "fi AV", font.clone(), 20.0, [0, 0, 0, 255]);
+    canvas.new_text((0.0, 50.0), (100.0, 100.0), "Wave", font, 20.0, [0, 0, 0, 255]);
+    canvas.save(path.to_str().unwrap()).unwrap();
+    let document = String::from_utf8_lossy(&std::fs::read(&path).unwrap()).into_owned();
+    assert_eq!(document.matches("/Subtype /Type0").count(), 1);
+    assert!(document.contains("+DejaVuSans\n") && document.contains("/Encoding /Identity-H"));
+    assert!(document.contains("/Subtype /CIDFontType2") && document.contains("/FontFile2"));
+    // The ligature of f and i is mapped back to both.
+    assert!(document.contains("<00660069>"));
+}
+
+#[test]
+fn multiple_outputs() {
+    use crate::parse::{parse_manifest, Cli};
+
+    let dir = std::env::temp_dir().join("rasm-outputs");
+    std::fs::create_dir_all(&dir).unwrap();
+    let manifest = dir.join("card.manifest.toml");
+    std::fs::write(
+        &manifest,
+        r#"
+size = [40, 40]
+color = [255, 255, 255, 255]
+[png]
+color_type = "gray"
+[[outputs]]
+filename = "square.png"
+[[outputs]]
+filename = "story.png"
+size = [9, 16]
+[[outputs]]
+filename = "banner.jpg"
+scale = 0.5
+[[objects]]
+name = "rect"
+color = [0, 0, 0, 255]
+origin = [0, 0]
+offset = [50, 50]
+"#,
+    )
+    .unwrap();
+    parse_manifest(&Cli { file: manifest, ..Default::default() }).unwrap();
+
+    for (filename, size) in [("square.png", (40, 40)), ("story.png", (9, 16))] {
+        let file = File::open(dir.join(filename)).unwrap();
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), size);
+        assert_eq!(reader.info().color_type, png::ColorType::Grayscale);
+    }
+    let file = std::fs::read(dir.join("banner.jpg")).unwrap();
+    let mut decoder = zune_jpeg::JpegDecoder::new(&file);
+    decoder.decode_headers().unwrap();
+    assert_eq!(decoder.dimensions(), Some((20, 20)));
+}
+
+#[test]
+fn write_to_memory_and_output_flag() {
+    use crate::parse::{default_output, parse_args};
+
+    let mut canvas = Canvas::new("qoi", 4, 4).unwrap();
+    canvas.new_rect((0.0, 0.0), (100.0, 100.0), [0, 0, 0, 255]);
+    let mut bytes = Vec::new();
+    canvas.write(&mut bytes).unwrap();
+    assert!(bytes.starts_with(b"qoif"));
+
+    let cli = parse_args(["rasm", "card", "--output", "-"].map(String::from).into_iter()).unwrap();
+    assert!(cli.file.ends_with("card.manifest.toml"));
+    assert_eq!(cli.output, Some(Path::new("-").to_path_buf()));
+    let cli = parse_args(["rasm", "-o", "out/card.png", "card"].map(String::from).into_iter()).unwrap();
+    assert!(cli.output.unwrap().is_absolute());
+
+    assert_eq!(default_output(Path::new("a/Rasm.toml"), "jpg"), Path::new("a/Rasm.jpg"));
+    assert_eq!(default_output(Path::new("card.manifest.toml"), "png"), Path::new("card.png"));
+}
+
+#[test]
+fn manifest_from_text() {
+    use crate::parse::write_manifest;
+
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith");
+    let text = r#"
+format = "qoi"
+size = [16, 16]
+color = [255, 255, 255, 255]
+[[objects]]
+name = "image"
+src = "pic.jpg"
+origin = [0, 50]
+resize = [100, 50]
+"#;
+    let mut bytes = Vec::new();
+    write_manifest(text, &base_dir, &mut bytes).unwrap();
+    let (header, pixels) = qoi::decode_to_vec(&bytes).unwrap();
+    assert_eq!((header.width, header.height), (16, 16));
+    assert_eq!(&pixels[..3], &[255, 255, 255]);
+    assert_ne!(&pixels[pixels.len() - 3..], &[255, 255, 255]);
+
+    let err = |text: &str| write_manifest(text, &base_dir, &mut Vec::new()).unwrap_err().to_string();
+    assert_eq!(err(&text.replace("size = [16, 16]\n", "")), "no image size");
+    let outputs = text.replace("format = \"qoi\"\n", "outputs = [{ filename = \"a.qoi\" }, { filename = \"b.png\" }]\n");
+    assert_eq!(err(&outputs), "cannot write 2 outputs to a single writer");
+    assert!(err(&text.replace("pic.jpg", "missing.jpg")).contains("missing.jpg"));
+
+    // Included Manifests and data are read from base_dir, not the working directory.
+    let dir = std::env::temp_dir().join("rasm-manifest-text");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("base.toml"), "format = \"qoi\"\nsize = [2, 2]\n").unwrap();
+    std::fs::write(dir.join("rows.csv"), "color\n\"[0, 0, 255, 255]\"\n").unwrap();
+    let text = "include = [\"base.toml\"]\ncolor = \"{{color}}\"\nobjects = []\n[data]\nsrc = \"rows.csv\"\n";
+    let mut bytes = Vec::new();
+    write_manifest(text, &dir, &mut bytes).unwrap();
+    let (header, pixels) = qoi::decode_to_vec(&bytes).unwrap();
+    assert_eq!((header.width, header.height), (2, 2));
+    assert_eq!(&pixels[..3], &[0, 0, 255]);
+    std::fs::write(dir.join("rows.csv"), "color\nred\nblue\n").unwrap();
+    assert_eq!(write_manifest(text, &dir, &mut Vec::new()).unwrap_err().to_string(), "cannot write 2 rows of data to a single writer");
+}
+
+#[test]
+fn library_render_and_builder() {
+    use crate::parse::{read_manifest, render_in, Error};
+
+    let image = Canvas::builder()
+        .size(10, 10)
+        .color([255, 255, 255, 255])
+        .rect((0.0, 50.0), (100.0, 100.0), [0, 0, 255, 255])
+        .render()
+        .unwrap();
+    assert_eq!((image.width, image.height), (10, 10));
+    assert_eq!(image[0][0][0], Pixel::from(&[255, 255, 255, 255]));
+    assert_eq!(image[0][9][0], Pixel::from(&[0, 0, 255, 255]));
+    let err = |result: Result<Image, Error>| result.unwrap_err().to_string();
+    assert_eq!(err(Canvas::builder().render()), "no image size");
+    assert_eq!(err(Canvas::builder().format("heic").size(2, 2).render()), "unknown image format heic");
+    let text = Canvas::builder().size(2, 2).text((0.0, 0.0), (100.0, 100.0), "Hi", [0, 0, 0, 255]);
+    assert_eq!(text.err().unwrap().to_string(), "set a font before adding text");
+    let font = crate::asset::Font::new("notes.txt".into(), b"not a font".to_vec());
+    assert_eq!(font.err().unwrap().to_string(), "notes.txt is not a font");
+
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith");
+    let manifest = read_manifest(
+        r#"
+size = [20, 10]
+color = [255, 255, 255, 255]
+[[outputs]]
+filename = "card.pdf"
+[[objects]]
+name = "image"
+src = "pic.jpg"
+origin = [0, 0]
+resize = [100, 100]
+"#,
+    )
+    .unwrap();
+    let image = render_in(&manifest, &base_dir).unwrap();
+    assert_eq!((image.width, image.height), (20, 10));
+
+    let manifest = read_manifest("size = [20, 10]\ncolor = [0, 0, 0, 255]\n[[objects]]\nname = \"image\"\nsrc = \"missing.png\"\norigin = [0, 0]").unwrap();
+    assert!(matches!(render_in(&manifest, &base_dir), Err(Error::Io(..))));
+    let err = read_manifest("size = [20, 10]\ncolor = [0, 0, 0, 255]\n[[objects]]\nname = \"text\"").unwrap_err();
+    assert_eq!(err.to_string(), "objects[0]: missing field `font`");
+}
+
+#[test]
+fn command_line() {
+    use crate::parse::{parse_args, read_manifest_with, Command, Verbosity};
+
+    let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+    let cli = args(&["rasm", "check", "card.manifest.toml", "-q", "--set", "size=[540, 540]"]).unwrap();
+    assert_eq!(cli.command, Command::Check);
+    assert!(cli.file.ends_with("card.manifest.toml"));
+    assert_eq!(cli.verbosity, Verbosity::Quiet);
+    assert_eq!(cli.set, [("size".to_string(), "[540, 540]".to_string())]);
+    let cli = args(&["rasm", "card", "--format=webp", "-v"]).unwrap();
+    assert_eq!(cli.command, Command::Render);
+    assert_eq!(cli.format.as_deref(), Some("webp"));
+    assert_eq!(args(&["rasm", "-V"]).unwrap().command, Command::Version);
+    assert_eq!(args(&["rasm"]).unwrap_err(), "no manifest given");
+    assert_eq!(args(&["rasm", "a", "b"]).unwrap_err(), "unexpected argument b");
+    assert_eq!(args(&["rasm", "card", "--set", "size"]).unwrap_err(), "--set needs a KEY=VALUE, not size");
+
+    let text = "size = [10, 10]\ncolor = [0, 0, 0, 255]\n[[objects]]\nname = \"rect\"\ncolor = [1, 1, 1, 255]";
+    let set = [
+        ("format".to_string(), "webp".to_string()),
+        ("objects.0.color".to_string(), "[2, 2, 2, 255]".to_string()),
+        ("animation.frames".to_string(), "2".to_string()),
+        ("animation.fps".to_string(), "1.5".to_string()),
+    ];
+    let manifest = read_manifest_with(text, &set).unwrap();
+    assert_eq!(manifest.format.as_deref(), Some("webp"));
+    assert_eq!(color(&manifest.objects[0]), Some([2, 2, 2, 255]));
+    assert_eq!(manifest.animation.unwrap().frames, 2);
+    let set = [("objects.1.color".to_string(), "[2, 2, 2, 255]".to_string())];
+    assert!(read_manifest_with(text, &set).is_err());
+}
+
+#[test]
+fn asset_cache() {
+    use crate::asset::AssetCache;
+    use crate::parse::{manifest_files, read_manifest};
+    use std::time::{Duration, SystemTime};
+
+    let dir = std::env::temp_dir().join("rasm-asset-cache");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("pic.jpg");
+    std::fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith/pic.jpg"), &file).unwrap();
+
+    let mut cache = AssetCache::new();
+    let load = || Ok(Image::new(1, 1));
+    cache.image(&file, None, Some([4, 4]), load).unwrap();
+    cache.image(&file, None, Some([4, 4]), load).unwrap();
+    assert_eq!(cache.loads(), 1);
+    cache.image(&file, None, Some([8, 8]), load).unwrap();
+    assert_eq!(cache.loads(), 2);
+    let later = SystemTime::now() + Duration::from_secs(60);
+    File::options().write(true).open(&file).unwrap().set_modified(later).unwrap();
+    cache.image(&file, None, Some([4, 4]), load).unwrap();
+    assert_eq!(cache.loads(), 3);
+
+    let manifest = read_manifest(
+        "size = [1, 1]\ncolor = [0, 0, 0, 255]\nobjects = [{ name = \"image\", src = \"pic.jpg\" }, \
+         { name = \"text\", font = \"a.ttf\" }, { name = \"image\", src = \"pic.jpg\" }]",
+    )
+    .unwrap();
+    assert_eq!(manifest_files(&manifest, &dir), [dir.join("pic.jpg"), dir.join("a.ttf")]);
+}
+
+#[test]
+fn data_rows() {
+    use crate::parse::{parse_manifest, read_manifests, Cli};
+
+    let dir = std::env::temp_dir().join("rasm-data-rows");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("cards.csv"), "name,color\nfirst,\"[0, 150, 75, 255]\"\nsecond,\"[255, 0, 0, 255]\"\n").unwrap();
+    std::fs::write(dir.join("cards.json"), r#"[{"name": "third", "size": 4}]"#).unwrap();
+    let text = r#"
+size = [2, 2]
+color = [255, 255, 255, 255]
+output = "{{name}}.qoi"
+[data]
+src = "cards.csv"
+[[objects]]
+name = "rect"
+id = "Card {{name}}"
+color = "{{ color }}"
+origin = [0, 0]
+offset = [100, 100]
+"#;
+    let manifests = read_manifests(text, &[], None, &dir).unwrap();
+    assert_eq!(manifests.len(), 2);
+    assert_eq!(manifests[0].output.as_deref(), Some(Path::new("first.qoi")));
+    assert_eq!(color(&manifests[1].objects[0]), Some([255, 0, 0, 255]));
+    assert_eq!(manifests[1].objects[0].id.as_deref(), Some("Card second"));
+
+    let json = text.replace("[2, 2]", "[\"{{size}}\", \"{{size}}\"]").replace("\"{{ color }}\"", "[0, 0, 0, 255]");
+    let manifests = read_manifests(&json, &[], Some(&dir.join("cards.json")), &dir).unwrap();
+    assert_eq!(manifests.len(), 1);
+    assert_eq!(manifests[0].size, Some([4, 4]));
+    assert_eq!(manifests[0].objects[0].id.as_deref(), Some("Card third"));
+    let err = read_manifests(text, &[], Some(&dir.join("cards.json")), &dir).unwrap_err();
+    assert_eq!(err.to_string(), "no column color in data");
+
+    std::fs::write(dir.join("Rasm.toml"), text.replace("output = \"{{name}}.qoi\"\n", "format = \"qoi\"\n")).unwrap();
+    let _ = std::fs::remove_file(dir.join("Rasm-2.qoi"));
+    parse_manifest(&Cli { file: dir.join("Rasm.toml"), ..Default::default() }).unwrap();
+    assert!(dir.join("Rasm-1.qoi").exists() && dir.join("Rasm-2.qoi").exists());
+}
+
+#[test]
+fn colors_vars_and_styles() {
+    use crate::color::parse_color;
+    use crate::parse::read_manifest;
+
+    assert_eq!(parse_color("#00964b"), Ok([0, 150, 75, 255]));
+    assert_eq!(parse_color("#F008"), Ok([255, 0, 0, 136]));
+    assert_eq!(parse_color("RebeccaPurple"), Ok([102, 51, 153, 255]));
+    assert_eq!(parse_color("rgb(255 0 0 / 50%)"), Ok([255, 0, 0, 128]));
+    assert_eq!(parse_color("rgba(0, 100%, 0, 0.25)"), Ok([0, 255, 0, 64]));
+    assert_eq!(parse_color("hsl(120deg 100% 25%)"), Ok([0, 128, 0, 255]));
+    assert_eq!(parse_color("oklch(62.8% 0.2577 29.23)"), Ok([255, 0, 0, 255]));
+    assert_eq!(parse_color("oklch(1 0 0)"), Ok([255, 255, 255, 255]));
+    assert!(parse_color("#12345").is_err());
+    assert!(parse_color("bluish").is_err());
+
+    let manifest = read_manifest(
+        r##"
+size = [10, 10]
+color = "$paper"
+[vars]
+brand = "#00964b"
+paper = "$white"
+white = "white"
+band = [0, 40]
+[styles.accent]
+color = "$brand"
+origin = "$band"
+[[objects]]
+name = "rect"
+style = "accent"
+offset = [100, 60]
+[[objects]]
+name = "rect"
+style = "accent"
+color = "navy"
+offset = [100, 60]
+"##,
+    )
+    .unwrap();
+    assert_eq!(manifest.color, [255, 255, 255, 255]);
+    assert_eq!(color(&manifest.objects[0]), Some([0, 150, 75, 255]));
+    assert_eq!(manifest.objects[0].origin, Some((0.0.into(), 40.0.into())));
+    assert_eq!(color(&manifest.objects[1]), Some([0, 0, 128, 255]));
+    let err = read_manifest("size = [1, 1]\ncolor = \"#zz\"\nobjects = []").unwrap_err();
+    assert!(err.to_string().contains("invalid color #zz"));
+    let err = read_manifest("size = [1, 1]\ncolor = \"red\"\nobjects = [{ name = \"rect\", style = \"bold\" }]").unwrap_err();
+    assert_eq!(err.to_string(), "objects[0]: unknown style bold");
+    assert!(read_manifest("size = [1, 1]\ncolor = \"$a\"\nobjects = []\n[vars]\na = \"$a\"").is_err());
+}
+
+#[test]
+fn includes_and_components() {
+    use crate::parse::{manifest_files, read_manifests};
+
+    let dir = std::env::temp_dir().join("rasm-includes");
+    std::fs::create_dir_all(dir.join("brand")).unwrap();
+    std::fs::create_dir_all(dir.join("card")).unwrap();
+    std::fs::write(
+        dir.join("brand/base.toml"),
+        r##"
+size = [100, 100]
+color = "white"
+[vars]
+brand = "#00964b"
+[components.lockup]
+params = { color = "$brand" }
+objects = [
+    { name = "image", id = "logo", src = "logo.png", origin = [0, 0] },
+    { name = "rect", id = "bar", color = "$color", origin = [0, 90], offset = [100, 100] },
+]
+[[objects]]
+name = "rect"
+id = "band"
+color = "$brand"
+origin = [0, 40]
+offset = [100, 60]
+[[objects]]
+name = "lockup"
+id = "footer"
+"##,
+    )
+    .unwrap();
+    let text = r#"
+include = ["../brand/base.toml"]
+size = [50, 50]
+[[objects]]
+name = "rect"
+id = "band"
+color = "navy"
+[[objects]]
+name = "lockup"
+params = { color = "red" }
+"#;
+    let base_dir = dir.join("card");
+    let manifest = read_manifests(text, &[], None, &base_dir).unwrap().remove(0);
+    assert_eq!(manifest.size, Some([50, 50]));
+    assert_eq!(manifest.include.as_deref(), Some(&[Path::new("../brand/base.toml").to_path_buf()][..]));
+    let objects: Vec<_> = manifest.objects.iter().map(|object| (object.spec.name(), object.id.as_deref(), color(object))).collect();
+    assert_eq!(
+        objects,
+        [
+            ("rect", Some("band"), Some([0, 0, 128, 255])),
+            ("image", Some("footer.logo"), None),
+            ("rect", Some("footer.bar"), Some([0, 150, 75, 255])),
+            ("image", Some("logo"), None),
+            ("rect", Some("bar"), Some([255, 0, 0, 255])),
+        ]
+    );
+    assert_eq!(manifest.objects[0].origin, Some((0.0.into(), 40.0.into())));
+    let ObjectSpec::Image { src, .. } = &manifest.objects[1].spec else { panic!() };
+    assert_eq!(src, Path::new("../brand/logo.png"));
+    assert!(manifest_files(&manifest, &base_dir).contains(&base_dir.join("../brand/base.toml")));
+
+    std::fs::write(dir.join("card/loop.toml"), "include = [\"loop.toml\"]").unwrap();
+    let err = read_manifests("include = [\"loop.toml\"]", &[], None, &base_dir).unwrap_err();
+    assert!(err.to_string().ends_with("includes itself"));
+}
+
+#[test]
+fn units_and_anchors() {
+    use crate::parse::{read_manifest, render_in};
+    use crate::unit::{Length, Units};
+
+    let units = Units { viewport: [200.0, 100.0], dpi: 144.0, scale: 0.5, em: 8.0 };
+    let length = |text| Length::parse(text).unwrap().to_px(50.0, &units);
+    assert_eq!(length("10"), 5.0);
+    assert_eq!(length("10%"), 5.0);
+    assert_eq!(length("10px"), 5.0);
+    assert_eq!(length("10vw"), 20.0);
+    assert_eq!(length("10vh"), 10.0);
+    assert_eq!(length("10pt"), 10.0);
+    assert_eq!(length("1.5em"), 12.0);
+    assert!(Length::parse("10cm").is_err());
+
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith");
+    let manifest = read_manifest(
+        r#"
+size = [20, 10]
+color = "white"
+[[objects]]
+name = "rect"
+color = "blue"
+origin = [0, 0]
+offset = ["4px", "20%"]
+anchor = "center"
+parent_anchor = "center"
+[[objects]]
+name = "image"
+src = "pic.jpg"
+resize = ["5px", "5px"]
+right = "1px"
+bottom = 0
+"#,
+    )
+    .unwrap();
+    let image = render_in(&manifest, &base_dir).unwrap();
+    let blue = Pixel::from(&[0, 0, 255, 255]);
+    let white = Pixel::from(&[255, 255, 255, 255]);
+    assert_eq!((image[0][4][8], image[0][5][11]), (blue, blue));
+    assert_eq!((image[0][3][8], image[0][4][7], image[0][6][8], image[0][4][12]), (white, white, white, white));
+    assert_ne!(image[0][9][18], white);
+    assert_eq!((image[0][4][18], image[0][9][19], image[0][9][13]), (white, white, white));
+}
+
+#[test]
+fn layout_containers() {
+    use crate::layout::{Align, Direction, Flex, Justify};
+    use crate::parse::{read_manifest, render_in};
+    use crate::unit::Bounds;
+
+    let flex = Flex { gap: 5.0, padding: 5.0, wrap: true, ..Default::default() };
+    let sizes = [(10.0, 10.0), (20.0, 5.0), (10.0, 10.0)];
+    assert_eq!(flex.measure(&sizes, 100.0), (60.0, 20.0));
+    assert_eq!(flex.measure(&sizes, 50.0), (45.0, 35.0));
+    let arranged = flex.arrange(&sizes, &Bounds::new(0.0, 0.0, 50.0, 50.0));
+    assert_eq!(arranged[1], Bounds::new(20.0, 5.0, 20.0, 5.0));
+    assert_eq!(arranged[2], Bounds::new(5.0, 20.0, 10.0, 10.0));
+    let flex = Flex { direction: Direction::Column, justify: Justify::SpaceBetween, align: Align::End, ..flex };
+    let arranged = flex.arrange(&sizes[..2], &Bounds::new(0.0, 0.0, 30.0, 40.0));
+    assert_eq!(arranged, [Bounds::new(15.0, 5.0, 10.0, 10.0), Bounds::new(5.0, 30.0, 20.0, 5.0)]);
+
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith");
+    let manifest = read_manifest(
+        r#"
+size = [60, 30]
+color = "white"
+[[objects]]
+name = "layout"
+origin = [0, 0]
+offset = [100, 100]
+padding = "5px"
+gap = "5px"
+justify = "center"
+align = "center"
+children = [
+    { name = "rect", color = "blue", size = ["10px", "10px"] },
+    { name = "image", src = "pic.jpg", resize = ["10px", "10px"] },
+    { name = "rect", color = "red", size = ["10px", "4px"] },
+]
+"#,
+    )
+    .unwrap();
+    let image = render_in(&manifest, &base_dir).unwrap();
+    let white = Pixel::from(&[255, 255, 255, 255]);
+    assert_eq!(image[0][10][10], Pixel::from(&[0, 0, 255, 255]));
+    assert_eq!((image[0][10][9], image[0][9][10], image[0][20][10]), (white, white, white));
+    assert_ne!(image[0][10][25], white);
+    assert_eq!((image[0][13][40], image[0][16][49]), (Pixel::from(&[255, 0, 0, 255]), Pixel::from(&[255, 0, 0, 255])));
+    assert_eq!((image[0][12][40], image[0][17][40], image[0][15][50]), (white, white, white));
+}
+
+#[test]
+fn ids_stacking_and_masks() {
+    use crate::parse::{read_manifest, render_in};
+
+    let manifest = read_manifest(
+        r#"
+size = [20, 20]
+color = "white"
+[[objects]]
+name = "rect"
+id = "title"
+z = 1
+origin = [0, 0]
+size = ["10px", "5px"]
+color = "red"
+[[objects]]
+name = "rect"
+below = "title"
+origin = [0, "2px"]
+size = ["10px", "5px"]
+color = "blue"
+[[objects]]
+name = "rect"
+z = -1
+origin = [0, 0]
+size = ["20px", "5px"]
+color = "lime"
+[[objects]]
+name = "rect"
+id = "window"
+opacity = 0
+origin = ["12px", "12px"]
+size = ["4px", "4px"]
+color = "white"
+[[objects]]
+name = "rect"
+mask = "window"
+origin = ["10px", "10px"]
+size = ["10px", "10px"]
+color = "black"
+"#,
+    )
+    .unwrap();
+    let image = render_in(&manifest, Path::new(".")).unwrap();
+    let white = Pixel::from(&[255, 255, 255, 255]);
+    assert_eq!(image[0][2][5], Pixel::from(&[255, 0, 0, 255]));
+    assert_eq!(image[0][2][15], Pixel::from(&[0, 255, 0, 255]));
+    assert_eq!((image[0][6][5], image[0][7][5], image[0][11][5]), (white, Pixel::from(&[0, 0, 255, 255]), Pixel::from(&[0, 0, 255, 255])));
+    assert_eq!(image[0][12][5], white);
+    assert_eq!(image[0][13][13], Pixel::from(&[0, 0, 0, 255]));
+    assert_eq!((image[0][11][11], image[0][18][18]), (white, white));
+
+    let manifest = read_manifest(
+        r#"
+size = [20, 20]
+color = "white"
+[[objects]]
+name = "rect"
+below = "title"
+size = [10, 10]
+color = "red"
+[[objects]]
+name = "rect"
+id = "title"
+size = [10, 10]
+color = "red"
+"#,
+    )
+    .unwrap();
+    let err = render_in(&manifest, Path::new(".")).unwrap_err();
+    assert_eq!(err.to_string(), "objects[0]: below title is not placed before");
+}
+
+#[test]
+fn sub_pixel_placement() {
+    let mut dot = Image::new(1, 1);
+    dot[0][0][0] = Pixel::from(&[255, 0, 0, 255]);
+    let image = Canvas::builder()
+        .size(4, 2)
+        .color([255, 255, 255, 255])
+        .rect((12.5, 0.0), (62.5, 50.0), [0, 0, 0, 255])
+        .image((12.5, 50.0), dot)
+        .render()
+        .unwrap();
+    let (white, black) = (Pixel::from(&[255, 255, 255, 255]), Pixel::from(&[0, 0, 0, 255]));
+    let half = Pixel::from(&[127, 127, 127, 255]);
+    assert_eq!([image[0][0][0], image[0][0][1], image[0][0][2], image[0][0][3]], [half, black, half, white]);
+    let (left, right) = (image[0][1][0], image[0][1][1]);
+    assert_eq!((left[0], right[0]), (255, 255));
+    assert!(left[1] < 255 && left[1] > 0 && right[1] < 255 && right[1] > 0);
+    assert_eq!(image[0][1][2], white);
+}
+
+#[test]
+fn clipping_to_the_canvas() {
+    use crate::parse::{read_manifest, render_in};
+
+    let mut dot = Image::new(3, 3);
+    dot[0].fill(Pixel::from(&[255, 0, 0, 255]));
+    let image = Canvas::builder()
+        .size(4, 4)
+        .color([255, 255, 255, 255])
+        .image((-25.0, -25.0), dot.clone())
+        .image((75.0, 50.0), dot)
+        .rect((-50.0, 75.0), (50.0, 150.0), [0, 0, 255, 255])
+        .render()
+        .unwrap();
+    let (white, red, blue) = (Pixel::from(&[255, 255, 255, 255]), Pixel::from(&[255, 0, 0, 255]), Pixel::from(&[0, 0, 255, 255]));
+    assert_eq!((image[0][0][0], image[0][1][1], image[0][2][2], image[0][2][0]), (red, red, white, white));
+    assert_eq!((image[0][2][3], image[0][3][3], image[0][2][2]), (red, red, white));
+    assert_eq!((image[0][3][0], image[0][3][1], image[0][3][2]), (blue, blue, white));
+
+    let base_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith");
+    let manifest = read_manifest(
+        r#"
+size = [20, 10]
+color = "white"
+[[objects]]
+name = "image"
+src = "pic.jpg"
+origin = [50, -20]
+resize = [100, 0]
+"#,
+    )
+    .unwrap();
+    let image = render_in(&manifest, &base_dir).unwrap();
+    assert_eq!(image[0][9][0], white);
+    assert_ne!(image[0][0][19], white);
+}
+
+#[test]
+fn check_and_schema() {
+    use crate::parse::{check_manifest, manifest_schema, read_manifest, render_in, Cli};
+
+    let hadith = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/hadith/Rasm.toml");
+    let warnings = check_manifest(&Cli { file: hadith.clone(), ..Default::default() }).unwrap();
+    assert_eq!(warnings, ["asset logo (logo.svg) is not used"]);
+
+    let text = r#"
+size = [20, 10]
+color = "white"
+animation = { frames = 4, fps = 2 }
+[[objects]]
+name = "rect"
+size = [10, 10]
+color = "red"
+opacity = 0.5
+keyframes = [{ frame = 0, scale = 1 }, { frame = 3, scale = 2 }]
+"#;
+    let dir = std::env::temp_dir().join("rasm-check");
+    std::fs::create_dir_all(&dir).unwrap();
+    render_in(&read_manifest(text).unwrap(), &dir).unwrap();
+    let err = |text: String| render_in(&read_manifest(&text).unwrap(), &dir).unwrap_err().to_string();
+    assert_eq!(err(text.replace("0.5", "1.5")), "objects[0]: opacity 1.5 is not between 0 and 1");
+    assert_eq!(err(text.replace("frame = 3", "frame = 4")), "objects[0].keyframes[1]: frame 4 is not before the frame count 4");
+    assert_eq!(err(text.replace("size = [10, 10]", "size = [\"-1px\", 10]")), "objects[0]: size is negative");
+    assert_eq!(err(text.replace("fps = 2", "fps = 0")), "animation fps 0 is not positive");
+
+    std::fs::write(dir.join("bad.png"), b"\x89PNG\r\n\x1a\n not really").unwrap();
+    std::fs::write(dir.join("Rasm.toml"), "size = [2, 2]\ncolor = \"white\"\nobjects = [{ name = \"image\", src = \"bad.png\" }]").unwrap();
+    let err = check_manifest(&Cli { file: dir.join("Rasm.toml"), ..Default::default() }).unwrap_err();
+    assert!(err.to_string().starts_with(&format!("{}: ", dir.join("bad.png").display())), "{err}");
+
+    let text = "size = [2, 2]\ncolor = \"white\"\nassets = [{ id = \"back\", src = \"bad.png\" }]\n\
+                objects = [{ name = \"rect\", origin = [0, 0], offset = [1, 1], color = \"red\", asset = \"back\" }]";
+    let err = |text: &str| render_in(&read_manifest(text).unwrap(), &dir).map(|_| ()).map_err(|err| err.to_string());
+    assert!(err(text).unwrap_err().starts_with(&dir.join("bad.png").display().to_string()));
+    assert_eq!(err(&text.replace("\"back\" }]", "\"logo\" }]")), Err("no asset logo".to_string()));
+    assert_eq!(err(&text.replace(", asset = \"back\"", "")), Ok(()));
+
+    Png::new(4, 2).write(&mut File::create(dir.join("good.png")).unwrap()).unwrap();
+    let text = "size = [2, 2]\ncolor = \"white\"\nobjects = [{ name = \"image\", src = \"good.png\", resize = [1, 0] }]";
+    assert_eq!(err(&text.replace("[1, 0]", "[0, 0]")), Err("objects[0]: resize is zero".to_string()));
+    assert_eq!(err(&text.replace("1, 0", "\"0.25px\", 0")), Err("objects[0]: resize 0.25px is smaller than a pixel".to_string()));
+    let message = format!("{}: cannot resize a 4x2 image to 0x0 pixels", dir.join("good.png").display());
+    assert_eq!(err(text), Err(message));
+
+    let schema: serde_json::Value = serde_json::from_str(&manifest_schema()).unwrap();
+    assert_eq!(schema["required"], serde_json::json!(["color", "objects"]));
+    assert!(schema["$defs"]["ObjectInfo"]["properties"]["origin"].is_object());
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    let example: toml::Value = toml::from_str(&std::fs::read_to_string(hadith).unwrap()).unwrap();
+    let mut example = serde_json::to_value(example).unwrap();
+    let errors: Vec<_> = validator.iter_errors(&example).map(|err| err.to_string()).collect();
+    assert!(errors.is_empty(), "{errors:?}");
+    example["objects"][1]["orgin"] = serde_json::json!([0, 0]);
+    assert!(!validator.is_valid(&example));
+    example["objects"][1] = serde_json::json!({ "name": "logos", "id": "lockup", "origin": [0, 0] });
+    assert!(!validator.is_valid(&example));
+    example["objects"][1] = serde_json::json!({ "name": "logos", "id": "lockup", "params": { "src": "a.png" } });
+    assert!(validator.is_valid(&example));
+}
+
+#[test]
+fn object_specs() {
+    use crate::parse::read_manifest;
+
+    let text = r#"
+size = [20, 10]
+color = "white"
+[[objects]]
+name = "layout"
+size = [20, 10]
+wrap = true
+children = [{ name = "rect", size = [5, 5], color = "red", opacity = 0.5 }]
+"#;
+    let manifest = read_manifest(text).unwrap();
+    let ObjectSpec::Layout { children, wrap, color, .. } = &manifest.objects[0].spec else { panic!() };
+    assert!(*wrap && color.is_none());
+    assert_eq!((children[0].spec.name(), children[0].opacity), ("rect", Some(0.5)));
+
+    let err = |text: String| read_manifest(&text).unwrap_err().to_string();
+    // Unknown fields are reported with the fields common to all Objects as well as those of their kind.
+    let rect = "`above`, `anchor`, `asset`, `below`, `bottom`, `color`, `id`, `keyframes`, `left_of`, `mask`, `offset`, `opacity`, \
+        `origin`, `parent_anchor`, `right`, `right_of`, `rotation`, `scale`, `size`, `style`, `z`";
+    assert_eq!(err(text.replace("size = [5, 5]", "size = [5, 5], orgin = [0, 0]")), format!("objects[0].children[0]: unknown field `orgin`, expected one of {rect}"));
+    let layout = "`above`, `align`, `anchor`, `asset`, `below`, `bottom`, `children`, `color`, `direction`, `gap`, `id`, `justify`, \
+        `keyframes`, `left_of`, `mask`, `offset`, `opacity`, `origin`, `padding`, `parent_anchor`, `right`, `right_of`, `rotation`, \
+        `scale`, `size`, `style`, `wrap`, `z`";
+    assert_eq!(err(text.replace("wrap", "font")), format!("objects[0]: unknown field `font`, expected one of {layout}"));
+    assert_eq!(err(text.replace("color = \"red\", ", "")), "objects[0].children[0]: missing field `color`");
+    assert_eq!(err(text.replace("\"rect\"", "\"circle\"")), "objects[0].children[0]: unknown variant `circle`, expected one of `rect`, `image`, `text`, `layout`");
+    assert!(err(text.replace("[20, 10]\nwrap", "[20, 10]\nkeyframes = [{ frame = 0, scael = 2 }]\nwrap")).contains("unknown field `scael`"));
+}
+
+#[test]
+fn json_and_yaml_manifests() {
+    use crate::parse::{default_output, parse_args, read_manifest, render, Syntax};
+
+    let toml = "size = [4, 2]\ncolor = \"white\"\n[[objects]]\nname = \"rect\"\nsize = [50, 100]\ncolor = \"red\"\n";
+    let json = r#"{ "size": [4, 2], "color": "white", "objects": [{ "name": "rect", "size": [50, 100], "color": "red" }] }"#;
+    let yaml = "# card\nsize: [4, 2]\ncolor: white\nobjects:\n  - name: rect\n    size: [50, 100]\n    color: red\n";
+    assert_eq!([toml, json, yaml].map(Syntax::sniff), [Syntax::Toml, Syntax::Json, Syntax::Yaml]);
+    for text in [toml, json, yaml] {
+        let image = render(&read_manifest(text).unwrap()).unwrap();
+        assert_eq!((image[0][1][1], image[0][1][2]), (Pixel::from(&[255, 0, 0, 255]), Pixel::from(&[255, 255, 255, 255])));
+        let typo = text.replace("size = [50", "sise = [50").replace("size\": [50", "sise\": [50").replace("  size:", "  sise:");
+        let err = read_manifest(&typo).unwrap_err();
+        let fields = "`above`, `anchor`, `asset`, `below`, `bottom`, `color`, `id`, `keyframes`, `left_of`, `mask`, `offset`, \
+            `opacity`, `origin`, `parent_anchor`, `right`, `right_of`, `rotation`, `scale`, `size`, `style`, `z`";
+        assert_eq!(err.to_string(), format!("objects[0]: unknown field `sise`, expected one of {fields}"), "{text}");
+    }
+    assert!(read_manifest("size: [4, 2").unwrap_err().to_string().starts_with("invalid manifest: "));
+
+    let dir = std::env::temp_dir().join("rasm-syntax");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Rasm.yaml"), yaml).unwrap();
+    let cli = parse_args(["rasm".to_string(), dir.to_string_lossy().into_owned()].into_iter()).unwrap();
+    assert_eq!(cli.file, dir.join("Rasm.yaml"));
+    assert_eq!(Syntax::from_path(&cli.file), Some(Syntax::Yaml));
+    assert_eq!(default_output(&dir.join("card.manifest.json"), "png"), dir.join("card.png"));
+}
+
+#[test]
+fn invalid_outputs() {
+    use crate::asset::AssetCache;
+    use crate::parse::{parse_manifest, read_manifest, write_output, Cli, Error, FormatInfo};
+
+    let err = |text: &str| {
+        let manifest = read_manifest(text).unwrap();
+        let outputs = parse_outputs(&manifest)?;
+        let format = parse_format(&outputs[0])?;
+        write_output(&manifest, format, Path::new("."), &mut AssetCache::new(), &mut Vec::new())
+    };
+    let text = "size = [2, 2]\ncolor = \"white\"\nformat = \"png\"\nobjects = [{ name = \"rect\", color = \"red\" }]\n";
+    assert!(err(text).is_ok());
+    for (text, message) in [
+        (text.replace("png", "heic"), "unknown image format heic"),
+        (text.replace("size = [2, 2]\n", ""), "no image size"),
+        (format!("{text}[png]\ncolor_type = \"cmyk\"\n"), "unknown png color type cmyk"),
+        (format!("{text}[webp]\nnear_lossless = 101\n").replace("png", "webp"), "webp near_lossless 101 is not between 0 and 100"),
+        (format!("{text}[webp]\nquality = 101\n").replace("png", "webp"), "webp quality 101 is not between 0 and 100"),
+        (format!("{text}[webp]\nquality = 80\nnear_lossless = 60\n").replace("png", "webp"), "webp quality is lossy and near_lossless is lossless, set only one"),
+        (format!("{text}[animation]\nframes = 2\nfps = 1\n").replace("png", "jpg"), "jpg images cannot be animated"),
+        (text.replace("\"red\"", "\"red\", below = \"title\""), "no object title placed before"),
+        (format!("output = \"out.webp\"\n{text}"), "out.webp is not a png image"),
+    ] {
+        assert!(matches!(err(&text), Err(Error::Invalid(err)) if err == message), "{message}");
+    }
+
+    // The extension of -o wins over the format of the Manifest, but not over --format.
+    let dir = std::env::temp_dir().join("rasm-output-format");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("Rasm.toml"), text.replace("\"red\"", "\"red\", origin = [0, 0], offset = [100, 100]")).unwrap();
+    let cli = Cli { file: dir.join("Rasm.toml"), output: Some(dir.join("out.webp")), ..Default::default() };
+    parse_manifest(&cli).unwrap();
+    assert!(std::fs::read(dir.join("out.webp")).unwrap().starts_with(b"RIFF"));
+    let err = parse_manifest(&Cli { format: Some("png".to_string()), ..cli }).unwrap_err();
+    assert_eq!(err.to_string(), format!("{} is not a png image", dir.join("out.webp").display()));
+
+    for (name, max) in [("gif", 65535), ("tga", 65535), ("jpg", 65535), ("webp", 16384)] {
+        let mut format = Formats::default().get(name, max + 1, 1, &FormatInfo::default()).unwrap();
+        let err = format.write(&mut Vec::new()).unwrap_err().to_string();
+        assert_eq!(err, format!("{name} images are at most {max} pixels wide and high, not {}x1", max + 1));
+    }
+
+    // A closed pipe is an error of the writer, not a panic of the encoder.
+    struct Closed;
+    impl std::io::Write for Closed {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let manifest = read_manifest(text).unwrap();
+    for name in ["png", "jpg", "webp", "qoi", "bmp", "tga", "ppm", "pam", "tiff", "gif", "svg", "pdf"] {
+        let format = Formats::default().get(name, 2, 2, &FormatInfo::default()).unwrap();
+        let result = write_output(&manifest, format, Path::new("."), &mut AssetCache::new(), &mut Closed);
+        assert!(matches!(result, Err(Error::Write(err)) if err.kind() == std::io::ErrorKind::BrokenPipe), "{name}");
+    }
+}
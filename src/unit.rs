@@ -0,0 +1,228 @@
+//! This module defines the units and anchors Objects are placed with.
+use std::borrow::Cow;
+use std::fmt;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A length with its unit.
+pub enum Length {
+    /// Percentage of the width or height of the parent, along the axis of the length.
+    /// Numbers without a unit are percentages.
+    Percent(f64),
+    /// Pixels of the Manifest, scaled with the Image.
+    Px(f64),
+    /// Percentage of the width of the Image.
+    Vw(f64),
+    /// Percentage of the height of the Image.
+    Vh(f64),
+    /// Points, 72 to an inch at the resolution of the Manifest.
+    Pt(f64),
+    /// Multiple of the font size.
+    Em(f64),
+}
+impl Length {
+    /// Parses a number followed by one of the units `%`, `px`, `vw`, `vh`, `pt` or `em`.
+    pub fn parse(text: &str) -> Result<Length, String> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+            .unwrap_or(text.len());
+        let (value, unit) = text.split_at(split);
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("invalid length {text}"))?;
+        match unit {
+            "" | "%" => Ok(Length::Percent(value)),
+            "px" => Ok(Length::Px(value)),
+            "vw" => Ok(Length::Vw(value)),
+            "vh" => Ok(Length::Vh(value)),
+            "pt" => Ok(Length::Pt(value)),
+            "em" => Ok(Length::Em(value)),
+            _ => Err(format!("unknown unit {unit} of length {text}")),
+        }
+    }
+    /// Converts the Length to pixels, given the size of the parent along its axis.
+    pub fn to_px(&self, parent: f64, units: &Units) -> f64 {
+        match *self {
+            Length::Percent(value) => parent * value / 100.0,
+            Length::Px(value) => value * units.scale,
+            Length::Vw(value) => units.viewport[0] * value / 100.0,
+            Length::Vh(value) => units.viewport[1] * value / 100.0,
+            Length::Pt(value) => value * units.dpi / 72.0 * units.scale,
+            Length::Em(value) => value * units.em,
+        }
+    }
+    /// Returns the number of the Length, whatever its unit.
+    pub fn value(&self) -> f64 {
+        match *self {
+            Length::Percent(value)
+            | Length::Px(value)
+            | Length::Vw(value)
+            | Length::Vh(value)
+            | Length::Pt(value)
+            | Length::Em(value) => value,
+        }
+    }
+    /// Returns true if the Length is zero, whatever its unit.
+    pub fn is_zero(&self) -> bool {
+        self.value() == 0.0
+    }
+}
+impl From<f64> for Length {
+    /// Creates a percentage.
+    fn from(value: f64) -> Self {
+        Length::Percent(value)
+    }
+}
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LengthVisitor;
+        impl Visitor<'_> for LengthVisitor {
+            type Value = Length;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a percentage or a length with a unit, as in \"12px\"")
+            }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Length, E> {
+                Ok(Length::Percent(value as f64))
+            }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Length, E> {
+                Ok(Length::Percent(value as f64))
+            }
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Length, E> {
+                Ok(Length::Percent(value))
+            }
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Length, E> {
+                Length::parse(text).map_err(E::custom)
+            }
+        }
+        deserializer.deserialize_any(LengthVisitor)
+    }
+}
+impl JsonSchema for Length {
+    fn schema_name() -> Cow<'static, str> {
+        "Length".into()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A percentage, or a number followed by one of the units %, px, vw, vh, pt or em.",
+            "anyOf": [
+                { "type": "number" },
+                { "type": "string", "pattern": "^\\s*[-+]?[0-9.]+(e[-+]?[0-9]+)?\\s*(%|px|vw|vh|pt|em)?\\s*$" }
+            ]
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// What Lengths are relative to, besides their parent.
+pub struct Units {
+    /// Width and height of the Image in pixels.
+    pub viewport: [f64; 2],
+    /// Resolution of the Manifest in dots per inch.
+    pub dpi: f64,
+    /// Pixels of the Image per pixel of the Manifest.
+    pub scale: f64,
+    /// Font size in pixels.
+    pub em: f64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+/// One of the nine points of the corners, edges and centre of a box.
+pub enum Anchor {
+    /// Top left corner.
+    #[default]
+    TopLeft,
+    /// Middle of the top edge.
+    Top,
+    /// Top right corner.
+    TopRight,
+    /// Middle of the left edge.
+    Left,
+    /// Centre.
+    Center,
+    /// Middle of the right edge.
+    Right,
+    /// Bottom left corner.
+    BottomLeft,
+    /// Middle of the bottom edge.
+    Bottom,
+    /// Bottom right corner.
+    BottomRight,
+}
+impl Anchor {
+    /// Returns the fractions of the width and height of a box at which the point lies.
+    pub fn fractions(&self) -> (f64, f64) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// A box in pixels.
+pub struct Bounds {
+    /// Column of the left edge.
+    pub x: f64,
+    /// Row of the top edge.
+    pub y: f64,
+    /// Width of the box.
+    pub width: f64,
+    /// Height of the box.
+    pub height: f64,
+}
+impl Bounds {
+    /// Creates a box from its top left corner and size.
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Bounds {
+        Bounds { x, y, width, height }
+    }
+    /// Returns the point of the box at anchor.
+    pub fn at(&self, anchor: Anchor) -> (f64, f64) {
+        let (fx, fy) = anchor.fractions();
+        (self.x + self.width * fx, self.y + self.height * fy)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+/// How a box is placed in its parent.
+pub struct Placement {
+    /// Distance from the parent_anchor of the parent to the anchor of the box, in pixels.
+    pub origin: (f64, f64),
+    /// Point of the box placed at origin.
+    pub anchor: Anchor,
+    /// Point of the parent origin is measured from.
+    pub parent_anchor: Anchor,
+    /// Distance from the right edge of the parent to the right edge of the box, replacing the column of origin.
+    pub right: Option<f64>,
+    /// Distance from the bottom edge of the parent to the bottom edge of the box, replacing the row of origin.
+    pub bottom: Option<f64>,
+}
+impl Placement {
+    /// Returns the Bounds of a box of size placed in parent.
+    pub fn place(&self, size: (f64, f64), parent: &Bounds) -> Bounds {
+        let (px, py) = parent.at(self.parent_anchor);
+        let (fx, fy) = self.anchor.fractions();
+        let x = match self.right {
+            Some(right) => parent.x + parent.width - right - size.0,
+            None => px + self.origin.0 - size.0 * fx,
+        };
+        let y = match self.bottom {
+            Some(bottom) => parent.y + parent.height - bottom - size.1,
+            None => py + self.origin.1 - size.1 * fy,
+        };
+        Bounds::new(x, y, size.0, size.1)
+    }
+}
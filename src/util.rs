@@ -1,13 +1,20 @@
 //! This module defines useful utilities used by the generator.
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use file_format::{FileFormat, Kind};
-use std::path::PathBuf;
+use std::fs::{read, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 //use crate::image::*;
+use crate::animation::*;
+use crate::asset::{AssetCache, Font};
+use crate::decoder::*;
 use crate::format::*;
 use crate::image::*;
+use crate::layout::*;
 use crate::object::*;
 use crate::parse::*;
+use crate::unit::*;
 
 #[allow(dead_code)]
 //#[derive(Clone, Debug)]
@@ -17,30 +24,98 @@ pub struct Canvas {
     pub width: usize,
     /// Height of Canvas.
     pub height: usize,
+    /// Frame of the animation being drawn.
+    pub frame: usize,
+    /// Resolution in dots per inch, converting points to pixels.
+    pub dpi: f64,
+    /// Pixels of the Canvas per pixel of the Manifest.
+    pub scale: f64,
+    /// Stacking order of the Objects being added. Objects of higher z are drawn
+    /// over those of lower z, and Objects of equal z in the order they are added.
+    pub z: i32,
+    /// Objects of the Manifest placed so far, by id.
+    pub placed: HashMap<String, Placed>,
     format: Box<dyn Format>,
-    shapes: VecDeque<Box<dyn Object>>,
+    shapes: VecDeque<(i32, Box<dyn Object>)>,
 }
 impl Canvas {
-    /// Creates a new Canvas.
-    pub fn new<T>(fmt: T, width: usize, height: usize) -> Canvas
+    /// Creates a new Canvas of the [Format] named fmt, failing if it is unknown.
+    pub fn new<T>(fmt: T, width: usize, height: usize) -> Result<Canvas, Error>
     where
         T: AsRef<str>,
     {
-        let img: Box<dyn Format>;
-        if fmt.as_ref() == "png" {
-            img = Box::new(Png::new(width, height))
-        } else if fmt.as_ref() == "jpg" {
-            img = Box::new(Jpg::new(width, height))
-        } else {
-            panic!("unknown image format")
-        }
-        Canvas {
+        let img = Formats::default().get(fmt.as_ref(), width, height, &FormatInfo::default())?;
+        Ok(Canvas {
             width,
             height,
+            frame: 0,
+            dpi: 72.0,
+            scale: 1.0,
+            z: 0,
+            placed: HashMap::new(),
             format: img,
             shapes: VecDeque::new(),
+        })
+    }
+    /// Creates a [CanvasBuilder] for a Png Canvas.
+    pub fn builder() -> CanvasBuilder {
+        CanvasBuilder::new()
+    }
+    /// Creates a new Canvas drawing on the [Image] of an output [Format].
+    pub fn with_format(mut format: Box<dyn Format>) -> Canvas {
+        let image = format.image();
+        Canvas {
+            width: image.width(),
+            height: image.height(),
+            frame: 0,
+            dpi: 72.0,
+            scale: 1.0,
+            z: 0,
+            placed: HashMap::new(),
+            format,
+            shapes: VecDeque::new(),
         }
     }
+    /// Converts coordinates represented as percentages of width and height of Canvas to a [Point],
+    /// keeping fractions of pixels.
+    pub fn point(&self, coordinates: (f64, f64)) -> Point {
+        let x = self.width as f64 * (coordinates.0 / 100.0);
+        let y = self.height as f64 * (coordinates.1 / 100.0);
+        Point::from((x, y))
+    }
+    /// Returns the [Bounds] of the whole Canvas.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::new(0.0, 0.0, self.width as f64, self.height as f64)
+    }
+    /// Returns the [Units] of lengths drawn on the Canvas with a font size of em pixels.
+    pub fn units(&self, em: f64) -> Units {
+        Units {
+            viewport: [self.width as f64, self.height as f64],
+            dpi: self.dpi,
+            scale: self.scale,
+            em,
+        }
+    }
+    /// Returns the default font size in pixels, 5 percent of height of Canvas.
+    pub fn default_font_size(&self) -> f64 {
+        self.height as f64 * 0.05
+    }
+    /// Adds an [Object] to the Canvas at its current z.
+    pub fn push(&mut self, object: Box<dyn Object>) -> &mut Box<dyn Object> {
+        self.shapes.push_back((self.z, object));
+        let idx = self.shapes.len();
+        &mut self.shapes[idx - 1].1
+    }
+    /// Removes the last [Object] added to the Canvas.
+    pub fn pop(&mut self) -> Option<Box<dyn Object>> {
+        self.shapes.pop_back().map(|(_, object)| object)
+    }
+    /// Removes the Objects added from index start on, in the order they are drawn.
+    fn split_off(&mut self, start: usize) -> Vec<Box<dyn Object>> {
+        let mut shapes: Vec<_> = self.shapes.split_off(start).into();
+        shapes.sort_by_key(|(z, _)| *z);
+        shapes.into_iter().map(|(_, object)| object).collect()
+    }
     /// Creates a new [Rectangle][R] in Canvas.
     ///
     /// [R]: Rect
@@ -50,18 +125,13 @@ impl Canvas {
         c: (f64, f64),
         color: [u8; 4],
     ) -> &mut Box<dyn Object> {
-        let ax = (self.width as f64 * (a.0 / 100.0)) as usize;
-        let ay = (self.height as f64 * (a.1 / 100.0)) as usize;
+        let a = self.point(a);
+        let c = self.point(c);
 
-        let cx = (self.width as f64 * (c.0 / 100.0)) as usize;
-        let cy = (self.height as f64 * (c.1 / 100.0)) as usize;
-
-        let mut rect = Rect::with_coordinates((ax, ay), (cx, cy));
+        let mut rect = Rect::with_corners((a.x, a.y), (c.x, c.y));
         rect.set_color(Pixel::from(&color));
         //rect.paste(self.fmt().image());
-        self.shapes.push_back(Box::new(rect));
-        let idx = self.shapes.len();
-        &mut self.shapes[idx - 1]
+        self.push(Box::new(rect))
     }
     /// Adds an [Image] to the Canvas.
     pub fn add_image(
@@ -69,65 +139,857 @@ impl Canvas {
         origin: (f64, f64),
         mut image: Image,
     ) -> &mut Box<dyn Object> {
-        let ox = (self.width as f64 * (origin.0 / 100.0)) as usize;
-        let oy = (self.height as f64 * (origin.1 / 100.0)) as usize;
-        image.origin = Point::from((ox, oy));
-        self.shapes.push_back(Box::new(image));
-        let idx = self.shapes.len();
-        &mut self.shapes[idx - 1]
+        image.origin = self.point(origin);
+        self.push(Box::new(image))
+    }
+    /// Creates a new [TextBox] in Canvas.
+    ///
+    /// The font size is a percentage of height of Canvas.
+    pub fn new_text(
+        &mut self,
+        a: (f64, f64),
+        c: (f64, f64),
+        content: &str,
+        font: Font,
+        size: f64,
+        color: [u8; 4],
+    ) -> &mut Box<dyn Object> {
+        let a = self.point(a);
+        let c = self.point(c);
+
+        let extent = ((c.x - a.x).round().max(0.0) as usize, (c.y - a.y).round().max(0.0) as usize);
+        let mut text = TextBox::new(content.to_string(), (0, 0), extent);
+        text.origin = Point::from((a.x.round(), a.y.round()));
+        text.set_font(font, (self.height as f64 * size / 100.0) as f32);
+        text.set_color(Pixel::from(&color));
+        self.push(Box::new(text))
     }
     /// Retrieve the Output format for Canvas.
     pub fn fmt(&mut self) -> &mut Box<dyn Format> {
         &mut self.format
     }
-    /// Saves the Canvas as format file in disk.
-    pub fn save(&mut self, filename: &str) {
-        for _i in 0..self.shapes.len() {
-            let mut obj = self.shapes.pop_front().unwrap();
+    /// Draws the Objects of the current frame on the [Image] of the format, in order of z.
+    pub fn render(&mut self) {
+        let mut shapes = std::mem::take(&mut self.shapes);
+        // The sort is stable, keeping Objects of equal z in the order they were added.
+        shapes.make_contiguous().sort_by_key(|(z, _)| *z);
+        for (_, obj) in shapes.iter_mut() {
             obj.draw(self)
         }
-        self.format.write(filename)
+        self.shapes = shapes;
+    }
+    /// Writes the Canvas encoded in its format to w.
+    pub fn write(&mut self, w: &mut dyn Write) -> Result<(), Error> {
+        self.render();
+        self.format.write(w)
+    }
+    /// Writes every frame of the Canvas encoded in its animated format to w.
+    pub fn write_animation(&mut self, w: &mut dyn Write, frames: usize, fps: f64, plays: u32) -> Result<(), Error> {
+        let Some(animation) = self.format.animation() else {
+            return invalid(format!("{} images cannot be animated", self.format.extension()));
+        };
+        animation.set_timing(fps, plays);
+        for frame in 0..frames {
+            self.frame = frame;
+            *self.format.image() = Image::new(self.width, self.height);
+            self.render();
+            if let Some(animation) = self.format.animation() {
+                animation.push_frame();
+            }
+        }
+        self.format.write(w)
+    }
+    /// Saves the Canvas as format file in disk.
+    pub fn save(&mut self, filename: &str) -> Result<(), Error> {
+        self.save_with(filename, |canvas, w| canvas.write(w))
+    }
+    /// Saves every frame of the Canvas as an animated format file in disk.
+    pub fn save_animation(&mut self, filename: &str, frames: usize, fps: f64, plays: u32) -> Result<(), Error> {
+        self.save_with(filename, |canvas, w| canvas.write_animation(w, frames, fps, plays))
+    }
+    /// Creates the file and writes the Canvas to it, reporting io errors with the filename.
+    fn save_with(
+        &mut self,
+        filename: &str,
+        write: impl FnOnce(&mut Canvas, &mut dyn Write) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let io_error = |err| Error::Io(PathBuf::from(filename), err);
+        let mut w = BufWriter::new(File::create(filename).map_err(io_error)?);
+        match write(self, &mut w) {
+            Err(Error::Write(err)) => return Err(io_error(err)),
+            result => result?,
+        }
+        w.flush().map_err(io_error)
+    }
+}
+
+/// Builder of a [Canvas], drawing Objects in the order they are added.
+///
+/// Coordinates are percentages of width and height of the Canvas, as in a Manifest.
+///
+/// ```no_run
+/// use rasm::asset::Font;
+/// use rasm::Canvas;
+///
+/// let image = Canvas::builder()
+///     .size(1080, 1080)
+///     .color([255, 255, 255, 255])
+///     .rect((0.0, 40.0), (100.0, 60.0), [20, 60, 120, 255])
+///     .font(Font::load("DejaVuSans.ttf".into())?, 8.0)
+///     .text((5.0, 42.0), (95.0, 58.0), "Hello", [255, 255, 255, 255])?
+///     .render()?;
+/// # Ok::<(), rasm::Error>(())
+/// ```
+pub struct CanvasBuilder {
+    format: String,
+    size: Option<(usize, usize)>,
+    color: Option<[u8; 4]>,
+    font: Option<(Font, f64)>,
+    objects: Vec<AddObject>,
+}
+/// Adds an Object to a [Canvas] built by a [CanvasBuilder].
+type AddObject = Box<dyn FnOnce(&mut Canvas)>;
+impl Default for CanvasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl CanvasBuilder {
+    /// Creates a builder of a Png Canvas without Objects.
+    pub fn new() -> CanvasBuilder {
+        CanvasBuilder {
+            format: "png".to_string(),
+            size: None,
+            color: None,
+            font: None,
+            objects: Vec::new(),
+        }
+    }
+    /// Sets the name of the [Format] of the Canvas.
+    pub fn format(mut self, name: &str) -> Self {
+        self.format = name.to_string();
+        self
+    }
+    /// Sets the width and height of the Canvas.
+    pub fn size(mut self, width: usize, height: usize) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+    /// Sets the background color of the Canvas.
+    pub fn color(mut self, color: [u8; 4]) -> Self {
+        self.color = Some(color);
+        self
+    }
+    /// Adds a [Rectangle][R] from a to c.
+    ///
+    /// [R]: Rect
+    pub fn rect(self, a: (f64, f64), c: (f64, f64), color: [u8; 4]) -> Self {
+        self.object_with(move |canvas| {
+            canvas.new_rect(a, c, color);
+        })
+    }
+    /// Adds an [Image] with its top left corner at origin.
+    pub fn image(self, origin: (f64, f64), image: Image) -> Self {
+        self.object_with(move |canvas| {
+            canvas.add_image(origin, image);
+        })
+    }
+    /// Sets the Font of the text added after it, with its size as a percentage of height of Canvas.
+    pub fn font(mut self, font: Font, size: f64) -> Self {
+        self.font = Some((font, size));
+        self
+    }
+    /// Adds a [TextBox] from a to c, in the Font set last, failing if no Font is set.
+    pub fn text(self, a: (f64, f64), c: (f64, f64), content: &str, color: [u8; 4]) -> Result<Self, Error> {
+        let Some((font, size)) = self.font.clone() else {
+            return invalid("set a font before adding text".to_string());
+        };
+        let content = content.to_string();
+        Ok(self.object_with(move |canvas| {
+            canvas.new_text(a, c, &content, font, size, color);
+        }))
+    }
+    /// Adds an [Object].
+    pub fn object(self, object: Box<dyn Object>) -> Self {
+        self.object_with(move |canvas| {
+            canvas.push(object);
+        })
+    }
+    /// Adds an Object once the size of the Canvas is known.
+    fn object_with(mut self, add: impl FnOnce(&mut Canvas) + 'static) -> Self {
+        self.objects.push(Box::new(add));
+        self
+    }
+    /// Builds the Canvas, failing if it has no size or its [Format] is unknown.
+    pub fn build(self) -> Result<Canvas, Error> {
+        let Some((width, height)) = self.size else {
+            return invalid("no image size".to_string());
+        };
+        let mut canvas = Canvas::new(&self.format, width, height)?;
+        if let Some(color) = self.color {
+            canvas.new_rect((0.0, 0.0), (100.0, 100.0), color);
+        }
+        for add in self.objects {
+            add(&mut canvas);
+        }
+        Ok(canvas)
+    }
+    /// Builds the Canvas and draws its Objects, returning the [Image].
+    pub fn render(self) -> Result<Image, Error> {
+        let mut canvas = self.build()?;
+        canvas.render();
+        Ok(canvas.fmt().image().clone())
+    }
+}
+
+/// Parses the output Images of the Manifest, filling in their omitted details.
+pub fn parse_outputs(manifest: &Manifest) -> Result<Vec<OutputInfo>, Error> {
+    let outputs = match &manifest.outputs {
+        Some(outputs) => {
+            if outputs.iter().any(|output| output.filename.is_none()) {
+                return Err(Error::Invalid("every entry of outputs needs a filename".to_string()));
+            }
+            outputs.clone()
+        }
+        None => vec![OutputInfo {
+            filename: manifest.output.clone(),
+            ..Default::default()
+        }],
+    };
+    outputs
+        .into_iter()
+        .map(|output| {
+            let Some([width, height]) = output.size.or(manifest.size) else {
+                return Err(Error::Invalid("no image size".to_string()));
+            };
+            let scale = output.scale.unwrap_or(1.0);
+            Ok(OutputInfo {
+                format: output.format.or_else(|| manifest.format.clone()),
+                size: Some([
+                    (width as f64 * scale).round() as usize,
+                    (height as f64 * scale).round() as usize,
+                ]),
+                scale: None,
+                options: output.options.or(&manifest.options),
+                ..output
+            })
+        })
+        .collect()
+}
+
+/// Parses the [Format] of an output Image.
+pub fn parse_format(output_info: &OutputInfo) -> Result<Box<dyn Format>, Error> {
+    let formats = Formats::default();
+    let name = match (&output_info.format, &output_info.filename) {
+        (Some(format), Some(filename)) if !formats.fits_extension(format, filename) => {
+            return invalid(format!("{} is not a {format} image", filename.display()))
+        }
+        (Some(format), _) => format.as_str(),
+        (None, Some(filename)) => formats.from_extension(filename).ok_or_else(|| {
+            Error::Invalid(format!("cannot infer image format of {}", filename.display()))
+        })?,
+        (None, None) => return Err(Error::Invalid("no image format".to_string())),
+    };
+    let Some([width, height]) = output_info.size else {
+        return Err(Error::Invalid("no image size".to_string()));
+    };
+    formats.get(name, width, height, &output_info.options)
+}
+
+/// Parses the background and the Objects of the Manifest onto a [Canvas] drawing on format.
+///
+/// Relative source paths of Objects are resolved from base_dir, and their
+/// files are loaded through cache.
+pub fn parse_canvas(
+    manifest: &Manifest,
+    format: Box<dyn Format>,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<Canvas, Error> {
+    let mut canvas = Canvas::with_format(format);
+    canvas.dpi = manifest.dpi.unwrap_or(72.0);
+    if let Some([width, _]) = manifest.size {
+        canvas.scale = canvas.width as f64 / width as f64;
+    }
+    // The background stays below Objects of negative z.
+    canvas.z = i32::MIN;
+    canvas.new_rect((0.0, 0.0), (100.0, 100.0), manifest.color);
+    canvas.z = 0;
+    parse_assets(manifest.assets.as_deref().unwrap_or_default(), &used_assets(&manifest.objects), base_dir, cache)?;
+    let parent = canvas.bounds();
+    for object_info in &manifest.objects {
+        parse_object(&mut canvas, object_info, &parent, base_dir, cache)?;
+    }
+    Ok(canvas)
+}
+
+/// Returns an [Error::Invalid] with message.
+fn invalid<T>(message: String) -> Result<T, Error> {
+    Err(Error::Invalid(message))
+}
+
+/// Parses the [Png] encoding options.
+pub fn parse_png(png: &mut Png, png_info: &PngInfo) -> Result<(), Error> {
+    if let Some(color_type) = &png_info.color_type {
+        png.set_color_type(match color_type.as_str() {
+            "rgb" => png::ColorType::Rgb,
+            "rgba" => png::ColorType::Rgba,
+            "gray" => png::ColorType::Grayscale,
+            "gray_alpha" => png::ColorType::GrayscaleAlpha,
+            _ => return invalid(format!("unknown png color type {color_type}")),
+        });
+    }
+    if let Some(bit_depth) = png_info.bit_depth {
+        png.set_bit_depth(match bit_depth {
+            8 => png::BitDepth::Eight,
+            16 => png::BitDepth::Sixteen,
+            _ => return invalid(format!("unsupported png bit depth {bit_depth}")),
+        });
+    }
+    Ok(())
+}
+
+/// Parses the [Jpg] encoding options.
+pub fn parse_jpg(jpg: &mut Jpg, jpg_info: &JpgInfo) -> Result<(), Error> {
+    if let Some(quality) = jpg_info.quality {
+        if !(1..=100).contains(&quality) {
+            return invalid(format!("jpg quality {quality} is not between 1 and 100"));
+        }
+        jpg.set_quality(quality);
+    }
+    if let Some(sampling) = &jpg_info.sampling {
+        jpg.set_sampling_factor(match sampling.as_str() {
+            "4:4:4" => jpeg_encoder::SamplingFactor::R_4_4_4,
+            "4:2:2" => jpeg_encoder::SamplingFactor::R_4_2_2,
+            "4:2:0" => jpeg_encoder::SamplingFactor::R_4_2_0,
+            _ => return invalid(format!("unknown jpg sampling factor {sampling}")),
+        });
+    }
+    if let Some(progressive) = jpg_info.progressive {
+        jpg.set_progressive(progressive);
+    }
+    if let Some(optimize_huffman) = jpg_info.optimize_huffman {
+        jpg.set_optimized_huffman_tables(optimize_huffman);
+    }
+    if let Some(restart_interval) = jpg_info.restart_interval {
+        jpg.set_restart_interval(restart_interval);
+    }
+    if let Some(matte) = jpg_info.matte {
+        jpg.set_matte(Pixel::from(&matte));
+    }
+    Ok(())
+}
+
+/// Parses the [Webp] encoding options.
+pub fn parse_webp(webp: &mut Webp, webp_info: &WebpInfo) -> Result<(), Error> {
+    match (webp_info.quality, webp_info.near_lossless) {
+        (Some(_), Some(_)) => {
+            return invalid("webp quality is lossy and near_lossless is lossless, set only one".to_string())
+        }
+        (Some(quality), None) if quality > 100 => {
+            return invalid(format!("webp quality {quality} is not between 0 and 100"))
+        }
+        (None, Some(quality)) if quality > 100 => {
+            return invalid(format!("webp near_lossless {quality} is not between 0 and 100"))
+        }
+        (Some(quality), None) => webp.set_quality(quality),
+        (None, Some(quality)) => webp.set_near_lossless(quality),
+        (None, None) => webp.set_lossless(),
+    }
+    Ok(())
+}
+
+/// Parses the [Tiff] encoding options.
+pub fn parse_tiff(tiff: &mut Tiff, tiff_info: &TiffInfo) -> Result<(), Error> {
+    if let Some(compression) = &tiff_info.compression {
+        tiff.set_compression(match compression.as_str() {
+            "none" => tiff::encoder::Compression::Uncompressed,
+            "lzw" => tiff::encoder::Compression::Lzw,
+            "deflate" => tiff::encoder::Compression::Deflate(Default::default()),
+            "packbits" => tiff::encoder::Compression::Packbits,
+            _ => return invalid(format!("unknown tiff compression {compression}")),
+        });
+    }
+    Ok(())
+}
+
+/// Parses the [Svg] encoding options.
+pub fn parse_svg(svg: &mut Svg, svg_info: &SvgInfo) -> Result<(), Error> {
+    if let Some(images) = &svg_info.images {
+        svg.set_link_images(match images.as_str() {
+            "embed" => false,
+            "link" => true,
+            _ => return invalid(format!("unknown svg images option {images}")),
+        });
+    }
+    Ok(())
+}
+
+/// Parses the [Pdf] encoding options.
+pub fn parse_pdf(pdf: &mut Pdf, pdf_info: &PdfInfo) -> Result<(), Error> {
+    match (pdf_info.dpi, pdf_info.page_size) {
+        (Some(_), Some(_)) => return invalid("a pdf has either a dpi or a page size".to_string()),
+        (Some(dpi), None) if dpi <= 0.0 => return invalid(format!("pdf dpi {dpi} is not positive")),
+        (Some(dpi), None) => pdf.set_dpi(dpi),
+        // There are 72 points in an inch of 25.4 millimetres.
+        (None, Some([width, height])) => pdf.set_page_size(width * 72.0 / 25.4, height * 72.0 / 25.4),
+        (None, None) => {}
+    }
+    if let Some(quality) = pdf_info.quality {
+        if !(1..=100).contains(&quality) {
+            return invalid(format!("pdf quality {quality} is not between 1 and 100"));
+        }
+        pdf.set_quality(quality);
+    }
+    Ok(())
+}
+
+/// Wraps the last [Object] of the Canvas in [Animated] if it has animated properties.
+///
+/// Keyframe origins place the bounds of the Object in parent as its origin does.
+pub fn parse_animated(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    bounds: &Bounds,
+) -> Result<(), Error> {
+    let keyframes = object_info.keyframes.as_deref().unwrap_or_default();
+    if keyframes.is_empty()
+        && object_info.opacity.is_none()
+        && object_info.scale.is_none()
+        && object_info.rotation.is_none()
+    {
+        return Ok(());
+    }
+    let mut animated = Animated::new(canvas.pop().unwrap());
+    animated.properties.opacity = object_info.opacity.unwrap_or(1.0);
+    animated.properties.scale = object_info.scale.unwrap_or(1.0);
+    animated.properties.rotation = object_info.rotation.unwrap_or(0.0);
+    for keyframe in keyframes {
+        let easing = match &keyframe.easing {
+            Some(name) => Easing::from_name(name).ok_or_else(|| Error::Invalid(format!("unknown easing {name}")))?,
+            None => Easing::default(),
+        };
+        if let Some((x, y)) = keyframe.origin {
+            let units = canvas.units(font_size(canvas, object_info));
+            let (placement, within) = place_in(canvas, object_info, parent, &units)?;
+            let placement = Placement {
+                origin: (x.to_px(parent.width, &units), y.to_px(parent.height, &units)),
+                right: None,
+                bottom: None,
+                ..placement
+            };
+            let placed = placement.place((bounds.width, bounds.height), &within);
+            let origin = (placed.x / canvas.width as f64 * 100.0, placed.y / canvas.height as f64 * 100.0);
+            animated.origin.insert(keyframe.frame, origin, easing);
+        }
+        if let Some(opacity) = keyframe.opacity {
+            animated.opacity.insert(keyframe.frame, opacity, easing);
+        }
+        if let Some(scale) = keyframe.scale {
+            animated.scale.insert(keyframe.frame, scale, easing);
+        }
+        if let Some(rotation) = keyframe.rotation {
+            animated.rotation.insert(keyframe.frame, rotation, easing);
+        }
+        if let Some(color) = keyframe.color {
+            animated.color.insert(keyframe.frame, color, easing);
+        }
+    }
+    canvas.push(Box::new(animated));
+    Ok(())
+}
+
+/// Loads the assets of the Manifest whose ids are used through cache, as Fonts or Images
+/// by the kind of their files.
+///
+/// Assets no Object uses are not loaded.
+pub fn parse_assets(assets_info: &[AssetInfo], used: &[&String], base_dir: &Path, cache: &mut AssetCache) -> Result<(), Error> {
+    for id in used {
+        let Some(asset_info) = assets_info.iter().find(|asset_info| asset_info.id == **id) else {
+            return invalid(format!("no asset {id}"));
+        };
+        let file = base_dir.join(&asset_info.src);
+        match FileFormat::from_file(&file).map_err(|err| Error::Io(file.clone(), err))?.kind() {
+            Kind::Font => {
+                load_font(&asset_info.src, base_dir, cache)?;
+            }
+            _ => {
+                cache.image(&file, None, None, || Decoders::default().decode(&file, None))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the [Placement] of an Object in parent.
+pub fn placement(object_info: &ObjectInfo, parent: &Bounds, units: &Units) -> Placement {
+    Placement {
+        origin: object_info.origin.map_or((0.0, 0.0), |(x, y)| {
+            (x.to_px(parent.width, units), y.to_px(parent.height, units))
+        }),
+        anchor: object_info.anchor.unwrap_or_default(),
+        parent_anchor: object_info.parent_anchor.unwrap_or_default(),
+        right: object_info.right.map(|right| right.to_px(parent.width, units)),
+        bottom: object_info.bottom.map(|bottom| bottom.to_px(parent.height, units)),
+    }
+}
+
+/// Returns the [Placement] of an Object in parent and the box it is placed in, which is
+/// that of the Object it is placed next to, if any, or else parent.
+pub fn place_in(
+    canvas: &Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    units: &Units,
+) -> Result<(Placement, Bounds), Error> {
+    let placement = placement(object_info, parent, units);
+    let sides = [
+        (&object_info.below, Anchor::BottomLeft, Anchor::TopLeft),
+        (&object_info.above, Anchor::TopLeft, Anchor::BottomLeft),
+        (&object_info.left_of, Anchor::TopLeft, Anchor::TopRight),
+        (&object_info.right_of, Anchor::TopRight, Anchor::TopLeft),
+    ];
+    let Some((id, parent_anchor, anchor)) = sides
+        .into_iter()
+        .find_map(|(id, parent_anchor, anchor)| Some((id.as_ref()?, parent_anchor, anchor)))
+    else {
+        return Ok((placement, *parent));
+    };
+    let placement = Placement {
+        anchor: object_info.anchor.unwrap_or(anchor),
+        parent_anchor: object_info.parent_anchor.unwrap_or(parent_anchor),
+        ..placement
+    };
+    Ok((placement, placed(canvas, id)?.bounds))
+}
+
+/// Returns the Object placed on the Canvas with id.
+fn placed(canvas: &Canvas, id: &str) -> Result<Placed, Error> {
+    canvas
+        .placed
+        .get(id)
+        .cloned()
+        .ok_or_else(|| Error::Invalid(format!("no object {id} placed before")))
+}
+
+/// Returns the font size of an Object in pixels, which is that of its text or the default.
+pub fn font_size(canvas: &Canvas, object_info: &ObjectInfo) -> f64 {
+    match object_info.spec {
+        ObjectSpec::Text { font_size: Some(size), .. } => {
+            size.to_px(canvas.height as f64, &canvas.units(canvas.default_font_size()))
+        }
+        _ => canvas.default_font_size(),
+    }
+}
+
+/// Returns the size of an Object in parent given by its size, or by the
+/// diagonal from its origin to its offset.
+fn given_size(object_info: &ObjectInfo, parent: &Bounds, units: &Units) -> Option<(f64, f64)> {
+    if let Some((width, height)) = object_info.size {
+        return Some((width.to_px(parent.width, units), height.to_px(parent.height, units)));
+    }
+    let (ox, oy) = object_info.origin.unwrap_or((Length::Percent(0.0), Length::Percent(0.0)));
+    let (cx, cy) = object_info.offset?;
+    Some((
+        (cx.to_px(parent.width, units) - ox.to_px(parent.width, units)).max(0.0),
+        (cy.to_px(parent.height, units) - oy.to_px(parent.height, units)).max(0.0),
+    ))
+}
+
+/// Returns a [Rectangle][R] filling bounds, its edges between pixels if they fall so.
+///
+/// [R]: Rect
+fn rect(bounds: &Bounds) -> Rect {
+    Rect::with_corners((bounds.x, bounds.y), (bounds.x + bounds.width, bounds.y + bounds.height))
+}
+
+/// Returns the [Flex] arrangement of the children of a layout in parent, or the
+/// default one for other Objects.
+pub fn flex(canvas: &Canvas, object_info: &ObjectInfo, parent: &Bounds) -> Flex {
+    let ObjectSpec::Layout { direction, gap, padding, justify, align, wrap, .. } = &object_info.spec else {
+        return Flex::default();
+    };
+    let units = canvas.units(font_size(canvas, object_info));
+    // Gaps and padding along either axis are percentages of the width of parent, as in CSS.
+    let length = |length: Option<Length>| length.map_or(0.0, |length| length.to_px(parent.width, &units));
+    Flex {
+        direction: direction.unwrap_or_default(),
+        gap: length(*gap),
+        padding: length(*padding),
+        justify: justify.unwrap_or_default(),
+        align: align.unwrap_or_default(),
+        wrap: *wrap,
+    }
+}
+
+/// Returns the bounds of a layout less its padding.
+fn content_bounds(bounds: &Bounds, flex: &Flex) -> Bounds {
+    Bounds::new(
+        bounds.x + flex.padding,
+        bounds.y + flex.padding,
+        (bounds.width - 2.0 * flex.padding).max(0.0),
+        (bounds.height - 2.0 * flex.padding).max(0.0),
+    )
+}
+
+/// Returns the size of an Object in parent in pixels.
+///
+/// Images are measured once loaded, and text and layouts without a size fit
+/// their content, wrapping at the width of parent.
+pub fn measure_object(
+    canvas: &Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(f64, f64), Error> {
+    let units = canvas.units(font_size(canvas, object_info));
+    if !matches!(object_info.spec, ObjectSpec::Image { .. }) {
+        if let Some(size) = given_size(object_info, parent, &units) {
+            return Ok(size);
+        }
     }
+    Ok(match &object_info.spec {
+        ObjectSpec::Image { .. } => {
+            let img = load_image(canvas, object_info, parent, base_dir, cache)?;
+            (img.width() as f64, img.height() as f64)
+        }
+        ObjectSpec::Text { font, content, .. } => {
+            let mut text = TextBox::new(content.clone(), (0, 0), (parent.width.max(0.0) as usize, 0));
+            text.set_font(load_font(font, base_dir, cache)?, font_size(canvas, object_info) as f32);
+            let (width, height) = text.measure();
+            ((width as f64).ceil(), (height as f64).ceil())
+        }
+        ObjectSpec::Layout { children, .. } => {
+            let flex = flex(canvas, object_info, parent);
+            let inner = content_bounds(parent, &flex);
+            let sizes: Vec<(f64, f64)> = children
+                .iter()
+                .map(|child| measure_object(canvas, child, &inner, base_dir, cache))
+                .collect::<Result<_, _>>()?;
+            let max = match flex.direction {
+                Direction::Row => parent.width,
+                Direction::Column => parent.height,
+            };
+            flex.measure(&sizes, max)
+        }
+        ObjectSpec::Rect { .. } => (0.0, 0.0),
+    })
+}
+
+/// Parses an Object placed in parent, returning its bounds.
+///
+/// The Object is measured, placed by its origin, anchors and edges, drawn and animated.
+pub fn parse_object(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<Bounds, Error> {
+    let size = measure_object(canvas, object_info, parent, base_dir, cache)?;
+    let units = canvas.units(font_size(canvas, object_info));
+    let (placement, within) = place_in(canvas, object_info, parent, &units)?;
+    let bounds = placement.place(size, &within);
+    draw_object(canvas, object_info, parent, &bounds, base_dir, cache)?;
+    Ok(bounds)
+}
+
+#[derive(Debug, Clone)]
+/// An Object of the Manifest placed on a [Canvas].
+pub struct Placed {
+    /// Details of the Object.
+    pub info: ObjectInfo,
+    /// Bounds of the parent of the Object.
+    pub parent: Bounds,
+    /// Bounds of the Object.
+    pub bounds: Bounds,
 }
 
-#[allow(unused_variables)]
-/// Parses assets in the Manifest. 
-pub fn parse_assets(assets_info: Vec<AssetInfo>) {
-    for asset_info in assets_info {
-        
+/// Draws an Object of parent in bounds at its z, animating it if it has animated
+/// properties and masking it if it has a mask.
+///
+/// Layouts draw their children but are not animated. Objects with an id are
+/// recorded as placed on the Canvas.
+pub fn draw_object(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    bounds: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let (z, start) = (canvas.z, canvas.shapes.len());
+    canvas.z = object_info.z.unwrap_or(z);
+    match &object_info.spec {
+        ObjectSpec::Rect { color } => parse_rect(canvas, color, bounds),
+        ObjectSpec::Image { .. } => parse_image(canvas, object_info, parent, bounds, base_dir, cache)?,
+        ObjectSpec::Text { .. } => parse_text(canvas, object_info, bounds, base_dir, cache)?,
+        ObjectSpec::Layout { .. } => parse_layout(canvas, object_info, parent, bounds, base_dir, cache)?,
+    }
+    if !matches!(object_info.spec, ObjectSpec::Layout { .. }) {
+        parse_animated(canvas, object_info, parent, bounds)?;
     }
+    if let Some(id) = &object_info.mask {
+        parse_mask(canvas, id, start, base_dir, cache)?;
+    }
+    if let Some(id) = &object_info.id {
+        let placed = Placed {
+            info: object_info.clone(),
+            parent: *parent,
+            bounds: *bounds,
+        };
+        canvas.placed.insert(id.clone(), placed);
+    }
+    canvas.z = z;
+    Ok(())
+}
+
+/// Wraps the Objects added to the Canvas from index start on in [Masked],
+/// drawn through the alpha of the Object placed with id.
+///
+/// The mask is drawn again ignoring its opacity, so a hidden Object can mask others.
+pub fn parse_mask(
+    canvas: &mut Canvas,
+    id: &str,
+    start: usize,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let placed = placed(canvas, id)?;
+    let mut masks = Canvas::with_format(Box::new(Png::new(canvas.width, canvas.height)));
+    masks.dpi = canvas.dpi;
+    masks.scale = canvas.scale;
+    masks.placed = canvas.placed.clone();
+    let mask_info = ObjectInfo {
+        opacity: None,
+        ..placed.info
+    };
+    draw_object(&mut masks, &mask_info, &placed.parent, &placed.bounds, base_dir, cache)?;
+    let objects = canvas.split_off(start);
+    canvas.push(Box::new(Masked::new(objects, masks.split_off(0))));
+    Ok(())
 }
 
-/// Parses a [Rectangle][R].
+/// Parses a [Rectangle][R] of color drawn in bounds.
 ///
 /// [R]: Rect
-pub fn parse_rect(canvas: &mut Canvas, object_info: ObjectInfo) {
-    canvas.new_rect(
-        object_info.origin.unwrap(),
-        object_info.offset.unwrap(),
-        object_info.color.unwrap(),
-    );
-}
-
-/// Parses an [Image].
-pub fn parse_image(canvas: &mut Canvas, object_info: ObjectInfo, mut file: PathBuf) {
-    let width = canvas.width;
-    let height = canvas.height;
-    file.pop();
-    file.push(object_info.src.unwrap());
-    //let path = file.to_str().unwrap();
-    let fmt = FileFormat::from_file(&file).unwrap();
-    let img = match fmt.kind() {
-        Kind::Image => Image::from_file(file),
-        _ => panic!("{} is not an Image file", file.to_str().unwrap()),
+pub fn parse_rect(canvas: &mut Canvas, color: &[u8; 4], bounds: &Bounds) {
+    let mut rect = rect(bounds);
+    rect.set_color(Pixel::from(color));
+    canvas.push(Box::new(rect));
+}
+
+/// Loads the [Image] of an Object in parent, resized as it asks.
+fn load_image(
+    canvas: &Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<Image, Error> {
+    let ObjectSpec::Image { src, frame, resize } = &object_info.spec else {
+        panic!("{} is not an image", object_info.spec.name());
     };
+    let units = canvas.units(font_size(canvas, object_info));
+    let file = base_dir.join(src);
+    let size = resize.map(|[width, height]| {
+        [
+            width.to_px(parent.width, &units).round() as usize,
+            height.to_px(parent.height, &units).round() as usize,
+        ]
+    });
+    cache.image(&file, *frame, size, || {
+        let fmt = FileFormat::from_file(&file).map_err(|err| Error::Io(file.clone(), err))?;
+        let mut img = match fmt.kind() {
+            Kind::Image => Decoders::default().decode(&file, *frame)?,
+            _ => return invalid(format!("{} is not an Image file", file.display())),
+        };
+        img.src = Some(src.clone());
+        if let Some(size) = size {
+            img.resize(size).map_err(|err| Error::Invalid(format!("{}: {err}", file.display())))?;
+        }
+        Ok(img)
+    })
+}
+
+/// Parses an [Image] of parent drawn in bounds.
+pub fn parse_image(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    bounds: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let mut img = load_image(canvas, object_info, parent, base_dir, cache)?;
+    img.origin = Point::from((bounds.x, bounds.y));
+    canvas.push(Box::new(img));
+    Ok(())
+}
 
-    let image = canvas.add_image(object_info.origin.unwrap(), img);
-    if object_info.resize.is_some() {
-        let scale = object_info.resize.unwrap();
-        let w2 = (width as f64 * (scale[0] / 100.0)) as usize;
-        let h2 = (height as f64 * (scale[1] / 100.0)) as usize;
-        image.resize([w2, h2]);
-        //println!("{}, {}", w2, h2);
+/// Loads the [Font] of the text of an Object from src.
+fn load_font(src: &Path, base_dir: &Path, cache: &mut AssetCache) -> Result<Font, Error> {
+    let file = base_dir.join(src);
+    cache.font(&file, || {
+        let data = read(&file).map_err(|err| Error::Io(file.clone(), err))?;
+        if !Font::is_font(&data) {
+            return invalid(format!("{} is not a font", file.display()));
+        }
+        Font::new(src.to_path_buf(), data)
+    })
+}
+
+/// Parses a [TextBox] drawn in bounds.
+///
+/// The font size defaults to 5 percent of height of Image and the color to black.
+pub fn parse_text(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    bounds: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let ObjectSpec::Text { font, content, color, .. } = &object_info.spec else {
+        panic!("{} is not text", object_info.spec.name());
+    };
+    let font = load_font(font, base_dir, cache)?;
+    let size = (bounds.width.round().max(0.0) as usize, bounds.height.round().max(0.0) as usize);
+    let mut text = TextBox::new(content.clone(), (0, 0), size);
+    // Text may start off the Canvas, and is clipped to it when drawn.
+    text.origin = Point::from((bounds.x.round(), bounds.y.round()));
+    text.set_font(font, font_size(canvas, object_info) as f32);
+    text.set_color(Pixel::from(&color.unwrap_or([0, 0, 0, 255])));
+    canvas.push(Box::new(text));
+    Ok(())
+}
+
+/// Parses a layout of parent drawn in bounds, with its color behind its children.
+///
+/// Children are measured in the layout less its padding and arranged by its [Flex].
+pub fn parse_layout(
+    canvas: &mut Canvas,
+    object_info: &ObjectInfo,
+    parent: &Bounds,
+    bounds: &Bounds,
+    base_dir: &Path,
+    cache: &mut AssetCache,
+) -> Result<(), Error> {
+    let ObjectSpec::Layout { children, color, .. } = &object_info.spec else {
+        panic!("{} is not a layout", object_info.spec.name());
+    };
+    if let Some(color) = color {
+        parse_rect(canvas, color, bounds);
     }
-}
\ No newline at end of file
+    let flex = flex(canvas, object_info, parent);
+    let inner = content_bounds(bounds, &flex);
+    let sizes: Vec<(f64, f64)> = children
+        .iter()
+        .map(|child| measure_object(canvas, child, &inner, base_dir, cache))
+        .collect::<Result<_, _>>()?;
+    for (child, child_bounds) in children.iter().zip(flex.arrange(&sizes, bounds)) {
+        draw_object(canvas, child, &inner, &child_bounds, base_dir, cache)?;
+    }
+    Ok(())
+}
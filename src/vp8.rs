@@ -0,0 +1,975 @@
+//! This module encodes Images as lossy VP8 key frames, the image data of lossy WebP.
+//!
+//! The encoder follows [RFC 6386](https://datatracker.ietf.org/doc/html/rfc6386). Every
+//! macroblock is predicted whole, with the 16x16 luma and 8x8 chroma mode that is closest to
+//! the Image, and its residue is transformed, quantized and coded with the default token
+//! probabilities.
+use crate::image::Pixel;
+use crate::parse::Error;
+
+/// Encodes opaque [Pixel]s as a VP8 key frame with a quality between 0 and 100.
+pub fn encode(pixels: &[Pixel], width: usize, height: usize, quality: u8) -> Result<Vec<u8>, Error> {
+    let mut frame = Frame::new(pixels, width, height);
+    let qi = (100 - quality.min(100) as usize) * 127 / 100;
+    let quant = Quant::new(qi);
+
+    let mut header = BoolEncoder::new();
+    header.put_literal(0, 1); // color space
+    header.put_literal(0, 1); // clamping type
+    header.put_literal(0, 1); // segmentation
+    header.put_literal(0, 1); // normal loop filter
+    header.put_literal(qi as u32 / 3, 6);
+    header.put_literal(0, 3); // sharpness
+    header.put_literal(0, 1); // loop filter adjustments
+    header.put_literal(0, 2); // one token partition
+    header.put_literal(qi as u32, 7);
+    header.put_literal(0, 5); // no quantizer deltas
+    header.put_literal(0, 1); // refresh entropy probabilities
+    for probs in COEFF_UPDATE_PROBS.iter().flatten().flatten().flatten() {
+        header.put(false, *probs);
+    }
+    header.put_literal(0, 1); // no macroblock skipping
+
+    let mut tokens = BoolEncoder::new();
+    let mut top = vec![[0u8; 9]; frame.mb_width];
+    for mby in 0..frame.mb_height {
+        let mut left = [0u8; 9];
+        for (mbx, top) in top.iter_mut().enumerate() {
+            let (luma, chroma) = frame.encode_macroblock(mbx, mby, &quant);
+            header.put_tree(LUMA_MODE_TREE[luma as usize], &YMODE_PROBS);
+            header.put_tree(CHROMA_MODE_TREE[chroma as usize], &UV_MODE_PROBS);
+            frame.write_tokens(&mut tokens, top, &mut left);
+        }
+    }
+
+    let header = header.finish();
+    let tokens = tokens.finish();
+    if header.len() >= 1 << 19 {
+        return Err(Error::Invalid(format!(
+            "cannot encode image: {width}x{height} pixels are too many for lossy webp"
+        )));
+    }
+    let tag = (header.len() as u32) << 5 | 1 << 4;
+    let mut bytes = Vec::with_capacity(10 + header.len() + tokens.len());
+    bytes.extend_from_slice(&tag.to_le_bytes()[..3]);
+    bytes.extend_from_slice(&[0x9d, 0x01, 0x2a]);
+    bytes.extend_from_slice(&(width as u16).to_le_bytes());
+    bytes.extend_from_slice(&(height as u16).to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&tokens);
+    Ok(bytes)
+}
+
+/// Prediction modes of whole macroblocks, in the order of the bitstream.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Dc,
+    V,
+    H,
+    Tm,
+}
+
+/// Bits of the key frame luma mode tree for each [Mode], read with [YMODE_PROBS].
+const LUMA_MODE_TREE: [&[(bool, usize)]; 4] = [
+    &[(true, 0), (false, 1), (false, 2)],
+    &[(true, 0), (false, 1), (true, 2)],
+    &[(true, 0), (true, 1), (false, 3)],
+    &[(true, 0), (true, 1), (true, 3)],
+];
+/// Bits of the key frame chroma mode tree for each [Mode], read with [UV_MODE_PROBS].
+const CHROMA_MODE_TREE: [&[(bool, usize)]; 4] = [
+    &[(false, 0)],
+    &[(true, 0), (false, 1)],
+    &[(true, 0), (true, 1), (false, 2)],
+    &[(true, 0), (true, 1), (true, 2)],
+];
+const YMODE_PROBS: [u8; 4] = [145, 156, 163, 128];
+const UV_MODE_PROBS: [u8; 3] = [142, 114, 183];
+
+/// Quantizer step sizes of the luma, second order luma and chroma blocks as (dc, ac).
+struct Quant {
+    y: (i32, i32),
+    y2: (i32, i32),
+    uv: (i32, i32),
+}
+impl Quant {
+    fn new(qi: usize) -> Quant {
+        let (dc, ac) = (DC_QUANT[qi] as i32, AC_QUANT[qi] as i32);
+        Quant {
+            y: (dc, ac),
+            y2: (dc * 2, (ac * 155 / 100).max(8)),
+            uv: (dc.min(132), ac),
+        }
+    }
+}
+
+/// Planes of a frame, padded to whole macroblocks, with the quantized coefficients of the
+/// last encoded macroblock.
+struct Frame {
+    mb_width: usize,
+    mb_height: usize,
+    /// Source Y, U and V planes.
+    source: [Vec<u8>; 3],
+    /// Reconstructed Y, U and V planes, as the decoder will see them.
+    recon: [Vec<u8>; 3],
+    /// Quantized coefficients in zigzag order: Y2, 16 Y, 4 U and 4 V blocks.
+    levels: [[i32; 16]; 25],
+}
+impl Frame {
+    fn new(pixels: &[Pixel], width: usize, height: usize) -> Frame {
+        let (mb_width, mb_height) = (width.div_ceil(16), height.div_ceil(16));
+        let pixel = |x: usize, y: usize| {
+            let pixel = &pixels[y.min(height - 1) * width + x.min(width - 1)];
+            [pixel[0] as i32, pixel[1] as i32, pixel[2] as i32]
+        };
+        let mut y_plane = vec![0; mb_width * mb_height * 256];
+        for y in 0..mb_height * 16 {
+            for x in 0..mb_width * 16 {
+                let [r, g, b] = pixel(x, y);
+                y_plane[y * mb_width * 16 + x] = rgb_to_y(r, g, b);
+            }
+        }
+        let mut u_plane = vec![0; mb_width * mb_height * 64];
+        let mut v_plane = vec![0; mb_width * mb_height * 64];
+        for y in 0..mb_height * 8 {
+            for x in 0..mb_width * 8 {
+                let mut sum = [0; 3];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let rgb = pixel(x * 2 + dx, y * 2 + dy);
+                    (0..3).for_each(|i| sum[i] += rgb[i]);
+                }
+                let [r, g, b] = sum;
+                u_plane[y * mb_width * 8 + x] = rgb_to_uv(-9719 * r - 19081 * g + 28800 * b);
+                v_plane[y * mb_width * 8 + x] = rgb_to_uv(28800 * r - 24116 * g - 4684 * b);
+            }
+        }
+        let recon = [y_plane.clone(), u_plane.clone(), v_plane.clone()];
+        Frame {
+            mb_width,
+            mb_height,
+            source: [y_plane, u_plane, v_plane],
+            recon,
+            levels: [[0; 16]; 25],
+        }
+    }
+
+    /// Chooses the modes of a macroblock, quantizes its residue and reconstructs it.
+    fn encode_macroblock(&mut self, mbx: usize, mby: usize, quant: &Quant) -> (Mode, Mode) {
+        let luma = self.best_mode(0, 16, mbx, mby);
+        let predicted = self.predict(0, 16, mbx, mby, luma);
+        let mut coeffs = [[0; 16]; 16];
+        for (i, coeffs) in coeffs.iter_mut().enumerate() {
+            let residue = self.residue(0, 16, mbx, mby, &predicted, i % 4, i / 4);
+            *coeffs = fdct(&residue);
+        }
+        let mut dc = [0; 16];
+        for i in 0..16 {
+            dc[i] = coeffs[i][0];
+        }
+        let mut y2 = fwht(&dc);
+        self.levels[0] = quantize(&mut y2, quant.y2);
+        iwht(&mut y2);
+        let mut reconstructed = [[0; 16]; 16];
+        for i in 0..16 {
+            coeffs[i][0] = 0;
+            self.levels[1 + i] = quantize(&mut coeffs[i], quant.y);
+            coeffs[i][0] = y2[i];
+            idct(&mut coeffs[i]);
+            reconstructed[i] = coeffs[i];
+        }
+        self.reconstruct(0, 16, mbx, mby, &predicted, &reconstructed);
+
+        let chroma = self.best_mode(1, 8, mbx, mby);
+        for plane in 1..3 {
+            let predicted = self.predict(plane, 8, mbx, mby, chroma);
+            let mut reconstructed = [[0; 16]; 4];
+            for (i, block) in reconstructed.iter_mut().enumerate() {
+                let residue = self.residue(plane, 8, mbx, mby, &predicted, i % 2, i / 2);
+                let mut coeffs = fdct(&residue);
+                self.levels[13 + plane * 4 + i] = quantize(&mut coeffs, quant.uv);
+                idct(&mut coeffs);
+                *block = coeffs;
+            }
+            self.reconstruct(plane, 8, mbx, mby, &predicted, &reconstructed);
+        }
+        (luma, chroma)
+    }
+
+    /// Writes the tokens of the last encoded macroblock, given whether the blocks above and
+    /// to the left of each block had non-zero coefficients.
+    fn write_tokens(&self, e: &mut BoolEncoder, top: &mut [u8; 9], left: &mut [u8; 9]) {
+        let nz = write_coefficients(e, &self.levels[0], 0, 1, (top[0] + left[0]) as usize);
+        top[0] = nz as u8;
+        left[0] = nz as u8;
+        for y in 0..4 {
+            for x in 0..4 {
+                let ctx = (top[1 + x] + left[1 + y]) as usize;
+                let nz = write_coefficients(e, &self.levels[1 + y * 4 + x], 1, 0, ctx);
+                top[1 + x] = nz as u8;
+                left[1 + y] = nz as u8;
+            }
+        }
+        for (j, blocks) in [(5, 17), (7, 21)] {
+            for y in 0..2 {
+                for x in 0..2 {
+                    let ctx = (top[j + x] + left[j + y]) as usize;
+                    let nz = write_coefficients(e, &self.levels[blocks + y * 2 + x], 0, 2, ctx);
+                    top[j + x] = nz as u8;
+                    left[j + y] = nz as u8;
+                }
+            }
+        }
+    }
+
+    /// Returns the mode whose prediction of a macroblock is closest to the source, using
+    /// only the edges that were already reconstructed.
+    fn best_mode(&self, plane: usize, size: usize, mbx: usize, mby: usize) -> Mode {
+        let mut modes = vec![Mode::Dc];
+        if mby > 0 {
+            modes.push(Mode::V);
+        }
+        if mbx > 0 {
+            modes.push(Mode::H);
+        }
+        if mbx > 0 && mby > 0 {
+            modes.push(Mode::Tm);
+        }
+        let planes = if plane == 0 { 0..1 } else { 1..3 };
+        let error = |mode: Mode| {
+            let mut error = 0;
+            for plane in planes.clone() {
+                let predicted = self.predict(plane, size, mbx, mby, mode);
+                let stride = self.mb_width * size;
+                for y in 0..size {
+                    for x in 0..size {
+                        let source = self.source[plane][(mby * size + y) * stride + mbx * size + x];
+                        error += (source as i32 - predicted[y * size + x] as i32).pow(2);
+                    }
+                }
+            }
+            error
+        };
+        modes.into_iter().min_by_key(|&mode| error(mode)).unwrap_or(Mode::Dc)
+    }
+
+    /// Predicts a macroblock of a plane from the reconstructed pixels above and to its left.
+    fn predict(&self, plane: usize, size: usize, mbx: usize, mby: usize, mode: Mode) -> Vec<u8> {
+        let stride = self.mb_width * size;
+        let recon = &self.recon[plane];
+        let (x0, y0) = (mbx * size, mby * size);
+        let above = |x: usize| recon[(y0 - 1) * stride + x0 + x] as i32;
+        let left = |y: usize| recon[(y0 + y) * stride + x0 - 1] as i32;
+        let mut predicted = vec![0; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                predicted[y * size + x] = match mode {
+                    Mode::Dc => 0,
+                    Mode::V => above(x) as u8,
+                    Mode::H => left(y) as u8,
+                    Mode::Tm => {
+                        let corner = recon[(y0 - 1) * stride + x0 - 1] as i32;
+                        (left(y) + above(x) - corner).clamp(0, 255) as u8
+                    }
+                };
+            }
+        }
+        if mode == Mode::Dc {
+            let shift = if size == 16 { 3 } else { 2 };
+            let (mut sum, mut shift) = (0, shift);
+            if mby > 0 {
+                sum += (0..size).map(above).sum::<i32>();
+                shift += 1;
+            }
+            if mbx > 0 {
+                sum += (0..size).map(left).sum::<i32>();
+                shift += 1;
+            }
+            let dc = if mbx == 0 && mby == 0 { 128 } else { (sum + (1 << (shift - 1))) >> shift };
+            predicted.fill(dc as u8);
+        }
+        predicted
+    }
+
+    /// Returns the difference between the source and the prediction of a 4x4 block.
+    #[allow(clippy::too_many_arguments)]
+    fn residue(&self, plane: usize, size: usize, mbx: usize, mby: usize, predicted: &[u8], bx: usize, by: usize) -> [i32; 16] {
+        let stride = self.mb_width * size;
+        let mut residue = [0; 16];
+        for y in 0..4 {
+            for x in 0..4 {
+                let (px, py) = (bx * 4 + x, by * 4 + y);
+                let source = self.source[plane][(mby * size + py) * stride + mbx * size + px];
+                residue[y * 4 + x] = source as i32 - predicted[py * size + px] as i32;
+            }
+        }
+        residue
+    }
+
+    /// Adds the reconstructed residue of the 4x4 blocks to the prediction of a macroblock.
+    fn reconstruct(&mut self, plane: usize, size: usize, mbx: usize, mby: usize, predicted: &[u8], blocks: &[[i32; 16]]) {
+        let stride = self.mb_width * size;
+        let per_row = size / 4;
+        for (i, block) in blocks.iter().enumerate() {
+            for y in 0..4 {
+                for x in 0..4 {
+                    let (px, py) = (i % per_row * 4 + x, i / per_row * 4 + y);
+                    let value = predicted[py * size + px] as i32 + block[y * 4 + x];
+                    self.recon[plane][(mby * size + py) * stride + mbx * size + px] = value.clamp(0, 255) as u8;
+                }
+            }
+        }
+    }
+}
+
+fn rgb_to_y(r: i32, g: i32, b: i32) -> u8 {
+    ((16839 * r + 33059 * g + 6420 * b + (16 << 16) + (1 << 15)) >> 16).clamp(0, 255) as u8
+}
+
+/// Converts a weighted sum of four pixels to a chroma value.
+fn rgb_to_uv(sum: i32) -> u8 {
+    ((sum + (128 << 18) + (1 << 17)) >> 18).clamp(0, 255) as u8
+}
+
+/// Quantizes coefficients in place to their dequantized values, returning the quantized
+/// levels in zigzag order.
+fn quantize(coeffs: &mut [i32; 16], (dc, ac): (i32, i32)) -> [i32; 16] {
+    let mut levels = [0; 16];
+    for (i, &position) in ZIGZAG.iter().enumerate() {
+        let q = if position == 0 { dc } else { ac };
+        // Rounding AC coefficients down a little drops noise that costs many bits.
+        let bias = if position == 0 { q / 2 } else { q * 7 / 16 };
+        let level = ((coeffs[position].abs() + bias) / q).min(2048);
+        levels[i] = level * coeffs[position].signum();
+        coeffs[position] = levels[i] * q;
+    }
+    levels
+}
+
+/// Writes the tokens of a block, returning whether it has non-zero coefficients.
+fn write_coefficients(e: &mut BoolEncoder, levels: &[i32; 16], first: usize, plane: usize, ctx: usize) -> bool {
+    let probs = &COEFF_PROBS[plane];
+    let Some(last) = (first..16).rev().find(|&i| levels[i] != 0) else {
+        e.put(false, probs[COEFF_BANDS[first]][ctx][0]);
+        return false;
+    };
+    let (mut ctx, mut after_zero) = (ctx, false);
+    for i in first..=last {
+        let p = &probs[COEFF_BANDS[i]][ctx];
+        if !after_zero {
+            e.put(true, p[0]);
+        }
+        let value = levels[i].unsigned_abs();
+        e.put(value != 0, p[1]);
+        if value == 0 {
+            (ctx, after_zero) = (0, true);
+            continue;
+        }
+        e.put(value > 1, p[2]);
+        if value > 1 {
+            e.put(value > 4, p[3]);
+            if value <= 4 {
+                e.put(value > 2, p[4]);
+                if value > 2 {
+                    e.put(value == 4, p[5]);
+                }
+            } else {
+                e.put(value > 10, p[6]);
+                let category = if value <= 10 {
+                    e.put(value > 6, p[7]);
+                    (value > 6) as usize
+                } else {
+                    e.put(value > 34, p[8]);
+                    if value <= 34 {
+                        e.put(value > 18, p[9]);
+                        2 + (value > 18) as usize
+                    } else {
+                        e.put(value > 66, p[10]);
+                        4 + (value > 66) as usize
+                    }
+                };
+                let extra = value - DCT_CAT_BASE[category];
+                let probs = PROB_DCT_CAT[category].iter().take_while(|&&p| p != 0);
+                let bits = probs.clone().count();
+                for (bit, &p) in probs.enumerate() {
+                    e.put(extra >> (bits - 1 - bit) & 1 != 0, p);
+                }
+            }
+        }
+        e.put(levels[i] < 0, 128);
+        (ctx, after_zero) = (if value == 1 { 1 } else { 2 }, false);
+    }
+    if last < 15 {
+        e.put(false, probs[COEFF_BANDS[last + 1]][ctx][0]);
+    }
+    true
+}
+
+/// Forward DCT of a 4x4 block, the inverse of [idct].
+fn fdct(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0; 16];
+    for i in 0..4 {
+        let row = &input[i * 4..i * 4 + 4];
+        let a1 = (row[0] + row[3]) * 8;
+        let b1 = (row[1] + row[2]) * 8;
+        let c1 = (row[1] - row[2]) * 8;
+        let d1 = (row[0] - row[3]) * 8;
+        temp[i * 4] = a1 + b1;
+        temp[i * 4 + 2] = a1 - b1;
+        temp[i * 4 + 1] = (c1 * 2217 + d1 * 5352 + 14500) >> 12;
+        temp[i * 4 + 3] = (d1 * 2217 - c1 * 5352 + 7500) >> 12;
+    }
+    let mut output = [0; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[12 + i];
+        let b1 = temp[4 + i] + temp[8 + i];
+        let c1 = temp[4 + i] - temp[8 + i];
+        let d1 = temp[i] - temp[12 + i];
+        output[i] = (a1 + b1 + 7) >> 4;
+        output[8 + i] = (a1 - b1 + 7) >> 4;
+        output[4 + i] = ((c1 * 2217 + d1 * 5352 + 12000) >> 16) + (d1 != 0) as i32;
+        output[12 + i] = (d1 * 2217 - c1 * 5352 + 51000) >> 16;
+    }
+    output
+}
+
+/// Inverse DCT of a 4x4 block, exactly as the decoder computes it.
+fn idct(block: &mut [i32; 16]) {
+    const C1: i64 = 20091;
+    const C2: i64 = 35468;
+    let mut temp = [0i64; 16];
+    for i in 0..4 {
+        let [b0, b1, b2, b3] = [0, 4, 8, 12].map(|j| block[j + i] as i64);
+        let (a, b) = (b0 + b2, b0 - b2);
+        let c = ((b1 * C2) >> 16) - (b3 + ((b3 * C1) >> 16));
+        let d = (b1 + ((b1 * C1) >> 16)) + ((b3 * C2) >> 16);
+        temp[i] = a + d;
+        temp[4 + i] = b + c;
+        temp[8 + i] = b - c;
+        temp[12 + i] = a - d;
+    }
+    for i in 0..4 {
+        let [t0, t1, t2, t3] = [0, 1, 2, 3].map(|j| temp[i * 4 + j]);
+        let (a, b) = (t0 + t2, t0 - t2);
+        let c = ((t1 * C2) >> 16) - (t3 + ((t3 * C1) >> 16));
+        let d = (t1 + ((t1 * C1) >> 16)) + ((t3 * C2) >> 16);
+        block[i * 4] = ((a + d + 4) >> 3) as i32;
+        block[i * 4 + 1] = ((b + c + 4) >> 3) as i32;
+        block[i * 4 + 2] = ((b - c + 4) >> 3) as i32;
+        block[i * 4 + 3] = ((a - d + 4) >> 3) as i32;
+    }
+}
+
+/// Forward Walsh-Hadamard transform of the luma DC coefficients, the inverse of [iwht].
+fn fwht(input: &[i32; 16]) -> [i32; 16] {
+    let mut temp = [0; 16];
+    for i in 0..4 {
+        let row = &input[i * 4..i * 4 + 4];
+        let a1 = (row[0] + row[2]) * 4;
+        let d1 = (row[1] + row[3]) * 4;
+        let c1 = (row[1] - row[3]) * 4;
+        let b1 = (row[0] - row[2]) * 4;
+        temp[i * 4] = a1 + d1 + (a1 != 0) as i32;
+        temp[i * 4 + 1] = b1 + c1;
+        temp[i * 4 + 2] = b1 - c1;
+        temp[i * 4 + 3] = a1 - d1;
+    }
+    let mut output = [0; 16];
+    for i in 0..4 {
+        let a1 = temp[i] + temp[8 + i];
+        let d1 = temp[4 + i] + temp[12 + i];
+        let c1 = temp[4 + i] - temp[12 + i];
+        let b1 = temp[i] - temp[8 + i];
+        let values = [a1 + d1, b1 + c1, b1 - c1, a1 - d1];
+        for (j, value) in values.into_iter().enumerate() {
+            output[j * 4 + i] = (value + (value < 0) as i32 + 3) >> 3;
+        }
+    }
+    output
+}
+
+/// Inverse Walsh-Hadamard transform of the luma DC coefficients, exactly as the decoder
+/// computes it.
+fn iwht(block: &mut [i32; 16]) {
+    for i in 0..4 {
+        let a1 = block[i] + block[12 + i];
+        let b1 = block[4 + i] + block[8 + i];
+        let c1 = block[4 + i] - block[8 + i];
+        let d1 = block[i] - block[12 + i];
+        block[i] = a1 + b1;
+        block[4 + i] = c1 + d1;
+        block[8 + i] = a1 - b1;
+        block[12 + i] = d1 - c1;
+    }
+    for row in block.chunks_exact_mut(4) {
+        let a1 = row[0] + row[3];
+        let b1 = row[1] + row[2];
+        let c1 = row[1] - row[2];
+        let d1 = row[0] - row[3];
+        row[0] = (a1 + b1 + 3) >> 3;
+        row[1] = (c1 + d1 + 3) >> 3;
+        row[2] = (a1 - b1 + 3) >> 3;
+        row[3] = (d1 - c1 + 3) >> 3;
+    }
+}
+
+/// Boolean entropy encoder of RFC 6386, section 7.
+struct BoolEncoder {
+    output: Vec<u8>,
+    range: u32,
+    bottom: u32,
+    bit_count: i32,
+}
+impl BoolEncoder {
+    fn new() -> BoolEncoder {
+        BoolEncoder { output: Vec::new(), range: 255, bottom: 0, bit_count: 24 }
+    }
+
+    /// Writes a bit that is zero with a probability of prob / 256.
+    fn put(&mut self, bit: bool, prob: u8) {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        if bit {
+            self.bottom = self.bottom.wrapping_add(split);
+            self.range -= split;
+        } else {
+            self.range = split;
+        }
+        while self.range < 128 {
+            self.range <<= 1;
+            if self.bottom & (1 << 31) != 0 {
+                // Carry into the bytes that were already written.
+                for byte in self.output.iter_mut().rev() {
+                    *byte = byte.wrapping_add(1);
+                    if *byte != 0 {
+                        break;
+                    }
+                }
+            }
+            self.bottom <<= 1;
+            self.bit_count -= 1;
+            if self.bit_count == 0 {
+                self.output.push((self.bottom >> 24) as u8);
+                self.bottom &= (1 << 24) - 1;
+                self.bit_count = 8;
+            }
+        }
+    }
+
+    /// Writes the low bits of value, most significant first.
+    fn put_literal(&mut self, value: u32, bits: u32) {
+        for bit in (0..bits).rev() {
+            self.put(value >> bit & 1 != 0, 128);
+        }
+    }
+
+    /// Writes the bits of a tree leaf, each with the probability at its index.
+    fn put_tree(&mut self, bits: &[(bool, usize)], probs: &[u8]) {
+        for &(bit, i) in bits {
+            self.put(bit, probs[i]);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for _ in 0..32 {
+            self.put(false, 128);
+        }
+        self.output
+    }
+}
+
+const COEFF_BANDS: [usize; 16] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7];
+const ZIGZAG: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+const DCT_CAT_BASE: [u32; 6] = [5, 7, 11, 19, 35, 67];
+const PROB_DCT_CAT: [[u8; 12]; 6] = [
+    [159, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [165, 145, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [173, 148, 140, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [176, 155, 140, 135, 0, 0, 0, 0, 0, 0, 0, 0],
+    [180, 157, 141, 134, 130, 0, 0, 0, 0, 0, 0, 0],
+    [254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129, 0],
+];
+
+#[rustfmt::skip]
+const DC_QUANT: [i16; 128] = [
+      4,   5,   6,   7,   8,   9,  10,  10,
+     11,  12,  13,  14,  15,  16,  17,  17,
+     18,  19,  20,  20,  21,  21,  22,  22,
+     23,  23,  24,  25,  25,  26,  27,  28,
+     29,  30,  31,  32,  33,  34,  35,  36,
+     37,  37,  38,  39,  40,  41,  42,  43,
+     44,  45,  46,  46,  47,  48,  49,  50,
+     51,  52,  53,  54,  55,  56,  57,  58,
+     59,  60,  61,  62,  63,  64,  65,  66,
+     67,  68,  69,  70,  71,  72,  73,  74,
+     75,  76,  76,  77,  78,  79,  80,  81,
+     82,  83,  84,  85,  86,  87,  88,  89,
+     91,  93,  95,  96,  98, 100, 101, 102,
+    104, 106, 108, 110, 112, 114, 116, 118,
+    122, 124, 126, 128, 130, 132, 134, 136,
+    138, 140, 143, 145, 148, 151, 154, 157,
+];
+
+#[rustfmt::skip]
+const AC_QUANT: [i16; 128] = [
+      4,   5,   6,   7,   8,   9,  10,  11,
+     12,  13,  14,  15,  16,  17,  18,  19,
+     20,  21,  22,  23,  24,  25,  26,  27,
+     28,  29,  30,  31,  32,  33,  34,  35,
+     36,  37,  38,  39,  40,  41,  42,  43,
+     44,  45,  46,  47,  48,  49,  50,  51,
+     52,  53,  54,  55,  56,  57,  58,  60,
+     62,  64,  66,  68,  70,  72,  74,  76,
+     78,  80,  82,  84,  86,  88,  90,  92,
+     94,  96,  98, 100, 102, 104, 106, 108,
+    110, 112, 114, 116, 119, 122, 125, 128,
+    131, 134, 137, 140, 143, 146, 149, 152,
+    155, 158, 161, 164, 167, 170, 173, 177,
+    181, 185, 189, 193, 197, 201, 205, 209,
+    213, 217, 221, 225, 229, 234, 239, 245,
+    249, 254, 259, 264, 269, 274, 279, 284,
+];
+
+/// Probabilities that a token probability is updated in the frame header, which never happens.
+const COEFF_UPDATE_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [176, 246, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 241, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 244, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 246, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [239, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 254, 255, 255, 255, 255, 255, 255],
+            [250, 255, 254, 255, 254, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [217, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [225, 252, 241, 253, 255, 255, 254, 255, 255, 255, 255],
+            [234, 250, 241, 250, 253, 255, 253, 254, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [223, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [238, 253, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 248, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [247, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [186, 251, 250, 255, 255, 255, 255, 255, 255, 255, 255],
+            [234, 251, 244, 254, 255, 255, 255, 255, 255, 255, 255],
+            [251, 251, 243, 253, 254, 255, 254, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [236, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [251, 253, 253, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+    [
+        [
+            [248, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 254, 252, 254, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 249, 253, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [246, 253, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 254, 251, 254, 254, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 254, 252, 255, 255, 255, 255, 255, 255, 255, 255],
+            [248, 254, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 255, 254, 254, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [245, 251, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [253, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 251, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [252, 253, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 254, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 252, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [249, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 254, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 253, 255, 255, 255, 255, 255, 255, 255, 255],
+            [250, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+        [
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [254, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+            [255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255],
+        ],
+    ],
+];
+
+// Section 13.5
+
+/// Default probabilities of the token tree, by plane, band and context.
+const COEFF_PROBS: [[[[u8; 11]; 3]; 8]; 4] = [
+    [
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [253, 136, 254, 255, 228, 219, 128, 128, 128, 128, 128],
+            [189, 129, 242, 255, 227, 213, 255, 219, 128, 128, 128],
+            [106, 126, 227, 252, 214, 209, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 98, 248, 255, 236, 226, 255, 255, 128, 128, 128],
+            [181, 133, 238, 254, 221, 234, 255, 154, 128, 128, 128],
+            [78, 134, 202, 247, 198, 180, 255, 219, 128, 128, 128],
+        ],
+        [
+            [1, 185, 249, 255, 243, 255, 128, 128, 128, 128, 128],
+            [184, 150, 247, 255, 236, 224, 128, 128, 128, 128, 128],
+            [77, 110, 216, 255, 236, 230, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 101, 251, 255, 241, 255, 128, 128, 128, 128, 128],
+            [170, 139, 241, 252, 236, 209, 255, 255, 128, 128, 128],
+            [37, 116, 196, 243, 228, 255, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 204, 254, 255, 245, 255, 128, 128, 128, 128, 128],
+            [207, 160, 250, 255, 238, 128, 128, 128, 128, 128, 128],
+            [102, 103, 231, 255, 211, 171, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 152, 252, 255, 240, 255, 128, 128, 128, 128, 128],
+            [177, 135, 243, 255, 234, 225, 128, 128, 128, 128, 128],
+            [80, 129, 211, 255, 194, 224, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [246, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [255, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [198, 35, 237, 223, 193, 187, 162, 160, 145, 155, 62],
+            [131, 45, 198, 221, 172, 176, 220, 157, 252, 221, 1],
+            [68, 47, 146, 208, 149, 167, 221, 162, 255, 223, 128],
+        ],
+        [
+            [1, 149, 241, 255, 221, 224, 255, 255, 128, 128, 128],
+            [184, 141, 234, 253, 222, 220, 255, 199, 128, 128, 128],
+            [81, 99, 181, 242, 176, 190, 249, 202, 255, 255, 128],
+        ],
+        [
+            [1, 129, 232, 253, 214, 197, 242, 196, 255, 255, 128],
+            [99, 121, 210, 250, 201, 198, 255, 202, 128, 128, 128],
+            [23, 91, 163, 242, 170, 187, 247, 210, 255, 255, 128],
+        ],
+        [
+            [1, 200, 246, 255, 234, 255, 128, 128, 128, 128, 128],
+            [109, 178, 241, 255, 231, 245, 255, 255, 128, 128, 128],
+            [44, 130, 201, 253, 205, 192, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 132, 239, 251, 219, 209, 255, 165, 128, 128, 128],
+            [94, 136, 225, 251, 218, 190, 255, 255, 128, 128, 128],
+            [22, 100, 174, 245, 186, 161, 255, 199, 128, 128, 128],
+        ],
+        [
+            [1, 182, 249, 255, 232, 235, 128, 128, 128, 128, 128],
+            [124, 143, 241, 255, 227, 234, 128, 128, 128, 128, 128],
+            [35, 77, 181, 251, 193, 211, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 157, 247, 255, 236, 231, 255, 255, 128, 128, 128],
+            [121, 141, 235, 255, 225, 227, 255, 255, 128, 128, 128],
+            [45, 99, 188, 251, 195, 217, 255, 224, 128, 128, 128],
+        ],
+        [
+            [1, 1, 251, 255, 213, 255, 128, 128, 128, 128, 128],
+            [203, 1, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [137, 1, 177, 255, 224, 255, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [253, 9, 248, 251, 207, 208, 255, 192, 128, 128, 128],
+            [175, 13, 224, 243, 193, 185, 249, 198, 255, 255, 128],
+            [73, 17, 171, 221, 161, 179, 236, 167, 255, 234, 128],
+        ],
+        [
+            [1, 95, 247, 253, 212, 183, 255, 255, 128, 128, 128],
+            [239, 90, 244, 250, 211, 209, 255, 255, 128, 128, 128],
+            [155, 77, 195, 248, 188, 195, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 24, 239, 251, 218, 219, 255, 205, 128, 128, 128],
+            [201, 51, 219, 255, 196, 186, 128, 128, 128, 128, 128],
+            [69, 46, 190, 239, 201, 218, 255, 228, 128, 128, 128],
+        ],
+        [
+            [1, 191, 251, 255, 255, 128, 128, 128, 128, 128, 128],
+            [223, 165, 249, 255, 213, 255, 128, 128, 128, 128, 128],
+            [141, 124, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 16, 248, 255, 255, 128, 128, 128, 128, 128, 128],
+            [190, 36, 230, 255, 236, 255, 128, 128, 128, 128, 128],
+            [149, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 226, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [247, 192, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [240, 128, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [1, 134, 252, 255, 255, 128, 128, 128, 128, 128, 128],
+            [213, 62, 250, 255, 255, 128, 128, 128, 128, 128, 128],
+            [55, 93, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+        [
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+            [128, 128, 128, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+    [
+        [
+            [202, 24, 213, 235, 186, 191, 220, 160, 240, 175, 255],
+            [126, 38, 182, 232, 169, 184, 228, 174, 255, 187, 128],
+            [61, 46, 138, 219, 151, 178, 240, 170, 255, 216, 128],
+        ],
+        [
+            [1, 112, 230, 250, 199, 191, 247, 159, 255, 255, 128],
+            [166, 109, 228, 252, 211, 215, 255, 174, 128, 128, 128],
+            [39, 77, 162, 232, 172, 180, 245, 178, 255, 255, 128],
+        ],
+        [
+            [1, 52, 220, 246, 198, 199, 249, 220, 255, 255, 128],
+            [124, 74, 191, 243, 183, 193, 250, 221, 255, 255, 128],
+            [24, 71, 130, 219, 154, 170, 243, 182, 255, 255, 128],
+        ],
+        [
+            [1, 182, 225, 249, 219, 240, 255, 224, 128, 128, 128],
+            [149, 150, 226, 252, 216, 205, 255, 171, 128, 128, 128],
+            [28, 108, 170, 242, 183, 194, 254, 223, 255, 255, 128],
+        ],
+        [
+            [1, 81, 230, 252, 204, 203, 255, 192, 128, 128, 128],
+            [123, 102, 209, 247, 188, 196, 255, 233, 128, 128, 128],
+            [20, 95, 153, 243, 164, 173, 255, 203, 128, 128, 128],
+        ],
+        [
+            [1, 222, 248, 255, 216, 213, 128, 128, 128, 128, 128],
+            [168, 175, 246, 252, 235, 205, 255, 255, 128, 128, 128],
+            [47, 116, 215, 255, 211, 212, 255, 255, 128, 128, 128],
+        ],
+        [
+            [1, 121, 236, 253, 212, 214, 255, 255, 128, 128, 128],
+            [141, 84, 213, 252, 201, 202, 255, 219, 128, 128, 128],
+            [42, 80, 160, 240, 162, 185, 255, 205, 128, 128, 128],
+        ],
+        [
+            [1, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [244, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+            [238, 1, 255, 128, 128, 128, 128, 128, 128, 128, 128],
+        ],
+    ],
+];